@@ -0,0 +1,291 @@
+# writes the small grids the vdb.rs tests load. The same float grid three times:
+#   tiny.vdb       openvdb, zip + active mask compression, full floats
+#   tiny_blosc.vdb openvdb, blosc (lz4, byte shuffled) + active mask compression, half floats
+#   tiny.nvdb      nanovdb 32, zip codec
+# each file has an empty grid called "other" in front of the "density" one so the tests also check that it gets skipped
+# the lz4 blocks come from the lz4 command line tool
+import struct, subprocess, zlib
+
+BG = 0.0
+# index space to world space: scale 0.5, then move by (1, 2, 3)
+SCALE = 0.5
+TRANSLATE = (1.0, 2.0, 3.0)
+
+def value(i, j, k):
+    return 1.0 + 0.01 * i + 0.02 * j + 0.03 * k
+
+# active voxels over 2 leaves, one of them at negative x
+ACTIVE = {(i, j, k) for i in range(-3, 5) for j in range(0, 3) for k in range(0, 2)}
+# (log2 of the tile size, origin, value, active)
+TILES = [
+    (3, (16, 0, 0), 2.5, True),
+    (7, (128, 0, 0), 0.75, False),
+    (12, (4096, 0, 0), 0.5, False),
+]
+
+def floor_to(c, log2):
+    return tuple(x & ~((1 << log2) - 1) for x in c)
+
+def child_index(origin, c, log2dim, child_log2):
+    off = [(c[a] - origin[a]) >> child_log2 for a in range(3)]
+    return (off[0] << (2 * log2dim)) | (off[1] << log2dim) | off[2]
+
+leaves = {}
+for c in ACTIVE:
+    leaves.setdefault(floor_to(c, 3), set()).add(c)
+lowers = {}
+for lf in leaves:
+    lowers.setdefault(floor_to(lf, 7), set()).add(lf)
+uppers = {}
+for lw in lowers:
+    uppers.setdefault(floor_to(lw, 12), set()).add(lw)
+for log2, origin, _, _ in TILES:
+    if log2 <= 7:
+        uppers.setdefault(floor_to(origin, 12), set())
+    if log2 == 3:
+        lowers.setdefault(floor_to(origin, 7), set())
+        uppers[floor_to(origin, 12)].add(floor_to(origin, 7))
+
+def leaf_values(lf):
+    return [value(lf[0] + (n >> 6), lf[1] + ((n >> 3) & 7), lf[2] + (n & 7))
+            if (lf[0] + (n >> 6), lf[1] + ((n >> 3) & 7), lf[2] + (n & 7)) in ACTIVE else BG
+            for n in range(512)]
+
+def leaf_active(lf):
+    return {child_index(lf, c, 3, 0) for c in leaves[lf]}
+
+# ---------------- compression
+
+def lz4_block(data):
+    out = subprocess.run(['lz4', '-9', '-c', '-B4', '--no-frame-crc'], input=data,
+                         capture_output=True, check=True).stdout
+    assert out[:4] == b'\x04\x22\x4d\x18'
+    pos = 7 + (8 if out[4] & 8 else 0)
+    size = struct.unpack('<I', out[pos:pos + 4])[0]
+    if size & 0x80000000:
+        return None
+    return out[pos + 4:pos + 4 + size]
+
+def blosc_compress(data, typesize, blocksize):
+    nbytes = len(data)
+    nblocks = (nbytes + blocksize - 1) // blocksize
+    flags = 0x1 | (1 << 5)
+    body = bytearray()
+    starts = []
+    header_len = 16 + 4 * nblocks
+    for b in range(nblocks):
+        block = data[b * blocksize:(b + 1) * blocksize]
+        size = len(block)
+        n = size // typesize
+        shuffled = bytearray(size)
+        for i in range(n):
+            for t in range(typesize):
+                shuffled[t * n + i] = block[i * typesize + t]
+        starts.append(header_len + len(body))
+        leftover = size != blocksize
+        splits = typesize if not leftover and n >= 128 else 1
+        part_size = size // splits
+        for s in range(splits):
+            part = bytes(shuffled[s * part_size:(s + 1) * part_size])
+            c = lz4_block(part)
+            if c is None or len(c) >= len(part):
+                c = part
+            body += struct.pack('<i', len(c)) + c
+    head = bytes([2, 1, flags, typesize]) + struct.pack('<III', nbytes, blocksize, header_len + len(body))
+    return head + b''.join(struct.pack('<I', s) for s in starts) + bytes(body)
+
+# ---------------- openvdb
+
+class W:
+    def __init__(self):
+        self.b = bytearray()
+    def pack(self, fmt, *v):
+        self.b += struct.pack('<' + fmt, *v)
+    def s(self, x):
+        x = x.encode()
+        self.pack('I', len(x))
+        self.b += x
+    def mask(self, bits, n):
+        words = [0] * (n // 64)
+        for i in bits:
+            words[i >> 6] |= 1 << (i & 63)
+        self.pack('%dQ' % len(words), *words)
+
+def write_values(w, vals, active, blosc, half):
+    # metadata 0: every inactive value is the background, so only the active ones are saved
+    if all(vals[i] == BG for i in range(len(vals)) if i not in active):
+        w.pack('B', 0)
+        vals = [vals[i] for i in range(len(vals)) if i in active]
+    else:
+        w.pack('B', 6)
+    data = b''.join(struct.pack('<e' if half else '<f', v) for v in vals)
+    if blosc:
+        if len(data) < 64:
+            w.pack('q', -len(data))
+            w.b += data
+        else:
+            c = blosc_compress(data, 2 if half else 4, 384 if half else 512)
+            w.pack('q', len(c))
+            w.b += c
+    else:
+        c = zlib.compress(data)
+        if len(c) < len(data):
+            w.pack('q', len(c))
+            w.b += c
+        else:
+            w.pack('q', -len(data))
+            w.b += data
+
+def openvdb_grid(blosc, half, empty):
+    w = W()
+    w.pack('I', (4 if blosc else 1) | 2)
+    w.pack('I', 1)
+    w.s('class'); w.s('string'); w.s('fog volume')
+    w.s('ScaleTranslateMap')
+    w.pack('3d', *TRANSLATE)
+    w.pack('3d', SCALE, SCALE, SCALE)
+    w.pack('12d', *([0.0] * 12))
+    w.pack('i', 1)
+    w.pack('f', BG)
+    if empty:
+        w.pack('II', 0, 0)
+        return bytes(w.b)
+    root_tiles = [t for t in TILES if t[0] == 12]
+    w.pack('II', len(root_tiles), len(uppers))
+    for _, o, v, a in root_tiles:
+        w.pack('3ifB', *o, v, 1 if a else 0)
+    order = []
+    for uo in sorted(uppers):
+        w.pack('3i', *uo)
+        kids = {child_index(uo, lw, 5, 7): lw for lw in uppers[uo]}
+        tiles = {child_index(uo, o, 5, 7): (v, a) for l, o, v, a in TILES if l == 7 and floor_to(o, 12) == uo}
+        w.mask(kids, 32768)
+        w.mask([i for i, (v, a) in tiles.items() if a], 32768)
+        vals = [tiles[i][0] if i in tiles else BG for i in range(32768)]
+        write_values(w, vals, {i for i, (v, a) in tiles.items() if a}, blosc, half)
+        for ci in sorted(kids):
+            lw = kids[ci]
+            lkids = {child_index(lw, lf, 4, 3): lf for lf in lowers[lw]}
+            ltiles = {child_index(lw, o, 4, 3): (v, a) for l, o, v, a in TILES if l == 3 and floor_to(o, 7) == lw}
+            w.mask(lkids, 4096)
+            w.mask([i for i, (v, a) in ltiles.items() if a], 4096)
+            vals = [ltiles[i][0] if i in ltiles else BG for i in range(4096)]
+            write_values(w, vals, {i for i, (v, a) in ltiles.items() if a}, blosc, half)
+            for lci in sorted(lkids):
+                lf = lkids[lci]
+                w.mask(leaf_active(lf), 512)
+                order.append(lf)
+    for lf in order:
+        w.mask(leaf_active(lf), 512)
+        write_values(w, leaf_values(lf), leaf_active(lf), blosc, half)
+    return bytes(w.b)
+
+def write_openvdb(path, blosc, half):
+    w = W()
+    w.pack('q', 0x56444220)
+    w.pack('III', 224, 11, 0)
+    w.pack('B', 1)
+    w.b += b'6b1c5a5e-0000-4000-8000-000000000000'
+    w.pack('I', 1)
+    w.s('creator'); w.s('string'); w.s('make_vdb_fixtures.py')
+    grid_type = 'Tree_float_5_4_3' + ('_HalfFloat' if half else '')
+    grids = [('other', openvdb_grid(blosc, half, True)), ('density', openvdb_grid(blosc, half, False))]
+    w.pack('i', len(grids))
+    # every descriptor is followed by its grid, the end offset points at the next descriptor
+    for name, grid in grids:
+        start = len(w.b) + 4 + len(name) + 4 + len(grid_type) + 4 + 24
+        w.s(name); w.s(grid_type); w.s('')
+        w.pack('3q', start, start, start + len(grid))
+        w.b += grid
+    open(path, 'wb').write(bytes(w.b))
+
+# ---------------- nanovdb
+
+def align(n):
+    return (n + 31) // 32 * 32
+
+def nanovdb_grid(name, empty):
+    root_tiles = [(o, None, a, v) for l, o, v, a in TILES if l == 12] + [(uo, uo, 0, BG) for uo in sorted(uppers)]
+    if empty:
+        root_tiles = []
+    root = 672 + 64
+    pos = root + 64 + 32 * len(root_tiles)
+    upper_size = align(8256 + 8 * 32768)
+    lower_size = align(1088 + 8 * 4096)
+    leaf_size = align(96 + 4 * 512)
+    upos, lpos, fpos = {}, {}, {}
+    for nodes, sizes, size in ((uppers, upos, upper_size), (lowers, lpos, lower_size), (leaves, fpos, leaf_size)):
+        for origin in ([] if empty else sorted(nodes)):
+            sizes[origin] = pos
+            pos += size
+    buf = bytearray(pos)
+    put = lambda off, fmt, *v: struct.pack_into('<' + fmt, buf, off, *v)
+
+    put(0, 'Q', 0x314244566f6e614e)
+    put(16, 'I', (32 << 21) | (6 << 10))
+    put(32, 'Q', pos)
+    buf[40:40 + len(name)] = name
+    # map: float matrix, inverse and translation first, the double versions from 384
+    for r in range(3):
+        put(296 + 4 * (4 * r), 'f', SCALE)
+        put(384 + 8 * (4 * r), 'd', SCALE)
+        put(456 + 8 * (4 * r), 'd', 1.0 / SCALE)
+    put(528, '3d', *TRANSLATE)
+    put(636, 'I', 1)
+    put(672 + 24, 'q', root - 672)
+    put(root + 24, 'I', len(root_tiles))
+    put(root + 28, 'f', BG)
+
+    def key(c):
+        u = [(x & 0xffffffff) >> 12 for x in c]
+        return u[2] | (u[1] << 21) | (u[0] << 42)
+
+    for i, (o, child, state, v) in enumerate(root_tiles):
+        t = root + 64 + 32 * i
+        put(t, 'QqIf', key(o), 0 if child is None else upos[child] - root, state, v)
+
+    def set_bits(off, bits):
+        for i in bits:
+            buf[off + (i >> 3)] |= 1 << (i & 7)
+
+    for (nodes, log2, log2dim, table, children) in ((upos, 7, 5, 8256, lpos), (lpos, 3, 4, 1088, fpos)):
+        size = 1 << (3 * log2dim)
+        for origin, p in nodes.items():
+            kids = {child_index(origin, c, log2dim, log2): c for c in children if floor_to(c, log2 + log2dim) == origin}
+            tiles = {child_index(origin, o, log2dim, log2): (v, a) for l, o, v, a in TILES
+                     if l == log2 and floor_to(o, log2 + log2dim) == origin}
+            set_bits(p + 32, [i for i, (v, a) in tiles.items() if a])
+            set_bits(p + 32 + size // 8, kids)
+            for i in range(size):
+                if i in kids:
+                    put(p + table + 8 * i, 'q', children[kids[i]] - p)
+                else:
+                    put(p + table + 8 * i, 'f', tiles[i][0] if i in tiles else BG)
+    for lf, p in fpos.items():
+        put(p, '3i', *lf)
+        set_bits(p + 16, leaf_active(lf))
+        put(p + 96, '512f', *leaf_values(lf))
+    return bytes(buf)
+
+def write_nanovdb(path):
+    out = bytearray()
+    # one segment per grid, each with its own header
+    for name in (b'other', b'density'):
+        grid = nanovdb_grid(name, name == b'other')
+        z = zlib.compress(grid, 9)
+        data = struct.pack('<Q', len(z)) + z
+        out += struct.pack('<QIHH', 0x324244566f6e614e, (32 << 21) | (6 << 10), 1, 1)
+        meta = bytearray(176)
+        struct.pack_into('<QQ', meta, 0, len(grid), len(data))
+        struct.pack_into('<II', meta, 32, 1, 2)
+        struct.pack_into('<I', meta, 136, len(name) + 1)
+        struct.pack_into('<H', meta, 168, 1)
+        out += meta + name + b'\0' + data
+    open(path, 'wb').write(bytes(out))
+
+if __name__ == '__main__':
+    import os
+    here = os.path.dirname(os.path.abspath(__file__))
+    write_openvdb(os.path.join(here, 'tiny.vdb'), False, False)
+    write_openvdb(os.path.join(here, 'tiny_blosc.vdb'), True, True)
+    write_nanovdb(os.path.join(here, 'tiny.nvdb'))
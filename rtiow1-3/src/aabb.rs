@@ -72,13 +72,18 @@ impl AABB {
 
     //after getting all these t values we compare the interval each axis and if ray_tmax > ray_t.min everything is superb
 
-    pub fn hit(&self, r: &Ray, mut ray_t: Interval) -> bool {
-        let ray_orig = r.origin();
+    pub fn hit(&self, r: &Ray, ray_t: Interval) -> bool {
         let ray_dir = r.direction();
+        let inv_dir = Vec3::new(1.0 / ray_dir[0], 1.0 / ray_dir[1], 1.0 / ray_dir[2]);
 
+        self.hit_inv(r.origin(), inv_dir, ray_t)
+    }
+
+    //same test with 1 / direction already worked out, a bvh tests the same ray against lots of boxes so it does that once per ray
+    pub fn hit_inv(&self, ray_orig: Point3, inv_dir: Vec3, mut ray_t: Interval) -> bool {
         for axis in 0..3 as usize {
             let ax = self.axis_interval(axis);
-            let adinv = 1.0 / ray_dir[axis];
+            let adinv = inv_dir[axis];
 
             let t0 = (ax.min - ray_orig[axis]) * adinv;
             let t1 = (ax.max - ray_orig[axis]) * adinv;
@@ -115,6 +120,29 @@ impl AABB {
         }
     }
 
+    //used by the SAH, the chance of a random ray hitting a box is proportional to its surface area
+    pub fn surface_area(&self) -> f64 {
+        let (dx, dy, dz) = (self.x.size(), self.y.size(), self.z.size());
+        if dx < 0.0 || dy < 0.0 || dz < 0.0 {
+            return 0.0;
+        }
+
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    //min > max on some axis, EMPTY or the overlap of boxes that dont overlap
+    pub fn is_empty(&self) -> bool {
+        self.x.min > self.x.max || self.y.min > self.y.max || self.z.min > self.z.max
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            0.5 * (self.x.min + self.x.max),
+            0.5 * (self.y.min + self.y.max),
+            0.5 * (self.z.min + self.z.max),
+        )
+    }
+
     fn pad_to_minumums(&mut self) -> () {
         let delta = 0.0001;
 
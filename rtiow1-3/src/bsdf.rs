@@ -0,0 +1,168 @@
+use std::ops::BitOr;
+
+use crate::{color::Color, hittable::HitRecord, onb::ONB, vec3::Vec3};
+
+//what kind of lobes a material has, the integrator looks at these instead of asking the material about every case
+//specular lobes are deltas, they can only be sampled (eval and pdf are 0 for any direction you hand them)
+//so light sampling and MIS only make sense for the diffuse and glossy ones
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BxdfFlags(u8);
+
+impl BxdfFlags {
+    pub const NONE: BxdfFlags = BxdfFlags(0);
+    pub const REFLECTION: BxdfFlags = BxdfFlags(1);
+    pub const TRANSMISSION: BxdfFlags = BxdfFlags(1 << 1);
+    pub const DIFFUSE: BxdfFlags = BxdfFlags(1 << 2);
+    pub const GLOSSY: BxdfFlags = BxdfFlags(1 << 3);
+    pub const SPECULAR: BxdfFlags = BxdfFlags(1 << 4);
+
+    pub const DIFFUSE_REFLECTION: BxdfFlags = BxdfFlags(1 | (1 << 2));
+    pub const DIFFUSE_TRANSMISSION: BxdfFlags = BxdfFlags((1 << 1) | (1 << 2));
+    pub const GLOSSY_REFLECTION: BxdfFlags = BxdfFlags(1 | (1 << 3));
+    pub const GLOSSY_TRANSMISSION: BxdfFlags = BxdfFlags((1 << 1) | (1 << 3));
+    pub const SPECULAR_REFLECTION: BxdfFlags = BxdfFlags(1 | (1 << 4));
+    pub const SPECULAR_TRANSMISSION: BxdfFlags = BxdfFlags((1 << 1) | (1 << 4));
+
+    pub fn contains(self, other: BxdfFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_reflective(self) -> bool {
+        self.0 & BxdfFlags::REFLECTION.0 != 0
+    }
+
+    pub fn is_transmissive(self) -> bool {
+        self.0 & BxdfFlags::TRANSMISSION.0 != 0
+    }
+
+    pub fn is_diffuse(self) -> bool {
+        self.0 & BxdfFlags::DIFFUSE.0 != 0
+    }
+
+    pub fn is_glossy(self) -> bool {
+        self.0 & BxdfFlags::GLOSSY.0 != 0
+    }
+
+    pub fn is_specular(self) -> bool {
+        self.0 & BxdfFlags::SPECULAR.0 != 0
+    }
+
+    //has at least one lobe that eval and pdf know about
+    pub fn is_non_specular(self) -> bool {
+        self.0 & (BxdfFlags::DIFFUSE.0 | BxdfFlags::GLOSSY.0) != 0
+    }
+}
+
+impl BitOr for BxdfFlags {
+    type Output = BxdfFlags;
+
+    fn bitor(self, rhs: BxdfFlags) -> BxdfFlags {
+        BxdfFlags(self.0 | rhs.0)
+    }
+}
+
+//result of sampling a material, f already has the cosine in it (same as eval) so the path weight is just f / pdf
+//for specular lobes pdf is the probability of picking that lobe and f is whatever makes f / pdf come out right
+pub struct BsdfSample {
+    pub wi: Vec3,
+    pub f: Color,
+    pub pdf: f64,
+    pub flags: BxdfFlags,
+}
+
+impl BsdfSample {
+    pub fn new(wi: Vec3, f: Color, pdf: f64, flags: BxdfFlags) -> BsdfSample {
+        BsdfSample { wi, f, pdf, flags }
+    }
+}
+
+//materials work in the local shading frame where z is the normal (facing the side the ray came from)
+//and x follows dpdu when the primitive has one. These helpers are for that frame
+pub fn cos_theta(w: Vec3) -> f64 {
+    w.z()
+}
+
+pub fn abs_cos_theta(w: Vec3) -> f64 {
+    w.z().abs()
+}
+
+pub fn same_hemisphere(a: Vec3, b: Vec3) -> bool {
+    a.z() * b.z() > 0.0
+}
+
+//mirror around the normal, in the local frame that is just flipping x and y
+pub fn reflect(wo: Vec3) -> Vec3 {
+    Vec3::new(-wo.x(), -wo.y(), wo.z())
+}
+
+//the material of a hit together with its shading frame, this is what the integrator talks to
+//it takes and gives world space directions, wo always points away from the surface (back along the incoming ray)
+//nothing here allocates, the old ScatterRecord needed a new Rc<dyn PDF> on every single hit
+//the frame is built on the shading normal, the geometric normal is only kept for the light leak check
+pub struct Bsdf<'a> {
+    frame: ONB,
+    ng: Vec3,
+    rec: &'a HitRecord,
+}
+
+impl<'a> Bsdf<'a> {
+    pub fn new(rec: &'a HitRecord) -> Bsdf<'a> {
+        Bsdf {
+            frame: ONB::new_tangent(rec.mat.shading_normal(rec), rec.dpdu),
+            ng: rec.normal,
+            rec,
+        }
+    }
+
+    pub fn flags(&self) -> BxdfFlags {
+        self.rec.mat.flags(self.rec)
+    }
+
+    //with smooth normals a direction can be above the shading normal but below the real surface (or the other way around)
+    //then the material would reflect light that comes from inside the object, which shows up as light leaking through
+    //at the edges of low poly meshes. The real surface decides if it is reflection or transmission, if the shading
+    //frame disagrees that direction gets nothing (Veach's fix, also what pbrt does)
+    fn leaks(&self, wo: Vec3, wi: Vec3, wo_local: Vec3, wi_local: Vec3) -> bool {
+        let geometric_reflect = Vec3::dot(wo, self.ng) * Vec3::dot(wi, self.ng) > 0.0;
+        geometric_reflect != same_hemisphere(wo_local, wi_local)
+    }
+
+    pub fn f(&self, wo: Vec3, wi: Vec3) -> Color {
+        let wo_local = self.frame.to_local(Vec3::unit_vector(wo));
+        let wi_local = self.frame.to_local(Vec3::unit_vector(wi));
+        if self.leaks(wo, wi, wo_local, wi_local) {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        self.rec.mat.eval(self.rec, wo_local, wi_local)
+    }
+
+    pub fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let wo = self.frame.to_local(Vec3::unit_vector(wo));
+        let wi = self.frame.to_local(Vec3::unit_vector(wi));
+
+        self.rec.mat.pdf(self.rec, wo, wi)
+    }
+
+    //uc picks the lobe, u is for the direction inside the lobe
+    pub fn sample_f(&self, wo: Vec3, uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        let wo_local = self.frame.to_local(Vec3::unit_vector(wo));
+
+        let mut bs = self.rec.mat.sample(self.rec, wo_local, uc, u)?;
+        if bs.pdf <= 0.0 || bs.wi.near_zero() {
+            return None;
+        }
+
+        let wi_local = bs.wi;
+        bs.wi = self.frame.transform(wi_local);
+        if self.leaks(wo, bs.wi, wo_local, wi_local) {
+            return None;
+        }
+
+        Some(bs)
+    }
+}
@@ -1,125 +1,611 @@
 //this file is really smart about the way it manages stuff
 
-use crate::{aabb::AABB, hittable::*, hittable_list::HittableList, interval::Interval, ray::*};
-use std::cmp::Ordering;
+use crate::{
+    aabb::AABB,
+    hittable::*,
+    hittable_list::HittableList,
+    interval::Interval,
+    ray::*,
+    vec3::{Point3, Vec3},
+};
+use std::cell::Cell;
 use std::rc::Rc;
+use std::time::Instant;
 
-pub struct BvhNode {
-    left: Rc<dyn Hittable>,
-    right: Rc<dyn Hittable>,
+//bins is how many buckets the centroids get thrown in per axis when looking for a split (more is slower to build, slightly better trees)
+//max_leaf_size is how many objects a leaf may keep before we are forced to split it
+#[derive(Copy, Clone, Debug)]
+pub struct BvhOptions {
+    pub bins: usize,
+    pub max_leaf_size: usize,
+}
+
+impl Default for BvhOptions {
+    fn default() -> BvhOptions {
+        BvhOptions {
+            bins: 16,
+            max_leaf_size: 4,
+        }
+    }
+}
+
+//the ray counters are Cells because hit only gets &self
+struct BvhStats {
+    build_ms: f64,
+    objects: usize,
+    interior_nodes: usize,
+    leaves: usize,
+    rays: Cell<u64>,
+    nodes_visited: Cell<u64>,
+    objects_tested: Cell<u64>,
+}
+
+impl BvhStats {
+    fn new(build: &SahBuild, start: Instant) -> BvhStats {
+        //nothing is printed here, loaders build a tree per part so a model would flood the output. print_stats shows it
+        BvhStats {
+            build_ms: start.elapsed().as_secs_f64() * 1000.0,
+            objects: build.order.len(),
+            interior_nodes: build.interior_nodes,
+            leaves: build.leaves,
+            rays: Cell::new(0),
+            nodes_visited: Cell::new(0),
+            objects_tested: Cell::new(0),
+        }
+    }
+
+    fn count(counter: &Cell<u64>, n: u64) {
+        counter.set(counter.get() + n);
+    }
+
+    //how the tree did over the render so far, nodes and objects per ray are the average traversal work
+    fn print(&self) {
+        let rays = self.rays.get().max(1) as f64;
+        println!(
+            "bvh: {} objects, {} nodes, {} leaves ({:.2} objects per leaf), built in {:.2} ms",
+            self.objects,
+            self.interior_nodes,
+            self.leaves,
+            self.objects as f64 / self.leaves.max(1) as f64,
+            self.build_ms
+        );
+        println!(
+            "bvh: {} rays, {:.2} nodes visited and {:.2} objects tested per ray",
+            self.rays.get(),
+            self.nodes_visited.get() as f64 / rays,
+            self.objects_tested.get() as f64 / rays
+        );
+    }
+}
+
+//what the SAH builder makes, both bvh versions turn this into their own layout
+//leaves point into order, which is the object indices sorted so every leaf's objects sit next to each other
+enum BuildNode {
+    Interior {
+        bbox: AABB,
+        axis: usize,
+        left: Box<BuildNode>,
+        right: Box<BuildNode>,
+    },
+    Leaf {
+        bbox: AABB,
+        start: usize,
+        count: usize,
+    },
+}
+
+impl BuildNode {
+    fn bbox(&self) -> AABB {
+        match self {
+            BuildNode::Interior { bbox, .. } => *bbox,
+            BuildNode::Leaf { bbox, .. } => *bbox,
+        }
+    }
+}
+
+struct SahBuild {
+    root: BuildNode,
+    order: Vec<usize>,
+    interior_nodes: usize,
+    leaves: usize,
+}
+
+//what the builder needs to know about each object, so bounding_box is only called once per object
+struct BuildObject {
+    index: usize,
     bbox: AABB,
+    centroid: Point3,
 }
 
-impl BvhNode {
-    pub fn new(mut list: HittableList) -> BvhNode {
-        let len = list.objects.len();
-        BvhNode::actual(&mut list.objects, 0, len) //if list is mutable then list.objects are also mutable (https://doc.rust-lang.org/book/ch05-01-defining-structs.html)
+#[derive(Copy, Clone)]
+struct Bin {
+    count: usize,
+    bbox: AABB,
+}
+
+const MAX_SAH_DEPTH: usize = 48;
+
+//visiting a node is about an 8th of the cost of intersecting an object (same ratio pbrt uses)
+const TRAVERSAL_COST: f64 = 0.125;
+
+fn build_sah(bboxes: &[AABB], options: BvhOptions) -> SahBuild {
+    let mut objects: Vec<BuildObject> = bboxes
+        .iter()
+        .enumerate()
+        //an empty box (a csg intersection of things that dont touch) has no middle, inf - inf would make the centroid
+        //bounds NaN. It cant be hit so it doesnt matter which leaf it ends up in
+        .map(|(index, bbox)| BuildObject {
+            index,
+            bbox: *bbox,
+            centroid: if bbox.is_empty() {
+                Point3::new(0., 0., 0.)
+            } else {
+                bbox.centroid()
+            },
+        })
+        .collect();
+
+    let options = BvhOptions {
+        bins: options.bins.max(2),
+        max_leaf_size: options.max_leaf_size.clamp(1, u16::MAX as usize),
+    };
+
+    let mut build = SahBuild {
+        root: BuildNode::Leaf {
+            bbox: AABB::EMPTY,
+            start: 0,
+            count: 0,
+        },
+        order: Vec::with_capacity(objects.len()),
+        interior_nodes: 0,
+        leaves: 0,
+    };
+    build.root = build_recursive(&mut objects, &options, &mut build, 0);
+
+    build
+}
+
+//binned SAH: throw the centroids into buckets along each axis, try every bucket boundary as a split and keep
+//the cheapest one. Cost of a split is how likely a ray is to hit each side (surface area ratio) times the objects in it
+//https://pbr-book.org/3ed-2018/Primitives_and_Intersection_Acceleration/Bounding_Volume_Hierarchies
+fn build_recursive(
+    objects: &mut [BuildObject],
+    options: &BvhOptions,
+    build: &mut SahBuild,
+    depth: usize,
+) -> BuildNode {
+    //centroid bounds are built by hand, new_point would pad them and make identical centroids look splittable
+    let mut bbox = AABB::EMPTY;
+    let mut centroid_bounds = AABB::EMPTY;
+    for o in objects.iter() {
+        bbox = AABB::new_boxes(&bbox, &o.bbox);
+        let c = o.centroid;
+        centroid_bounds = AABB::new_boxes(
+            &centroid_bounds,
+            &AABB {
+                x: Interval::new(c.x(), c.x()),
+                y: Interval::new(c.y(), c.y()),
+                z: Interval::new(c.z(), c.z()),
+            },
+        );
     }
 
-    fn actual(objects: &mut Vec<Rc<dyn Hittable>>, start: usize, end: usize) -> BvhNode {
-        let mut bbox = AABB::EMPTY;
+    let n = objects.len();
+    if n <= 1 {
+        return make_leaf(objects, bbox, build);
+    }
 
-        for object_index in start..end {
-            bbox = AABB::new_boxes(&bbox, &objects[object_index].bounding_box());
+    //past MAX_SAH_DEPTH only halve the objects, that keeps the depth (and the traversal stack) bounded even if
+    //the SAH keeps peeling off one object at a time
+    let mut best: Option<(f64, usize, usize)> = None;
+    for axis in 0..3 {
+        if depth >= MAX_SAH_DEPTH {
+            break;
         }
 
-        let axis = bbox.longest_axis();
+        let extent = centroid_bounds.axis_interval(axis);
+        if extent.size() <= 0.0 {
+            continue;
+        }
 
-        let comparator = match axis {
-            0 => BvhNode::box_x_compare,
-            1 => BvhNode::box_y_compare,
-            _ => BvhNode::box_z_compare,
-        };
+        let mut bins = vec![
+            Bin {
+                count: 0,
+                bbox: AABB::EMPTY,
+            };
+            options.bins
+        ];
+        for o in objects.iter() {
+            let b = bin_index(o.centroid[axis], extent, options.bins);
+            bins[b].count += 1;
+            bins[b].bbox = AABB::new_boxes(&bins[b].bbox, &o.bbox);
+        }
 
-        let object_span = end - start;
+        //sweep from the right once to get the area/count of everything right of each boundary, then from the left
+        let mut right_area = vec![0.0; options.bins];
+        let mut right_count = vec![0; options.bins];
+        let mut acc_box = AABB::EMPTY;
+        let mut acc_count = 0;
+        for i in (1..options.bins).rev() {
+            acc_box = AABB::new_boxes(&acc_box, &bins[i].bbox);
+            acc_count += bins[i].count;
+            right_area[i] = acc_box.surface_area();
+            right_count[i] = acc_count;
+        }
 
-        let left: Rc<dyn Hittable>;
-        let right: Rc<dyn Hittable>;
+        acc_box = AABB::EMPTY;
+        acc_count = 0;
+        for split in 1..options.bins {
+            acc_box = AABB::new_boxes(&acc_box, &bins[split - 1].bbox);
+            acc_count += bins[split - 1].count;
+            if acc_count == 0 || right_count[split] == 0 {
+                continue;
+            }
 
-        //ok so visualize the BVH tree, the root note is the node that covers all the objects on the scene
-        //each node has 2 child nodes, left and right. left and right is determined by choosing a random axis, sorting objects about that axis
-        //and then putting half to left and the other half to right. You do this recursively untill you reach individual spheres
-        //now here its important to know that in the case of 3 nodes being left to divide we put 2 to one 1 to the other.
-        //well how do we deal with the 1 node that is left? We established that BvhNode needs left and right to be constructed.
-        //this is why when there is 1 node left we copy the node itself as its children and end the recursion there.
+            let cost = acc_count as f64 * acc_box.surface_area()
+                + right_count[split] as f64 * right_area[split];
+            if best.is_none_or(|(c, _, _)| cost < c) {
+                best = Some((cost, axis, split));
+            }
+        }
+    }
 
-        //also note that when we reach the leaf, left and right are the Sphere hittables
-        //while all the other left and rights are BvhNode hittables
-        //why is this important? --> see comments on the hit function below
-        if object_span == 1 {
-            left = objects[start].clone(); //increment referernce to the sphere 
-            right = objects[start].clone(); //either sphere, quad or hittable grouping of such primitives (HittableList)
-        } else if object_span == 2 {
-            left = objects[start].clone();
-            right = objects[start + 1].clone();
-        } else {
-            objects[start..end].sort_by(|a, b| comparator(a, b));
+    let parent_area = bbox.surface_area().max(1e-12);
+    let (mid, axis) = match best {
+        Some((cost, axis, split)) => {
+            let cost = TRAVERSAL_COST + cost / parent_area;
 
-            let mid = start + object_span / 2;
+            //splitting isnt worth it, intersecting everything here is cheaper
+            if cost >= n as f64 && n <= options.max_leaf_size {
+                return make_leaf(objects, bbox, build);
+            }
 
-            //these recursive calls are fine because the mutable reference is just passed down not created again
-            left = Rc::new(BvhNode::actual(objects, start, mid));
-            right = Rc::new(BvhNode::actual(objects, mid, end));
+            let extent = *centroid_bounds.axis_interval(axis);
+            let mid = partition(objects, |o| {
+                bin_index(o.centroid[axis], &extent, options.bins) < split
+            });
+            (mid, axis)
+        }
+        None => {
+            //every centroid is in the same spot (or we are too deep), nothing sensible to split on
+            if n <= options.max_leaf_size {
+                return make_leaf(objects, bbox, build);
+            }
+            (n / 2, bbox.longest_axis() as usize)
         }
+    };
 
-        //also here note that since the leafmost left and rights are spheres thier boundingbox is called that build the whole tree.
-        let bbox1 = left.bounding_box();
-        let bbox2 = right.bounding_box();
+    let (left, right) = objects.split_at_mut(mid);
+    let left = build_recursive(left, options, build, depth + 1);
+    let right = build_recursive(right, options, build, depth + 1);
+    build.interior_nodes += 1;
 
-        BvhNode {
-            left: left,
-            right: right,
-            bbox: AABB::new_boxes(&bbox1, &bbox2),
+    BuildNode::Interior {
+        bbox: AABB::new_boxes(&left.bbox(), &right.bbox()),
+        axis,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+fn make_leaf(objects: &[BuildObject], bbox: AABB, build: &mut SahBuild) -> BuildNode {
+    let start = build.order.len();
+    build.order.extend(objects.iter().map(|o| o.index));
+    build.leaves += 1;
+
+    BuildNode::Leaf {
+        bbox,
+        start,
+        count: objects.len(),
+    }
+}
+
+fn bin_index(c: f64, extent: &Interval, bins: usize) -> usize {
+    let b = ((c - extent.min) / extent.size() * bins as f64) as usize;
+    b.min(bins - 1)
+}
+
+//moves everything pred says yes to to the front, returns how many there are
+fn partition(objects: &mut [BuildObject], pred: impl Fn(&BuildObject) -> bool) -> usize {
+    let mut first = 0;
+    for i in 0..objects.len() {
+        if pred(&objects[i]) {
+            objects.swap(first, i);
+            first += 1;
         }
     }
+    first
+}
+
+//same as the hittable list, keep shrinking the interval to the closest hit so far
+fn hit_objects<T: Hittable>(
+    objects: &[T],
+    r: &Ray,
+    ray_t: Interval,
+    rec: &mut HitRecord,
+) -> Option<f64> {
+    let mut closest_so_far: Option<f64> = None;
+    for object in objects {
+        let t_max = closest_so_far.unwrap_or(ray_t.max);
+        if object.hit(r, Interval::new(ray_t.min, t_max), rec) {
+            closest_so_far = Some(rec.t);
+        }
+    }
+    closest_so_far
+}
 
-    //reminder that things are sorted by one corner of the box, meaning the bbox can overlap if an pbjects bbox leaks into to neighborhing bbox
-    fn box_compare(a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>, axis_index: usize) -> Ordering {
-        let bbox1 = a.bounding_box();
-        let bbox2 = b.bounding_box();
+//the pointer tree version, every node is its own allocation and hit recurses down it (left always first)
+//kept around to compare against LinearBvh, which is what the scenes use
+enum BvhTree {
+    Interior {
+        bbox: AABB,
+        left: Box<BvhTree>,
+        right: Box<BvhTree>,
+    },
+    Leaf {
+        bbox: AABB,
+        objects: Vec<Rc<dyn Hittable>>,
+    },
+}
 
-        let a_axis_interval = bbox1.axis_interval(axis_index);
-        let b_axis_interval = bbox2.axis_interval(axis_index);
+impl BvhTree {
+    fn from_build(node: BuildNode, objects: &[Rc<dyn Hittable>], order: &[usize]) -> BvhTree {
+        match node {
+            BuildNode::Interior {
+                bbox, left, right, ..
+            } => BvhTree::Interior {
+                bbox,
+                left: Box::new(BvhTree::from_build(*left, objects, order)),
+                right: Box::new(BvhTree::from_build(*right, objects, order)),
+            },
+            BuildNode::Leaf { bbox, start, count } => BvhTree::Leaf {
+                bbox,
+                objects: order[start..start + count]
+                    .iter()
+                    .map(|&i| objects[i].clone())
+                    .collect(),
+            },
+        }
+    }
 
-        if a_axis_interval.min < b_axis_interval.min {
-            Ordering::Less
-        } else {
-            Ordering::Greater
+    fn bbox(&self) -> AABB {
+        match self {
+            BvhTree::Interior { bbox, .. } => *bbox,
+            BvhTree::Leaf { bbox, .. } => *bbox,
         }
     }
+}
 
-    fn box_x_compare(a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>) -> Ordering {
-        BvhNode::box_compare(a, b, 0)
+pub struct BvhNode {
+    root: BvhTree,
+    stats: BvhStats,
+}
+
+impl BvhNode {
+    pub fn new(list: HittableList) -> BvhNode {
+        BvhNode::new_with(list, BvhOptions::default())
     }
 
-    fn box_y_compare(a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>) -> Ordering {
-        BvhNode::box_compare(a, b, 1)
+    pub fn new_with(list: HittableList, options: BvhOptions) -> BvhNode {
+        let start = Instant::now();
+
+        let bboxes: Vec<AABB> = list.objects.iter().map(|o| o.bounding_box()).collect();
+        let build = build_sah(&bboxes, options);
+        let stats = BvhStats::new(&build, start);
+
+        BvhNode {
+            root: BvhTree::from_build(build.root, &list.objects, &build.order),
+            stats,
+        }
     }
 
-    fn box_z_compare(a: &Rc<dyn Hittable>, b: &Rc<dyn Hittable>) -> Ordering {
-        BvhNode::box_compare(a, b, 2)
+    fn hit_tree(&self, node: &BvhTree, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        BvhStats::count(&self.stats.nodes_visited, 1);
+
+        match node {
+            BvhTree::Interior { bbox, left, right } => {
+                //bbox also uses ray_t as we dont care about bboxes further if we hit something closer
+                if !bbox.hit(r, ray_t) {
+                    return false;
+                }
+
+                //if we hit the left box, pass in rec.t as the max for the right hit to not overwrite rec
+                let hit_left = self.hit_tree(left, r, ray_t, rec);
+                let t_max = if hit_left { rec.t } else { ray_t.max };
+                let hit_right = self.hit_tree(right, r, Interval::new(ray_t.min, t_max), rec);
+
+                hit_left || hit_right
+            }
+            BvhTree::Leaf { bbox, objects } => {
+                if !bbox.hit(r, ray_t) {
+                    return false;
+                }
+
+                BvhStats::count(&self.stats.objects_tested, objects.len() as u64);
+                hit_objects(objects, r, ray_t, rec).is_some()
+            }
+        }
+    }
+
+    pub fn print_stats(&self) {
+        self.stats.print();
     }
 }
 
-//when the hit function is called for the root node of the BVH tree in hittable_list
-//this function recurses all the way down to the leaf. But remember the leaf left and rights are other Hittables not BvhNodes!
-//this means that other_hittable.hit() is called instead of this function. This is why it works
+//the tree is private, the whole thing is one hittable to the rest of the code
+//note the objects in the leaves can be anything, including another BvhNode, their hit is just called like in a list
 impl Hittable for BvhNode {
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
-        if !self.bbox.hit(r, ray_t) {
-            //bbox also uses ray_t as we dont care about bboxes further if we hit something closer
+        BvhStats::count(&self.stats.rays, 1);
+        self.hit_tree(&self.root, r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.root.bbox()
+    }
+}
+
+//one node of the flattened tree. The first child of an interior node is always the next node in the array,
+//offset is where the second child is. For leaves offset is where their objects start and count how many (0 means interior)
+#[derive(Copy, Clone)]
+struct LinearNode {
+    bbox: AABB,
+    offset: u32,
+    count: u16,
+    axis: u8,
+}
+
+//deepest tree we can walk, MAX_SAH_DEPTH plus however many halvings are left after it
+const TRAVERSAL_STACK_SIZE: usize = MAX_SAH_DEPTH + 64;
+
+//the same SAH tree laid out in one array in depth first order, so walking it is mostly going forward in memory
+//and there is no recursion. The objects are stored in leaf order too, T is usually Rc<dyn Hittable> but can be a concrete type
+//https://pbr-book.org/3ed-2018/Primitives_and_Intersection_Acceleration/Bounding_Volume_Hierarchies#CompactBVHForTraversal
+pub struct LinearBvh<T: Hittable = Rc<dyn Hittable>> {
+    nodes: Vec<LinearNode>,
+    objects: Vec<T>,
+    stats: BvhStats,
+}
+
+impl LinearBvh<Rc<dyn Hittable>> {
+    pub fn new(list: HittableList) -> LinearBvh {
+        LinearBvh::new_with(list.objects, BvhOptions::default())
+    }
+}
+
+impl<T: Hittable> LinearBvh<T> {
+    pub fn new_with(objects: Vec<T>, options: BvhOptions) -> LinearBvh<T> {
+        let start = Instant::now();
+
+        let bboxes: Vec<AABB> = objects.iter().map(|o| o.bounding_box()).collect();
+        let build = build_sah(&bboxes, options);
+
+        //put the objects in leaf order without needing T: Clone
+        let mut slots: Vec<Option<T>> = objects.into_iter().map(Some).collect();
+        let ordered: Vec<T> = build
+            .order
+            .iter()
+            .map(|&i| slots[i].take().unwrap())
+            .collect();
+
+        let mut nodes = Vec::with_capacity(build.interior_nodes + build.leaves);
+        LinearBvh::<T>::flatten(&build.root, &mut nodes);
+
+        let stats = BvhStats::new(&build, start);
+
+        LinearBvh {
+            nodes,
+            objects: ordered,
+            stats,
+        }
+    }
+
+    fn flatten(node: &BuildNode, nodes: &mut Vec<LinearNode>) -> usize {
+        let index = nodes.len();
+        match node {
+            BuildNode::Leaf { bbox, start, count } => {
+                nodes.push(LinearNode {
+                    bbox: *bbox,
+                    offset: *start as u32,
+                    count: *count as u16,
+                    axis: 0,
+                });
+            }
+            BuildNode::Interior {
+                bbox,
+                axis,
+                left,
+                right,
+            } => {
+                nodes.push(LinearNode {
+                    bbox: *bbox,
+                    offset: 0,
+                    count: 0,
+                    axis: *axis as u8,
+                });
+                LinearBvh::<T>::flatten(left, nodes);
+                let second = LinearBvh::<T>::flatten(right, nodes);
+                nodes[index].offset = second as u32;
+            }
+        }
+        index
+    }
+
+    pub fn print_stats(&self) {
+        self.stats.print();
+    }
+}
+
+impl<T: Hittable> Hittable for LinearBvh<T> {
+    //walk the array with a small stack of nodes still to visit. At an interior node we go into the child on the side
+    //the ray comes from first, so the closest hit is usually found early and ray_t shrinks before the far child is tested
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        BvhStats::count(&self.stats.rays, 1);
+        if self.nodes.is_empty() {
             return false;
         }
 
-        //if we hit the left box, pass in rec.t as the max for the right hit to not overwrite rec
-        let hit_left = self.left.hit(r, ray_t, rec);
-        let t_max = if hit_left { rec.t } else { ray_t.max };
-        let hit_right = self.right.hit(r, Interval::new(ray_t.min, t_max), rec);
+        let dir = r.direction();
+        let inv_dir = Vec3::new(1.0 / dir.x(), 1.0 / dir.y(), 1.0 / dir.z());
+        let dir_is_neg = [inv_dir.x() < 0.0, inv_dir.y() < 0.0, inv_dir.z() < 0.0];
+        let origin = r.origin();
+
+        let mut closest_so_far = ray_t.max;
+        let mut hit_anything = false;
+
+        let mut stack = [0usize; TRAVERSAL_STACK_SIZE];
+        let mut stack_len = 0;
+        let mut current = 0;
+        let mut visited = 0;
+        let mut tested = 0;
+
+        loop {
+            let node = &self.nodes[current];
+            visited += 1;
+
+            if node
+                .bbox
+                .hit_inv(origin, inv_dir, Interval::new(ray_t.min, closest_so_far))
+            {
+                if node.count > 0 {
+                    let start = node.offset as usize;
+                    let objects = &self.objects[start..start + node.count as usize];
+                    tested += objects.len();
+
+                    if let Some(t) =
+                        hit_objects(objects, r, Interval::new(ray_t.min, closest_so_far), rec)
+                    {
+                        hit_anything = true;
+                        closest_so_far = t;
+                    }
+                } else {
+                    let (near, far) = if dir_is_neg[node.axis as usize] {
+                        (node.offset as usize, current + 1)
+                    } else {
+                        (current + 1, node.offset as usize)
+                    };
+
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    current = near;
+                    continue;
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+
+        BvhStats::count(&self.stats.nodes_visited, visited);
+        BvhStats::count(&self.stats.objects_tested, tested as u64);
 
-        hit_left || hit_right
+        hit_anything
     }
 
     fn bounding_box(&self) -> AABB {
-        self.bbox
+        self.nodes.first().map(|n| n.bbox).unwrap_or(AABB::EMPTY)
     }
 }
@@ -1,11 +1,12 @@
+use crate::bsdf::Bsdf;
 use crate::color::*;
 use crate::hittable::HitRecord;
-use crate::hittable::Hittable;
+use crate::hittable::{Hittable, MAX_CROSSINGS};
 use crate::interval::Interval;
-use crate::material::ScatterRecord;
-use crate::pdf::CosinePDF;
+use crate::light::Light;
+use crate::medium::{Medium, MediumSample};
+use crate::medium_interface::MediumStack;
 use crate::pdf::HittablePDF;
-use crate::pdf::MixturePDF;
 use crate::pdf::PDF;
 use crate::ray::Ray;
 use crate::utils::random_double;
@@ -13,6 +14,7 @@ use crate::vec3::{Point3, Vec3};
 use std::fs::File;
 use std::io::Write;
 use std::rc::Rc;
+use std::time::Instant;
 
 pub struct Camera {
     pub aspect_ratio: f64,
@@ -34,6 +36,15 @@ pub struct Camera {
     pub focus_dist: f64,
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
+    //lights that arent geometry (point, spot, sun), the lights hittable passed to render is still for the area lights
+    pub scene_lights: Vec<Rc<dyn Light>>,
+    //smoke and clouds that arent in the world, every path segment is tracked through them (see medium.rs)
+    pub media: Vec<Rc<dyn Medium>>,
+    //what the camera sits in (fog that fills the scene), surfaces with a MediumInterface change it along each path
+    pub medium: Option<Rc<dyn Medium>>,
+    //rays get a random time between these two, moving objects are blurred over that stretch. open == close freezes everything at that time
+    pub shutter_open: f64,
+    pub shutter_close: f64,
 }
 
 impl Camera {
@@ -66,6 +77,11 @@ impl Camera {
             focus_dist: focus_dist,
             defocus_disk_u: Vec3::new(0., 0., 0.),
             defocus_disk_v: Vec3::new(0., 0., 0.),
+            scene_lights: Vec::new(),
+            media: Vec::new(),
+            medium: None,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
         }
     }
 
@@ -75,6 +91,7 @@ impl Camera {
         lights: Rc<dyn Hittable>,
     ) -> std::io::Result<()> {
         self.initialize();
+        let start = Instant::now();
 
         let mut file = File::create("image.ppm")?;
         writeln!(file, "P3\n{} {}\n255", self.image_width, self.image_height)?;
@@ -102,6 +119,8 @@ impl Camera {
                 write_color(&(pixel_color * self.pixel_samples_scale), &mut file).unwrap();
             }
         }
+
+        println!("rendered in {:.2} s", start.elapsed().as_secs_f64());
         Ok(())
     }
 
@@ -162,7 +181,8 @@ impl Camera {
             self.defocus_disk_sample()
         };
         let ray_direction = pixel_sample - ray_origin;
-        let ray_time = random_double();
+        let ray_time =
+            self.shutter_open + random_double() * (self.shutter_close - self.shutter_open);
 
         Ray::new(ray_origin, ray_direction, ray_time)
     }
@@ -178,6 +198,25 @@ impl Camera {
         self.center + (p.x() * self.defocus_disk_u) + (p.y() * self.defocus_disk_v)
     }
 
+    //the power heuristic from veach, weights a sample by how likely each strategy was to make it
+    //https://lisyarus.github.io/blog/posts/multiple-importance-sampling.html
+    fn mis_weight(pdf: f64, other_pdf: f64) -> f64 {
+        let a = pdf * pdf;
+        let b = other_pdf * other_pdf;
+        if a + b == 0.0 {
+            return 0.0;
+        }
+        a / (a + b)
+    }
+
+    //(I think) what the book fails to mention is that we haven't come up with
+    //a better single PDF that reduces noise, the actual improvement happens in MIS
+    //only lights PDF is broken becaues it violates the rules of monte carlo (f(x) > 0 -> p(x) > 0)
+
+    //so this is MIS now: at every non specular hit we sample the lights (next event estimation) and also sample the material for the
+    //next bounce. If that bounce happens to hit a light, its emission is weighted against what the light sampling would have done
+    //the two strategies cover each others weak spots (small lights vs glossy lobes) and the weights make them sum to the right answer
+    //it is a loop now instead of recursion, throughput is the product of all the f / pdf along the path
     fn ray_color(
         &self,
         r: &Ray,
@@ -185,56 +224,300 @@ impl Camera {
         world: &dyn Hittable,
         lights: Rc<dyn Hittable>,
     ) -> Color {
-        if depth <= 0 {
+        let mut color = Color::new(0., 0., 0.);
+        let mut throughput = Color::new(1., 1., 1.);
+        let mut ray = *r;
+
+        //the camera ray and rays after a specular bounce cant be light sampled, so emission they hit counts fully
+        let mut specular_bounce = true;
+        let mut prev_pdf = 0.0;
+        let mut prev_p = ray.origin();
+
+        //what the path is inside of, it changes every time the path goes through a surface that has media on it
+        let mut stack = MediumStack::new(self.medium.clone());
+
+        //surfaces the path goes straight through dont count as bounces, but there can only be so many of them
+        let mut bounces = 0;
+        let mut skipped = 0;
+        while bounces < depth {
+            let mut rec = HitRecord::new();
+
+            //because of floating point errors we have a 0.001 min to ensure rays
+            //dont self intersect. If starting ray is below the sphere it will hit itself
+            let mut hit = world.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec);
+
+            //a collision in a medium before the surface scatters the ray there instead, the phase function takes the
+            //place of the bsdf for the rest of the bounce
+            let t_max = if hit { rec.t } else { f64::INFINITY };
+            let media = self.sample_media(&ray, t_max, &stack);
+            throughput = throughput * media.weight;
+            let scattered_in_medium = media.collision.is_some();
+            if let Some(collision) = media.collision {
+                rec = collision;
+                hit = true;
+            }
+
+            if !hit {
+                color = color + throughput * self.background;
+
+                //lights at infinity (the sun disk) are seen by escaping rays, weighted against sampling them directly
+                for light in &self.scene_lights {
+                    let le = light.le(&ray);
+                    if le.near_zero() {
+                        continue;
+                    }
+
+                    let weight = if specular_bounce {
+                        1.0
+                    } else {
+                        Camera::mis_weight(prev_pdf, light.pdf_li(prev_p, ray.direction()))
+                    };
+                    color = color + throughput * le * weight;
+                }
+                break;
+            }
+
+            if !scattered_in_medium && Camera::is_invisible(&ray, &rec, &stack) {
+                stack.cross(&rec);
+                skipped += 1;
+                if skipped > MAX_CROSSINGS {
+                    break;
+                }
+                ray = Ray::new(rec.p, ray.direction(), ray.time());
+                continue;
+            }
+            rec.outer_ior = stack.outer_ior(&rec);
+
+            let color_from_emission = rec.mat.emitted(&ray, &rec, rec.u, rec.v, rec.p);
+            if !color_from_emission.near_zero() {
+                let weight = if specular_bounce {
+                    1.0
+                } else {
+                    let light_pdf = lights.pdf_value(prev_p, ray.direction());
+                    Camera::mis_weight(prev_pdf, light_pdf)
+                };
+
+                color = color + throughput * color_from_emission * weight;
+            }
+
+            //if the material has no lobes (lights) the path ends here, we only cared about the emission
+            let bsdf = Bsdf::new(&rec);
+            let flags = bsdf.flags();
+            if flags.is_empty() {
+                break;
+            }
+
+            let wo = -ray.direction();
+
+            //as long as the pdf and the scatter generation match, it will converge to the same result with varying speeds.
+            //When the material's eval changes you are changing how the material reacts. (I think)
+            if flags.is_non_specular() {
+                color = color
+                    + throughput * self.sample_light(&bsdf, &rec, &ray, world, &lights, &stack);
+
+                for light in &self.scene_lights {
+                    color = color
+                        + throughput
+                            * self.sample_scene_light(
+                                light.as_ref(),
+                                &bsdf,
+                                &rec,
+                                &ray,
+                                world,
+                                &stack,
+                            );
+                }
+            }
+
+            let Some(bs) = bsdf.sample_f(wo, random_double(), [random_double(), random_double()])
+            else {
+                break;
+            };
+
+            //also important, this confused me: from https://en.wikipedia.org/wiki/Lambertian_reflectance
+            //"When viewed from various angles, the reflected radiant intensity and the apparent area of the surface both vary with the cosine of the
+            //viewing angle, so the reflected radiance (intensity per unit area) is the same from all viewing angles."
+            throughput = throughput * bs.f / bs.pdf;
+            if throughput.near_zero() {
+                break;
+            }
+
+            //refracting into or out of an object with media changes what the path is in
+            if bs.flags.is_transmissive() {
+                stack.cross(&rec);
+            }
+
+            specular_bounce = bs.flags.is_specular();
+            prev_pdf = bs.pdf;
+            prev_p = rec.p;
+            ray = Ray::new(rec.p, bs.wi, ray.time());
+            bounces += 1;
+        }
+
+        color
+    }
+
+    //one shadow ray towards a random point on the lights, weighted against the chance of the material picking that direction
+    //whatever the shadow ray hits first is what we see, so an occluder just gives 0 emission
+    fn sample_light(
+        &self,
+        bsdf: &Bsdf,
+        rec: &HitRecord,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &Rc<dyn Hittable>,
+        stack: &MediumStack,
+    ) -> Color {
+        let wo = -ray.direction();
+        let light_pdf_gen = HittablePDF::new(lights.clone(), rec.p);
+        let wi = light_pdf_gen.generate();
+        let light_pdf = light_pdf_gen.value(wi);
+        if light_pdf <= 0.0 {
+            return Color::new(0., 0., 0.);
+        }
+
+        let f = bsdf.f(wo, wi);
+        if f.near_zero() {
             return Color::new(0., 0., 0.);
         }
 
-        let mut rec = HitRecord::new();
+        let shadow_ray = Ray::new(rec.p, wi, ray.time());
+        let (light_rec, tr) = self.trace_shadow(&shadow_ray, f64::INFINITY, world, stack);
+        let Some(light_rec) = light_rec else {
+            return Color::new(0., 0., 0.);
+        };
 
-        //because of floating point errors we have a 0.001 min to ensure rays
-        //dont self intersect. If starting ray is below the sphere it will hit itself
-        if !world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
-            return self.background;
+        let emitted = light_rec.mat.emitted(
+            &shadow_ray,
+            &light_rec,
+            light_rec.u,
+            light_rec.v,
+            light_rec.p,
+        );
+        if emitted.near_zero() {
+            return Color::new(0., 0., 0.);
+        }
+
+        let weight = Camera::mis_weight(light_pdf, bsdf.pdf(wo, wi));
+        f * emitted * tr * weight / light_pdf
+    }
+
+    //same thing for a light that isnt in the world, here the shadow ray only has to reach the light without hitting anything
+    //delta lights cant be found by the bsdf sample so they get the full weight
+    fn sample_scene_light(
+        &self,
+        light: &dyn Light,
+        bsdf: &Bsdf,
+        rec: &HitRecord,
+        ray: &Ray,
+        world: &dyn Hittable,
+        stack: &MediumStack,
+    ) -> Color {
+        let wo = -ray.direction();
+        let Some(ls) = light.sample_li(rec.p, [random_double(), random_double()]) else {
+            return Color::new(0., 0., 0.);
+        };
+        if ls.pdf <= 0.0 || ls.li.near_zero() {
+            return Color::new(0., 0., 0.);
+        }
+
+        let f = bsdf.f(wo, ls.wi);
+        if f.near_zero() {
+            return Color::new(0., 0., 0.);
+        }
+
+        //wi is a unit vector so t is the distance, stop a bit short so we dont hit whatever the light sits on
+        let shadow_ray = Ray::new(rec.p, ls.wi, ray.time());
+        let shadow_dist = ls.dist * (1.0 - 1e-4);
+        let (blocker, tr) = self.trace_shadow(&shadow_ray, shadow_dist, world, stack);
+        if blocker.is_some() {
+            return Color::new(0., 0., 0.);
         }
 
-        let mut srec = ScatterRecord::new();
-        let color_from_emission = rec.mat.emitted(&rec, rec.u, rec.v, rec.p);
+        let weight = if light.is_delta() {
+            1.0
+        } else {
+            Camera::mis_weight(ls.pdf, bsdf.pdf(wo, ls.wi))
+        };
+        f * ls.li * tr * weight / ls.pdf
+    }
 
-        //if scatter returns false it means that the material doesnt scatter the ray for some
-        //reason. In this case we only case about the emission if there is any.
-        if !rec.mat.scatter(r, &rec, &mut srec) {
-            return color_from_emission;
+    //each medium picks its own first collision with delta tracking, the nearest one before t_max is where the ray scatters
+    //(later media only have to look up to the closest one so far). The medium the path is in goes last, its weight is
+    //for the stretch it was asked about so that has to be the final one
+    fn sample_media(&self, r: &Ray, t_max: f64, stack: &MediumStack) -> MediumSample {
+        let mut closest: Option<HitRecord> = None;
+        let mut weight = Color::new(1., 1., 1.);
+        for medium in self.media.iter().chain(stack.current()) {
+            let t_max = closest.as_ref().map_or(t_max, |c| c.t);
+            let sample = medium.sample(r, Interval::new(0.001, t_max));
+            weight = weight * sample.weight;
+            if sample.collision.is_some() {
+                closest = sample.collision;
+            }
         }
 
-        if srec.skip_pdf {
-            return srec.attenuation
-                * self.ray_color(&srec.skip_ray, depth - 1, world, lights.clone());
+        MediumSample {
+            collision: closest,
+            weight,
         }
+    }
 
-        //(I think) what the book fails to mention is that we haven't come up with
-        //a better single PDF that reduces noise, the actual improvement happens in MIS
-        //only lights PDF is broken becaues it violates the rules of monte carlo (f(x) > 0 -> p(x) > 0)
-        let light_ptr = Rc::new(HittablePDF::new(lights.clone(), rec.p));
-        //let mixed_pdf = MixturePDF::new(light_ptr, srec.pdf);
+    //surfaces that only mark where a medium starts (no lobes and no light) and the ones a higher priority object hides
+    //paths and shadow rays go straight through them
+    fn is_invisible(r: &Ray, rec: &HitRecord, stack: &MediumStack) -> bool {
+        if rec.interface.is_none() {
+            return false;
+        }
+        if stack.is_false_hit(rec) {
+            return true;
+        }
 
-        let scattered = Ray::new(rec.p, light_ptr.generate(), r.time());
-        let pdf = light_ptr.value(scattered.direction());
+        rec.mat.flags(rec).is_empty() && rec.mat.emitted(r, rec, rec.u, rec.v, rec.p).near_zero()
+    }
 
-        //as long as the pdf and the scatter generation match, it will converge to the same result with varying speeds.
-        //When scatter pdf changes you are changing how the material reacts. (I think)
-        let scatter_pdf = rec.mat.scatter_pdf(&r, &rec, &scattered);
-        //pdf = scatter_pdf; //we implicitly had this this in book 1&2
-        //think about it the pdf in lambertian and isotropic mats from the previous books was the same as the not shown scatter_pdf (they canceled out)
+    //follows a shadow ray to the first surface that is really there before t_max, going through the invisible ones
+    //and the media in between. Gives back that surface (None when nothing is in the way) and how much light gets through
+    fn trace_shadow(
+        &self,
+        r: &Ray,
+        t_max: f64,
+        world: &dyn Hittable,
+        stack: &MediumStack,
+    ) -> (Option<HitRecord>, Color) {
+        let mut stack = stack.clone();
+        let mut tr = Color::new(1., 1., 1.);
+        let mut t_min = 0.001;
+
+        for _ in 0..MAX_CROSSINGS {
+            let mut rec = HitRecord::new();
+            let hit = world.hit(r, Interval::new(t_min, t_max), &mut rec);
+            let end = if hit { rec.t } else { t_max };
+            if let Some(medium) = stack.current() {
+                tr = tr * medium.transmittance(r, Interval::new(t_min, end));
+            }
+
+            if !hit || !Camera::is_invisible(r, &rec, &stack) {
+                tr = tr * self.media_transmittance(r, end);
+                return (hit.then_some(rec), tr);
+            }
 
-        //also important, this confused me: from https://en.wikipedia.org/wiki/Lambertian_reflectance
-        //"When viewed from various angles, the reflected radiant intensity and the apparent area of the surface both vary with the cosine of the
-        //viewing angle, so the reflected radiance (intensity per unit area) is the same from all viewing angles."
+            stack.cross(&rec);
+            t_min = rec.t + 0.001;
+        }
 
-        let color_from_scatter = srec.attenuation
-            * scatter_pdf
-            * self.ray_color(&scattered, depth - 1, world, lights.clone())
-            / pdf;
+        (None, Color::new(0., 0., 0.))
+    }
 
-        color_from_emission + color_from_scatter
+    //how much of the light along a shadow ray gets through all the media, with ratio tracking
+    fn media_transmittance(&self, r: &Ray, t_max: f64) -> Color {
+        self.media
+            .iter()
+            .fold(Color::new(1., 1., 1.), |tr, medium| {
+                if tr.near_zero() {
+                    return tr;
+                }
+                tr * medium.transmittance(r, Interval::new(0.001, t_max))
+            })
     }
 }
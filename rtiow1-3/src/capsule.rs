@@ -0,0 +1,151 @@
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use crate::aabb::AABB;
+use crate::hittable::*;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::ONB;
+use crate::ray::Ray;
+use crate::utils::random_double;
+use crate::vec3::{Point3, Vec3};
+
+//every point within radius of the segment a-b, a cylinder with half a sphere on each end
+//u goes around (angle / 2pi), v goes from the tip of the a end (0) to the tip of the b end (1)
+pub struct Capsule {
+    a: Point3,
+    frame: ONB,
+    height: f64,
+    radius: f64,
+    mat: Rc<dyn Material>,
+    bbox: AABB,
+    side_area: f64,
+    area: f64,
+}
+
+impl Capsule {
+    pub fn new(a: Point3, b: Point3, radius: f64, mat: Rc<dyn Material>) -> Capsule {
+        let radius = radius.max(0.0);
+        let height = (b - a).length();
+        let rvec = Vec3::new(radius, radius, radius);
+        //a == b has no direction, any frame works for what is then just a sphere
+        let axis = if height > 0.0 {
+            b - a
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        };
+        let side_area = 2.0 * PI * radius * height;
+
+        Capsule {
+            a,
+            frame: ONB::new(axis),
+            height,
+            radius,
+            mat,
+            bbox: AABB::new_boxes(
+                &AABB::new_point(a - rvec, a + rvec),
+                &AABB::new_point(b - rvec, b + rvec),
+            ),
+            side_area,
+            area: side_area + 4.0 * PI * radius * radius,
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+}
+
+impl Hittable for Capsule {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        //local space, the segment goes from the origin up the z axis
+        let o = self.frame.to_local(r.origin() - self.a);
+        let d = self.frame.to_local(r.direction());
+        let r2 = self.radius * self.radius;
+        let mut closest = ray_t.max;
+        let mut found = false;
+
+        //roots of a t^2 + 2h t + c, only kept when the hit z is inside the part's z range
+        let mut try_roots = |a: f64, h: f64, c: f64, z_range: Interval| {
+            let discriminant = h * h - a * c;
+            if a <= 1e-12 || discriminant < 0.0 {
+                return;
+            }
+            let sqrtd = discriminant.sqrt();
+            for t in [(-h - sqrtd) / a, (-h + sqrtd) / a] {
+                if ray_t.surrounds(t) && t < closest && z_range.contains(o.z() + t * d.z()) {
+                    closest = t;
+                    found = true;
+                }
+            }
+        };
+
+        //the tube between the ends
+        try_roots(
+            d.x() * d.x() + d.y() * d.y(),
+            o.x() * d.x() + o.y() * d.y(),
+            o.x() * o.x() + o.y() * o.y() - r2,
+            Interval::new(0.0, self.height),
+        );
+
+        //the two end spheres, each one only counts on its own side of the tube
+        let a = d.squared_length();
+        for (end, z_range) in [
+            (0.0, Interval::new(f64::NEG_INFINITY, 0.0)),
+            (self.height, Interval::new(self.height, f64::INFINITY)),
+        ] {
+            let oc = o - Vec3::new(0.0, 0.0, end);
+            try_roots(a, Vec3::dot(oc, d), oc.squared_length() - r2, z_range);
+        }
+
+        if !found {
+            return false;
+        }
+
+        //the normal points away from the closest point on the segment
+        let p = o + closest * d;
+        let on_axis = Vec3::new(0.0, 0.0, p.z().clamp(0.0, self.height));
+        let outward_normal = (p - on_axis) / self.radius;
+        let phi = p.y().atan2(p.x()) + PI;
+        let dist = (p.x() * p.x() + p.y() * p.y()).sqrt();
+
+        rec.t = closest;
+        rec.p = r.at(closest);
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(r, self.frame.transform(outward_normal));
+        rec.u = phi / (2.0 * PI);
+        rec.v = (p.z() + self.radius) / (self.height + 2.0 * self.radius).max(1e-12);
+        rec.dpdu = self
+            .frame
+            .transform(Vec3::new(-p.y(), p.x(), 0.0) / dist.max(1e-12));
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, dir: Vec3) -> f64 {
+        area_pdf_value(self, origin, dir, self.area)
+    }
+
+    //uniform over the area. The two half spheres together are one whole sphere, so a point on the sphere is
+    //put on the a end or the b end depending on which half it landed in
+    fn random(&self, origin: Vec3) -> Vec3 {
+        let local = if random_double() * self.area < self.side_area {
+            let phi = 2.0 * PI * random_double();
+            Vec3::new(
+                self.radius * phi.cos(),
+                self.radius * phi.sin(),
+                random_double() * self.height,
+            )
+        } else {
+            let n = Vec3::random_unit_vector();
+            let end = if n.z() < 0.0 { 0.0 } else { self.height };
+            self.radius * n + Vec3::new(0.0, 0.0, end)
+        };
+
+        self.a + self.frame.transform(local) - origin
+    }
+}
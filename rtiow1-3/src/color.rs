@@ -16,6 +16,58 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     0.0
 }
 
+//how bright a color looks to us, green counts the most (rec 709 weights, same primaries as srgb)
+pub fn luminance(c: Color) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+//cie xyz to linear srgb (d65 white), whatever ends up outside the srgb gamut gets clamped to 0
+pub fn xyz_to_rgb(xyz: Vec3) -> Color {
+    let (x, y, z) = (xyz.x(), xyz.y(), xyz.z());
+    Color::new(
+        (3.2404542 * x - 1.5371385 * y - 0.4985314 * z).max(0.0),
+        (-0.9692660 * x + 1.8760108 * y + 0.0415560 * z).max(0.0),
+        (0.0556434 * x - 0.2040259 * y + 1.0572252 * z).max(0.0),
+    )
+}
+
+//color of an ideal black body at some temperature in kelvin (candle ~1900, tungsten bulb ~2700, daylight ~6500)
+//integrates planck's law against the cie 1931 matching functions (the multi lobe gaussian fit from wyman, sloan and shirley 2013)
+//then goes xyz -> linear srgb. Normalized so the luminance is 1, it only gives the tint, brightness comes from the light
+pub fn blackbody(temperature_k: f64) -> Color {
+    let t = temperature_k.max(1.0);
+
+    //planck, constant factors dropped since we normalize anyway
+    let planck = |lambda_nm: f64| {
+        let l = lambda_nm * 1e-9;
+        let c2 = 1.4387769e-2;
+        1.0 / (l.powi(5) * ((c2 / (l * t)).exp() - 1.0))
+    };
+
+    let g = |x: f64, mu: f64, s1: f64, s2: f64| {
+        let s = if x < mu { s1 } else { s2 };
+        let d = (x - mu) / s;
+        (-0.5 * d * d).exp()
+    };
+
+    let mut xyz = Vec3::new(0.0, 0.0, 0.0);
+    let mut lambda = 380.0;
+    while lambda <= 780.0 {
+        let b = planck(lambda);
+        let x = 1.056 * g(lambda, 599.8, 37.9, 31.0) + 0.362 * g(lambda, 442.0, 16.0, 26.7)
+            - 0.065 * g(lambda, 501.1, 20.4, 26.2);
+        let y = 0.821 * g(lambda, 568.8, 46.9, 40.5) + 0.286 * g(lambda, 530.9, 16.3, 31.1);
+        let z = 1.217 * g(lambda, 437.0, 11.8, 36.0) + 0.681 * g(lambda, 459.0, 26.0, 13.8);
+        xyz = xyz + b * Vec3::new(x, y, z);
+        lambda += 5.0;
+    }
+
+    let rgb = xyz_to_rgb(xyz);
+
+    let lum = luminance(rgb);
+    if lum > 0.0 { rgb / lum } else { rgb }
+}
+
 pub fn write_color(pixel_color: &Color, file: &mut File) -> std::io::Result<()> {
     let mut r = pixel_color.x();
     let mut g = pixel_color.y();
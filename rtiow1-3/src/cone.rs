@@ -0,0 +1,165 @@
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use crate::aabb::AABB;
+use crate::disk::disk_bbox;
+use crate::hittable::*;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::ONB;
+use crate::ray::Ray;
+use crate::utils::random_double;
+use crate::vec3::{Point3, Vec3};
+
+//cone with a round base of radius at base and its tip at base + axis, capped ones have the base closed with a disk
+//u goes around (angle / 2pi), v goes up the side (0 at base, 1 at the tip), on the cap v is the distance from the middle / radius
+pub struct Cone {
+    base: Point3,
+    frame: ONB,
+    height: f64,
+    radius: f64,
+    capped: bool,
+    mat: Rc<dyn Material>,
+    bbox: AABB,
+    side_area: f64,
+    cap_area: f64,
+}
+
+impl Cone {
+    pub fn new(base: Point3, axis: Vec3, radius: f64, capped: bool, mat: Rc<dyn Material>) -> Cone {
+        let radius = radius.max(0.0);
+        let height = axis.length();
+        let apex = base + axis;
+
+        Cone {
+            base,
+            frame: ONB::new(axis),
+            height,
+            radius,
+            capped,
+            mat,
+            bbox: AABB::new_boxes(&disk_bbox(base, axis, radius), &AABB::new_point(apex, apex)),
+            side_area: PI * radius * (radius * radius + height * height).sqrt(),
+            cap_area: if capped { PI * radius * radius } else { 0.0 },
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        self.side_area + self.cap_area
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        //local space, the axis is z and the base is at the origin
+        //the side is x^2 + y^2 = k^2 (height - z)^2 with k = radius / height, cut to 0 <= z <= height
+        //(the same equation also has the upside down cone above the tip, the z test throws that one away)
+        let o = self.frame.to_local(r.origin() - self.base);
+        let d = self.frame.to_local(r.direction());
+        let k = self.radius / self.height.max(1e-12);
+        let k2 = k * k;
+        let g = self.height - o.z();
+
+        let a = d.x() * d.x() + d.y() * d.y() - k2 * d.z() * d.z();
+        let h = o.x() * d.x() + o.y() * d.y() + k2 * g * d.z();
+        let c = o.x() * o.x() + o.y() * o.y() - k2 * g * g;
+
+        let mut roots = Vec::with_capacity(2);
+        if a.abs() < 1e-12 {
+            //the ray is parallel to the side, only one crossing
+            if h.abs() > 1e-12 {
+                roots.push(-c / (2.0 * h));
+            }
+        } else {
+            let discriminant = h * h - a * c;
+            if discriminant >= 0.0 {
+                let sqrtd = discriminant.sqrt();
+                let (t0, t1) = ((-h - sqrtd) / a, (-h + sqrtd) / a);
+                roots.push(t0.min(t1));
+                roots.push(t0.max(t1));
+            }
+        }
+
+        let mut closest = ray_t.max;
+        let mut on_side = false;
+        let mut found = false;
+        for t in roots {
+            let z = o.z() + t * d.z();
+            if ray_t.surrounds(t) && (0.0..=self.height).contains(&z) {
+                closest = t;
+                on_side = true;
+                found = true;
+                break;
+            }
+        }
+
+        if self.capped && d.z().abs() > 1e-12 {
+            let t = -o.z() / d.z();
+            let (x, y) = (o.x() + t * d.x(), o.y() + t * d.y());
+            if ray_t.surrounds(t) && t < closest && x * x + y * y <= self.radius * self.radius {
+                closest = t;
+                on_side = false;
+                found = true;
+            }
+        }
+
+        if !found {
+            return false;
+        }
+
+        let p = o + closest * d;
+        let phi = p.y().atan2(p.x()) + PI;
+        let dist = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        //the gradient of the side equation, it leans up towards the tip. Right at the tip there is none so use the axis
+        let outward_normal = if !on_side {
+            Vec3::new(0.0, 0.0, -1.0)
+        } else if dist < 1e-12 {
+            Vec3::new(0.0, 0.0, 1.0)
+        } else {
+            Vec3::unit_vector(Vec3::new(p.x(), p.y(), k2 * (self.height - p.z())))
+        };
+
+        rec.t = closest;
+        rec.p = r.at(closest);
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(r, self.frame.transform(outward_normal));
+        rec.u = phi / (2.0 * PI);
+        rec.v = if on_side {
+            p.z() / self.height.max(1e-12)
+        } else {
+            dist / self.radius.max(1e-12)
+        };
+        rec.dpdu = self
+            .frame
+            .transform(Vec3::new(-p.y(), p.x(), 0.0) / dist.max(1e-12));
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, dir: Vec3) -> f64 {
+        area_pdf_value(self, origin, dir, self.area())
+    }
+
+    //uniform over the whole surface. The side gets narrower going up so its area grows with the distance from the tip,
+    //sqrt of a uniform number for that distance spreads the points evenly
+    fn random(&self, origin: Vec3) -> Vec3 {
+        let phi = 2.0 * PI * random_double();
+
+        let (r, z) = if random_double() * self.area() < self.side_area {
+            let from_tip = random_double().sqrt();
+            (self.radius * from_tip, self.height * (1.0 - from_tip))
+        } else {
+            (self.radius * random_double().sqrt(), 0.0)
+        };
+
+        let p = self.base
+            + self
+                .frame
+                .transform(Vec3::new(r * phi.cos(), r * phi.sin(), z));
+        p - origin
+    }
+}
@@ -5,6 +5,7 @@ use crate::{
     hittable::{HitRecord, Hittable},
     interval::*,
     material::*,
+    phase::PhaseFunction,
     texture::Texture,
     utils::random_double,
 };
@@ -36,6 +37,20 @@ impl ConstantMedium {
             phase_function: Rc::new(Isotropic::new(albedo)),
         }
     }
+
+    //forward scattering fog and the like, new is the same with IsotropicPhase
+    pub fn new_phase(
+        boundary: Rc<dyn Hittable>,
+        density: f64,
+        albedo: Color,
+        phase: Rc<dyn PhaseFunction>,
+    ) -> ConstantMedium {
+        ConstantMedium {
+            boundary,
+            neg_inv_density: -1. / density,
+            phase_function: Rc::new(PhaseMaterial::new(albedo, phase)),
+        }
+    }
 }
 
 //only works for convex shapes
@@ -97,6 +112,12 @@ impl Hittable for ConstantMedium {
         rec.t = rec1.t + hit_distance / ray_length;
         rec.p = r.at(rec.t);
 
+        //there is no surface in here, but the shading frame still needs some normal. Any one works since the phase function doesnt care
+        rec.normal = crate::vec3::Vec3::new(1.0, 0.0, 0.0);
+        rec.shading_normal = rec.normal;
+        rec.dpdu = crate::vec3::Vec3::new(0.0, 0.0, 0.0);
+        rec.front_face = true;
+
         rec.mat = self.phase_function.clone();
         true
     }
@@ -0,0 +1,178 @@
+use std::rc::Rc;
+
+use crate::aabb::AABB;
+use crate::hittable::*;
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+#[derive(Copy, Clone, Debug)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    //left with right cut out of it
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOp::Union => in_left || in_right,
+            CsgOp::Intersection => in_left && in_right,
+            CsgOp::Difference => in_left && !in_right,
+        }
+    }
+}
+
+//constructive solid geometry, two closed objects combined into one. Both sides have to be closed (spheres, capped cylinders,
+//boxes, watertight meshes) since the inside is worked out from which way the ray crosses their surfaces
+//the nodes can be nested, a csg is closed too
+//https://en.wikipedia.org/wiki/Constructive_solid_geometry
+pub struct Csg {
+    left: Rc<dyn Hittable>,
+    right: Rc<dyn Hittable>,
+    op: CsgOp,
+    bbox: AABB,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, left: Rc<dyn Hittable>, right: Rc<dyn Hittable>) -> Csg {
+        let (a, b) = (left.bounding_box(), right.bounding_box());
+        let bbox = match op {
+            CsgOp::Union => AABB::new_boxes(&a, &b),
+            //boxes that dont overlap leave nothing, an inverted box would give the bvh a negative surface area
+            CsgOp::Intersection => {
+                let overlap = AABB {
+                    x: Interval::intersect(&a.x, &b.x),
+                    y: Interval::intersect(&a.y, &b.y),
+                    z: Interval::intersect(&a.z, &b.z),
+                };
+                if overlap.is_empty() {
+                    AABB::EMPTY
+                } else {
+                    AABB::new(overlap.x, overlap.y, overlap.z)
+                }
+            }
+            CsgOp::Difference => a,
+        };
+
+        Csg {
+            left,
+            right,
+            op,
+            bbox,
+        }
+    }
+
+    //walks the crossings of both sides in order and keeps the ones where the combined inside changes
+    //the sides are asked for everything past ray_t.min, not just up to ray_t.max: if the ray is inside the left
+    //object and its exit is past ray_t.max we still need to know that to decide about a crossing of the right one
+    fn boundary(&self, r: &Ray, ray_t: Interval, first_only: bool, hits: &mut Vec<HitRecord>) {
+        if !self.bbox.hit(r, ray_t) {
+            return;
+        }
+
+        let ahead = Interval::new(ray_t.min, f64::INFINITY);
+        let mut left_hits = Vec::new();
+        let mut right_hits = Vec::new();
+        self.left.hit_all(r, ahead, &mut left_hits);
+        if left_hits.is_empty() && !matches!(self.op, CsgOp::Union) {
+            //intersection and difference are both empty where left is
+            return;
+        }
+        self.right.hit_all(r, ahead, &mut right_hits);
+
+        //coming out through the first crossing means the ray starts inside
+        let mut in_left = left_hits.first().is_some_and(|h| !h.front_face);
+        let mut in_right = right_hits.first().is_some_and(|h| !h.front_face);
+
+        let mut left_hits = left_hits.into_iter().peekable();
+        let mut right_hits = right_hits.into_iter().peekable();
+        loop {
+            let from_left = match (left_hits.peek(), right_hits.peek()) {
+                (Some(a), Some(b)) => a.t <= b.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+
+            let before = self.op.inside(in_left, in_right);
+            let mut rec = if from_left {
+                let rec = left_hits.next().unwrap();
+                in_left = rec.front_face;
+                rec
+            } else {
+                let rec = right_hits.next().unwrap();
+                in_right = rec.front_face;
+                rec
+            };
+            let after = self.op.inside(in_left, in_right);
+
+            if rec.t > ray_t.max {
+                break;
+            }
+            if before == after {
+                continue;
+            }
+
+            //normal already faces the ray, only whether this is a way in or out of the result can change
+            //(the inside of the cut out part of a difference is outside of the result)
+            rec.front_face = after;
+            hits.push(rec);
+            if first_only {
+                return;
+            }
+        }
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let mut hits = Vec::with_capacity(1);
+        self.boundary(r, ray_t, true, &mut hits);
+
+        match hits.pop() {
+            Some(hit) => {
+                *rec = hit;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn hit_all(&self, r: &Ray, ray_t: Interval, hits: &mut Vec<HitRecord>) {
+        self.boundary(r, ray_t, false, hits);
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::vec3::Point3;
+
+    fn sphere(x: f64) -> Rc<dyn Hittable> {
+        Rc::new(Sphere::new(
+            Point3::new(x, 0.0, 0.0),
+            1.0,
+            Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ))
+    }
+
+    #[test]
+    fn intersection_box() {
+        let apart = Csg::new(CsgOp::Intersection, sphere(0.0), sphere(5.0));
+        assert!(apart.bounding_box().is_empty());
+        assert_eq!(apart.bounding_box().surface_area(), 0.0);
+
+        let overlapping = Csg::new(CsgOp::Intersection, sphere(0.0), sphere(1.5));
+        let bbox = overlapping.bounding_box();
+        assert_eq!((bbox.x.min, bbox.x.max), (0.5, 1.0));
+        assert_eq!((bbox.y.min, bbox.y.max), (-1.0, 1.0));
+    }
+}
@@ -0,0 +1,382 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::AABB,
+    hittable::*,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+};
+
+//Flat is a ribbon that always turns to face the ray (cheap, what you want for hair seen from far away)
+//Cylinder is an actual round tube (getting thinner with the width), better for thick strands and grass blades seen up close
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CurveType {
+    Flat,
+    Cylinder,
+}
+
+//one straight piece of the refined curve, u0 and u1 are where it sits on the original curve parameter
+struct CurveSegment {
+    p0: Point3,
+    p1: Point3,
+    r0: f64,
+    r1: f64,
+    u0: f64,
+    u1: f64,
+}
+
+//cubic bezier curve with a width that goes linearly from width0 at the root to width1 at the tip
+//instead of solving the curve intersection directly (pbrt does recursive subdivision at hit time) we cut it into
+//enough straight segments up front so it looks smooth, then each segment is intersected as a round cone/ribbon
+//https://pbr-book.org/3ed-2018/Shapes/Curves
+pub struct Curve {
+    cp: [Point3; 4],
+    segments: Vec<CurveSegment>,
+    kind: CurveType,
+    mat: Rc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Curve {
+    pub fn new(
+        cp: [Point3; 4],
+        width0: f64,
+        width1: f64,
+        kind: CurveType,
+        mat: Rc<dyn Material>,
+    ) -> Curve {
+        let r0 = 0.5 * width0.max(0.0);
+        let r1 = 0.5 * width1.max(0.0);
+
+        //the curve never leaves the hull of its control points, so the box of the points grown by the radius covers it
+        let rmax = r0.max(r1);
+        let rvec = Vec3::new(rmax, rmax, rmax);
+        let mut bbox = AABB::EMPTY;
+        for p in cp {
+            bbox = AABB::new_boxes(&bbox, &AABB::new_point(p - rvec, p + rvec));
+        }
+
+        let n = Curve::segment_count(&cp, rmax);
+        let mut segments = Vec::with_capacity(n);
+        for i in 0..n {
+            let u0 = i as f64 / n as f64;
+            let u1 = (i + 1) as f64 / n as f64;
+
+            segments.push(CurveSegment {
+                p0: Curve::eval_bezier(&cp, u0),
+                p1: Curve::eval_bezier(&cp, u1),
+                r0: r0 + (r1 - r0) * u0,
+                r1: r0 + (r1 - r0) * u1,
+                u0,
+                u1,
+            });
+        }
+
+        Curve {
+            cp,
+            segments,
+            kind,
+            mat,
+            bbox,
+        }
+    }
+
+    //how many pieces we need so the straight segments stay within a fraction of the width from the real curve
+    //this is the refinement depth estimate from pbrt, L0 measures how far the control polygon is from being a line
+    fn segment_count(cp: &[Point3; 4], radius: f64) -> usize {
+        let mut l0: f64 = 0.0;
+        for i in 0..2 {
+            let d = cp[i] - 2.0 * cp[i + 1] + cp[i + 2];
+            l0 = l0.max(d.x().abs()).max(d.y().abs()).max(d.z().abs());
+        }
+
+        let eps = (0.1 * radius).max(1e-6);
+        let depth = if l0 > 0.0 {
+            ((std::f64::consts::SQRT_2 * 6.0 * l0 / (8.0 * eps)).log2() * 0.5).ceil()
+        } else {
+            0.0
+        };
+
+        1 << (depth.clamp(0.0, 6.0) as usize)
+    }
+
+    //de casteljau in closed form
+    pub fn eval_bezier(cp: &[Point3; 4], u: f64) -> Point3 {
+        let s = 1.0 - u;
+        s * s * s * cp[0] + 3.0 * s * s * u * cp[1] + 3.0 * s * u * u * cp[2] + u * u * u * cp[3]
+    }
+
+    pub fn bezier_tangent(cp: &[Point3; 4], u: f64) -> Vec3 {
+        let s = 1.0 - u;
+        3.0 * s * s * (cp[1] - cp[0])
+            + 6.0 * s * u * (cp[2] - cp[1])
+            + 3.0 * u * u * (cp[3] - cp[2])
+    }
+
+    //round cone intersection: a capsule with the root radius at one end and the tip radius at the other, it is the
+    //shape a sphere sweeps out while it shrinks along the segment, so the spheres at the joints close the gaps
+    //rd has to be a unit vector here, returns the ray t and where along the segment the sphere we hit sits (0..1)
+    //https://iquilezles.org/articles/intersectors/
+    fn hit_capsule(
+        seg: &CurveSegment,
+        ro: Point3,
+        rd: Vec3,
+        t_range: Interval,
+    ) -> Option<(f64, f64)> {
+        let (ra, rb) = (seg.r0, seg.r1);
+        let rr = ra - rb;
+        let ba = seg.p1 - seg.p0;
+        let oa = ro - seg.p0;
+
+        let baba = Vec3::dot(ba, ba);
+        let bard = Vec3::dot(ba, rd);
+        let baoa = Vec3::dot(ba, oa);
+        let rdoa = Vec3::dot(rd, oa);
+        let oaoa = Vec3::dot(oa, oa);
+
+        let mut best: Option<(f64, f64)> = None;
+        let mut consider = |t: f64, s: f64| {
+            if t_range.surrounds(t) && best.is_none_or(|(bt, _)| t < bt) {
+                best = Some((t, s));
+            }
+        };
+
+        //one end sphere swallows the other, there is no body, just the bigger sphere
+        let d2 = baba - rr * rr;
+        if d2 <= 1e-12 {
+            let (center, r, end) = if ra >= rb {
+                (seg.p0, ra, 0.0)
+            } else {
+                (seg.p1, rb, 1.0)
+            };
+            let oc = ro - center;
+            let b = Vec3::dot(rd, oc);
+            let h = b * b - Vec3::dot(oc, oc) + r * r;
+            if h >= 0.0 {
+                consider(-b - h.sqrt(), end);
+                consider(-b + h.sqrt(), end);
+            }
+            return best;
+        }
+
+        //body, both roots because the ray might start inside (a transmitted ray leaving the strand)
+        //y / d2 is where the sphere that touches the hit point sits on the segment
+        let a = d2 - bard * bard;
+        let b = d2 * rdoa - baoa * bard + bard * rr * ra;
+        let c = d2 * oaoa - baoa * baoa + 2.0 * baoa * rr * ra - baba * ra * ra;
+        let h = b * b - a * c;
+        if a > 1e-12 && h >= 0.0 {
+            let sq = h.sqrt();
+            for t in [(-b - sq) / a, (-b + sq) / a] {
+                let y = baoa - ra * rr + t * bard;
+                if y > 0.0 && y < d2 {
+                    consider(t, y / d2);
+                }
+            }
+        }
+
+        //the two end spheres, only the part that sticks out past the body counts
+        for (center, r, end) in [(seg.p0, ra, 0.0), (seg.p1, rb, 1.0)] {
+            let oc = ro - center;
+            let b = Vec3::dot(rd, oc);
+            let c = Vec3::dot(oc, oc) - r * r;
+            let h = b * b - c;
+            if h < 0.0 {
+                continue;
+            }
+
+            let sq = h.sqrt();
+            for t in [-b - sq, -b + sq] {
+                let y = baoa - ra * rr + t * bard;
+                if (end == 0.0 && y <= 0.0) || (end == 1.0 && y >= d2) {
+                    consider(t, end);
+                }
+            }
+        }
+
+        best
+    }
+
+    //closest approach between the ray and the segment line, it is a hit if the gap is smaller than the width there
+    //the ribbon has no thickness so the hit is right at the closest approach point
+    fn hit_ribbon(
+        seg: &CurveSegment,
+        ro: Point3,
+        rd: Vec3,
+        t_range: Interval,
+    ) -> Option<(f64, f64)> {
+        let ba = seg.p1 - seg.p0;
+        let w0 = ro - seg.p0;
+
+        let b = Vec3::dot(rd, ba);
+        let c = Vec3::dot(ba, ba);
+        let d = Vec3::dot(rd, w0);
+        let e = Vec3::dot(ba, w0);
+        let den = c - b * b;
+
+        //looking straight down the segment, the ribbon is seen edge on
+        if den.abs() < 1e-12 {
+            return None;
+        }
+
+        let s = ((e - b * d) / den).clamp(0.0, 1.0);
+        let on_axis = seg.p0 + s * ba;
+        let t = Vec3::dot(on_axis - ro, rd);
+
+        if !t_range.surrounds(t) {
+            return None;
+        }
+
+        let r = seg.r0 + (seg.r1 - seg.r0) * s;
+        if (ro + t * rd - on_axis).squared_length() > r * r {
+            return None;
+        }
+
+        Some((t, s))
+    }
+}
+
+impl Hittable for Curve {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if !self.bbox.hit(r, ray_t) {
+            return false;
+        }
+
+        //work with a unit direction so the capsule math stays simple, t gets scaled back at the end
+        let dir_len = r.direction().length();
+        let rd = r.direction() / dir_len;
+        let ro = r.origin();
+        let mut t_range = Interval::new(ray_t.min * dir_len, ray_t.max * dir_len);
+
+        let mut closest: Option<(f64, f64, usize)> = None;
+        for (i, seg) in self.segments.iter().enumerate() {
+            let hit = match self.kind {
+                CurveType::Flat => Curve::hit_ribbon(seg, ro, rd, t_range),
+                CurveType::Cylinder => Curve::hit_capsule(seg, ro, rd, t_range),
+            };
+
+            if let Some((t, s)) = hit {
+                t_range.max = t;
+                closest = Some((t, s, i));
+            }
+        }
+
+        let Some((t, s, i)) = closest else {
+            return false;
+        };
+
+        let seg = &self.segments[i];
+        let u = seg.u0 + (seg.u1 - seg.u0) * s;
+        let tangent = Vec3::unit_vector(Curve::bezier_tangent(&self.cp, u));
+        let p = ro + t * rd;
+        let on_axis = seg.p0 + s * (seg.p1 - seg.p0);
+        let radius = (seg.r0 + (seg.r1 - seg.r0) * s).max(1e-12);
+
+        //the ribbon faces the ray, the cylinder has its real normal pointing away from the axis
+        let outward_normal = match self.kind {
+            CurveType::Flat => {
+                let n = -rd - Vec3::dot(-rd, tangent) * tangent;
+                if n.near_zero() {
+                    return false;
+                }
+                Vec3::unit_vector(n)
+            }
+            //from the center of the sphere that was hit, so a tapered strand leans towards the thin end. The part
+            //around the curve is taken against the smooth tangent so the normals dont jump at the segment joints
+            CurveType::Cylinder => {
+                let n = Vec3::unit_vector(p - on_axis);
+                let axis = seg.p1 - seg.p0;
+                let along = if axis.near_zero() {
+                    0.0
+                } else {
+                    Vec3::dot(n, Vec3::unit_vector(axis)).clamp(-1.0, 1.0)
+                };
+                let around = Vec3::unit_vector(n - Vec3::dot(n, tangent) * tangent);
+                (1.0 - along * along).sqrt() * around + along * tangent
+            }
+        };
+
+        rec.t = t / dir_len;
+        rec.p = p;
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(r, outward_normal);
+        rec.dpdu = tangent;
+
+        //v is where across the width the ray went through (0 one edge, 0.5 the middle, 1 the other edge)
+        //measured along normal x tangent, which is the y axis of the shading frame the hair material builds
+        let side = Vec3::unit_vector(Vec3::cross(rec.normal, tangent));
+        let view = -rd - Vec3::dot(-rd, tangent) * tangent;
+        let across = if view.near_zero() {
+            0.0
+        } else {
+            //offset of the ray from the axis in the plane that faces the ray, same for both curve types
+            let view = Vec3::unit_vector(view);
+            let offset = ro - on_axis;
+            let offset = offset - Vec3::dot(offset, tangent) * tangent;
+            let offset = offset - Vec3::dot(offset, view) * view;
+            Vec3::dot(offset, side) / radius
+        };
+
+        rec.u = u;
+        rec.v = (0.5 * (across.clamp(-1.0, 1.0) + 1.0)).clamp(0.0, 1.0);
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::color::Color;
+    use crate::material::Lambertian;
+
+    //a straight strand along x that goes from 1 wide at the root to 0.2 at the tip, it stays a single segment
+    //seen from above, the side of the round cone is where the spheres sweeping along it touch their common tangent cone
+    #[test]
+    fn straight_tapered_curve() {
+        let (r0, r1, len) = (0.5, 0.1, 4.0);
+        let cp = [0.0, 1.0, 2.0, 3.0]
+            .map(|i| i * len / 3.0)
+            .map(|x| Point3::new(x, 0.0, 0.0));
+        let mat = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let curve = Curve::new(cp, 2.0 * r0, 2.0 * r1, CurveType::Cylinder, mat);
+        assert_eq!(curve.segments.len(), 1);
+
+        let sin = (r0 - r1) / len;
+        let cos = (1.0 - sin * sin).sqrt();
+        for x in [0.5, 2.0, 3.5] {
+            //the sphere at center c reaches x on its side: c + r(c) sin = x, with r(c) = r0 - (r0 - r1) c / len
+            let c = (x - r0 * sin) / (1.0 - (r0 - r1) / len * sin);
+            let height = (r0 - (r0 - r1) * c / len) * cos;
+
+            let r = Ray::new(Point3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0), 0.0);
+            let mut rec = HitRecord::new();
+            assert!(curve.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+            assert!(
+                (rec.p.z() - height).abs() < 1e-9,
+                "at x {}: {}",
+                x,
+                rec.p.z()
+            );
+            assert!((rec.t - (5.0 - height) / 2.0).abs() < 1e-9);
+            assert!((rec.normal - Vec3::new(sin, 0.0, cos)).length() < 1e-9);
+        }
+
+        //past the root it is the root sphere, past the tip it is the tip sphere
+        let mut rec = HitRecord::new();
+        let r = Ray::new(Point3::new(-0.3, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(curve.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+        assert!((rec.p.z() - 0.4).abs() < 1e-9);
+
+        //the ends are thinner than the middle of the widths, a constant radius would still hit this
+        let r = Ray::new(Point3::new(3.9, 0.2, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(!curve.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+    }
+}
@@ -0,0 +1,164 @@
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use crate::aabb::AABB;
+use crate::disk::disk_bbox;
+use crate::hittable::*;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::ONB;
+use crate::ray::Ray;
+use crate::utils::random_double;
+use crate::vec3::{Point3, Vec3};
+
+//cylinder from base to base + axis, capped ones are closed with a disk on each end, uncapped ones are just the tube
+//u goes around (angle / 2pi), v goes up the side (0 at base, 1 at the top), on the caps v is the distance from the middle / radius
+pub struct Cylinder {
+    base: Point3,
+    frame: ONB,
+    height: f64,
+    radius: f64,
+    capped: bool,
+    mat: Rc<dyn Material>,
+    bbox: AABB,
+    side_area: f64,
+    cap_area: f64,
+}
+
+#[derive(Copy, Clone)]
+enum Part {
+    Side,
+    Bottom,
+    Top,
+}
+
+impl Cylinder {
+    pub fn new(
+        base: Point3,
+        axis: Vec3,
+        radius: f64,
+        capped: bool,
+        mat: Rc<dyn Material>,
+    ) -> Cylinder {
+        let radius = radius.max(0.0);
+        let height = axis.length();
+
+        Cylinder {
+            base,
+            frame: ONB::new(axis),
+            height,
+            radius,
+            capped,
+            mat,
+            bbox: AABB::new_boxes(
+                &disk_bbox(base, axis, radius),
+                &disk_bbox(base + axis, axis, radius),
+            ),
+            side_area: 2.0 * PI * radius * height,
+            cap_area: if capped { PI * radius * radius } else { 0.0 },
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        self.side_area + 2.0 * self.cap_area
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        //local space, the axis is z and the base is at the origin
+        let o = self.frame.to_local(r.origin() - self.base);
+        let d = self.frame.to_local(r.direction());
+        let r2 = self.radius * self.radius;
+
+        let mut closest = ray_t.max;
+        let mut part = None;
+
+        //the infinite tube x^2 + y^2 = r^2 cut to 0 <= z <= height
+        let a = d.x() * d.x() + d.y() * d.y();
+        let h = o.x() * d.x() + o.y() * d.y();
+        let c = o.x() * o.x() + o.y() * o.y() - r2;
+        let discriminant = h * h - a * c;
+        if a > 1e-12 && discriminant >= 0.0 {
+            let sqrtd = discriminant.sqrt();
+            for t in [(-h - sqrtd) / a, (-h + sqrtd) / a] {
+                let z = o.z() + t * d.z();
+                if ray_t.surrounds(t) && t < closest && (0.0..=self.height).contains(&z) {
+                    closest = t;
+                    part = Some(Part::Side);
+                    break;
+                }
+            }
+        }
+
+        if self.capped && d.z().abs() > 1e-12 {
+            for (z, cap) in [(0.0, Part::Bottom), (self.height, Part::Top)] {
+                let t = (z - o.z()) / d.z();
+                let (x, y) = (o.x() + t * d.x(), o.y() + t * d.y());
+                if ray_t.surrounds(t) && t < closest && x * x + y * y <= r2 {
+                    closest = t;
+                    part = Some(cap);
+                }
+            }
+        }
+
+        let Some(part) = part else {
+            return false;
+        };
+
+        let p = o + closest * d;
+        let phi = p.y().atan2(p.x()) + PI;
+        let dist = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        let outward_normal = match part {
+            Part::Side => Vec3::new(p.x(), p.y(), 0.0) / self.radius,
+            Part::Bottom => Vec3::new(0.0, 0.0, -1.0),
+            Part::Top => Vec3::new(0.0, 0.0, 1.0),
+        };
+
+        rec.t = closest;
+        rec.p = r.at(closest);
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(r, self.frame.transform(outward_normal));
+        rec.u = phi / (2.0 * PI);
+        rec.v = match part {
+            Part::Side => p.z() / self.height.max(1e-12),
+            _ => dist / self.radius.max(1e-12),
+        };
+        rec.dpdu = self
+            .frame
+            .transform(Vec3::new(-p.y(), p.x(), 0.0) / dist.max(1e-12));
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, dir: Vec3) -> f64 {
+        area_pdf_value(self, origin, dir, self.area())
+    }
+
+    //uniform over the whole surface, first pick the side or a cap by their share of the area
+    fn random(&self, origin: Vec3) -> Vec3 {
+        let phi = 2.0 * PI * random_double();
+        let pick = random_double() * self.area();
+
+        let (r, z) = if pick < self.side_area {
+            (self.radius, random_double() * self.height)
+        } else {
+            let z = if pick < self.side_area + self.cap_area {
+                0.0
+            } else {
+                self.height
+            };
+            (self.radius * random_double().sqrt(), z)
+        };
+
+        let p = self.base
+            + self
+                .frame
+                .transform(Vec3::new(r * phi.cos(), r * phi.sin(), z));
+        p - origin
+    }
+}
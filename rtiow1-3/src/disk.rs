@@ -0,0 +1,20 @@
+use crate::aabb::AABB;
+use crate::vec3::{Point3, Vec3};
+
+//the flat disk and annulus are Quad::new_disk and Quad::new_annulus, this is what the round shapes (cylinder, cone,
+//torus) share for their bounding boxes
+
+//a circle tilted by axis reaches out radius * sin(angle between axis and x) along x, and the same for y and z
+pub fn disk_extent(axis: Vec3, radius: f64) -> Vec3 {
+    let a = Vec3::unit_vector(axis);
+    Vec3::new(
+        radius * (1.0 - a.x() * a.x()).max(0.0).sqrt(),
+        radius * (1.0 - a.y() * a.y()).max(0.0).sqrt(),
+        radius * (1.0 - a.z() * a.z()).max(0.0).sqrt(),
+    )
+}
+
+pub fn disk_bbox(center: Point3, axis: Vec3, radius: f64) -> AABB {
+    let e = disk_extent(axis, radius);
+    AABB::new_point(center - e, center + e)
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::rc::Rc;
+
+use gltf::camera::Projection;
+use gltf::image::Format;
+use gltf::khr_lights_punctual::Kind;
+use image::RgbImage;
+
+use crate::bvh::LinearBvh;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::hittable::{Hittable, Transform};
+use crate::hittable_list::HittableList;
+use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
+use crate::mat4::Mat4;
+use crate::material::{Material, MetallicRoughness, NormalMapped};
+use crate::mesh::TriangleMesh;
+use crate::texture::{ImageTexture, Texture};
+use crate::vec3::{Point3, Vec3};
+
+//everything a glTF file brings along. world is already one bvh over all the meshes, lights go into cam.scene_lights
+//and the camera (the first one in the scene, if there is one) can be put onto a Camera with apply
+pub struct GltfScene {
+    pub world: HittableList,
+    pub lights: Vec<Rc<dyn Light>>,
+    pub camera: Option<GltfCamera>,
+}
+
+//a glTF camera looks down its local -z with +y up
+pub struct GltfCamera {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    pub vfov: f64,
+    pub aspect_ratio: Option<f64>,
+}
+
+impl GltfCamera {
+    pub fn apply(&self, cam: &mut Camera) {
+        cam.lookfrom = self.lookfrom;
+        cam.lookat = self.lookat;
+        cam.vup = self.vup;
+        cam.vfov = self.vfov;
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            cam.aspect_ratio = aspect_ratio;
+        }
+    }
+}
+
+//glTF lights are photometric (candela, lux), this is the 683 lm/W that blender's exporter uses to go back to watts
+const LUMENS_PER_WATT: f64 = 683.0;
+
+//loads a .gltf or .glb with everything it references (buffers and images next to it, or embedded)
+//each mesh is built once in its own space (one TriangleMesh per primitive) and every node that uses it places it with a Transform
+//only triangle primitives and the first uv set are supported, the rest is skipped with a warning
+pub fn load_gltf(path: &str) -> std::io::Result<GltfScene> {
+    let (document, buffers, images) = gltf::import(path)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("gltf {}: {}", path, e)))?;
+
+    let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    else {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("gltf {}: no scene in file", path),
+        ));
+    };
+
+    let mut loader = Loader {
+        buffers: &buffers,
+        images: &images,
+        materials: HashMap::new(),
+        meshes: HashMap::new(),
+        textures: HashMap::new(),
+        list: HittableList::new(),
+        lights: Vec::new(),
+        camera: None,
+    };
+
+    for node in scene.nodes() {
+        loader.node(&node, &Mat4::IDENTITY);
+    }
+
+    Ok(GltfScene {
+        world: HittableList::new_list(Rc::new(LinearBvh::new(loader.list))),
+        lights: loader.lights,
+        camera: loader.camera,
+    })
+}
+
+struct Loader<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    //None is the default material glTF uses for primitives without one
+    materials: HashMap<Option<usize>, Rc<dyn Material>>,
+    //None for meshes that had nothing we could use
+    meshes: HashMap<usize, Option<Rc<dyn Hittable>>>,
+    //by image and if it is srgb, the same image can be used as color and as data
+    textures: HashMap<(usize, bool), Rc<dyn Texture>>,
+    list: HittableList,
+    lights: Vec<Rc<dyn Light>>,
+    camera: Option<GltfCamera>,
+}
+
+impl Loader<'_> {
+    fn node(&mut self, node: &gltf::Node, parent: &Mat4) {
+        let local = Mat4::from_columns(node.transform().matrix().map(|c| c.map(|x| x as f64)));
+        let world = *parent * local;
+
+        if let Some(mesh) = node.mesh()
+            && let Some(object) = self.mesh(&mesh)
+        {
+            if world == Mat4::IDENTITY {
+                self.list.add(object);
+            } else if world.inverse().is_some() {
+                self.list.add(Rc::new(Transform::new(object, world)));
+            }
+        }
+
+        if let Some(camera) = node.camera() {
+            self.camera(&camera, &world);
+        }
+
+        if let Some(light) = node.light() {
+            self.light(&light, &world);
+        }
+
+        for child in node.children() {
+            self.node(&child, &world);
+        }
+    }
+
+    fn mesh(&mut self, mesh: &gltf::Mesh) -> Option<Rc<dyn Hittable>> {
+        if let Some(object) = self.meshes.get(&mesh.index()) {
+            return object.clone();
+        }
+
+        let mut primitives = HittableList::new();
+        for primitive in mesh.primitives() {
+            if let Some(object) = self.primitive(&primitive) {
+                primitives.add(object);
+            }
+        }
+
+        let object: Option<Rc<dyn Hittable>> = match primitives.objects.len() {
+            0 => None,
+            1 => primitives.objects.pop(),
+            _ => Some(Rc::new(LinearBvh::new(primitives))),
+        };
+        self.meshes.insert(mesh.index(), object.clone());
+        object
+    }
+
+    fn primitive(&mut self, primitive: &gltf::Primitive) -> Option<Rc<dyn Hittable>> {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            eprintln!(
+                "gltf: skipping a {:?} primitive, only triangles are supported",
+                primitive.mode()
+            );
+            return None;
+        }
+
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+        let positions: Vec<Point3> = reader
+            .read_positions()?
+            .map(|p| Point3::new(p[0] as f64, p[1] as f64, p[2] as f64))
+            .collect();
+
+        //no indices means every 3 vertices are a triangle
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        if indices.len() < 3 {
+            return None;
+        }
+
+        let mat = self.material(&primitive.material());
+        let mut mesh = TriangleMesh::new(positions, indices, mat);
+
+        if let Some(normals) = reader.read_normals() {
+            mesh.normals = normals
+                .map(|n| Vec3::unit_vector(Vec3::new(n[0] as f64, n[1] as f64, n[2] as f64)))
+                .collect();
+        }
+
+        //glTF has the uv origin in the top left, ImageTexture has it in the bottom left
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            mesh.uvs = uvs
+                .into_f32()
+                .map(|t| [t[0] as f64, 1.0 - t[1] as f64])
+                .collect();
+        }
+
+        //COLOR_0 multiplies the base color
+        if let Some(colors) = reader.read_colors(0) {
+            mesh.colors = colors
+                .into_rgb_f32()
+                .map(|c| Color::new(c[0] as f64, c[1] as f64, c[2] as f64))
+                .collect();
+        }
+
+        //the 4th component is the handedness of the bitangent, -1 on mirrored uvs
+        if let Some(tangents) = reader.read_tangents() {
+            (mesh.tangents, mesh.tangent_signs) = tangents
+                .map(|t| {
+                    (
+                        Vec3::new(t[0] as f64, t[1] as f64, t[2] as f64),
+                        t[3] as f64,
+                    )
+                })
+                .unzip();
+        }
+
+        Some(Rc::new(mesh.into_bvh()))
+    }
+
+    fn material(&mut self, material: &gltf::Material) -> Rc<dyn Material> {
+        if let Some(mat) = self.materials.get(&material.index()) {
+            return mat.clone();
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, _] = pbr.base_color_factor();
+        let mut mat = MetallicRoughness::new(
+            Color::new(r as f64, g as f64, b as f64),
+            pbr.metallic_factor() as f64,
+            pbr.roughness_factor() as f64,
+        );
+
+        mat.base_color_tex = pbr
+            .base_color_texture()
+            .map(|info| self.texture(&info.texture(), true));
+        mat.metallic_roughness_tex = pbr
+            .metallic_roughness_texture()
+            .map(|info| self.texture(&info.texture(), false));
+
+        let [r, g, b] = material.emissive_factor();
+        let strength = material.emissive_strength().unwrap_or(1.0) as f64;
+        mat.emission = strength * Color::new(r as f64, g as f64, b as f64);
+        mat.emission_tex = material
+            .emissive_texture()
+            .map(|info| self.texture(&info.texture(), true));
+
+        let mut mat: Rc<dyn Material> = Rc::new(mat);
+        if let Some(normal) = material.normal_texture() {
+            let tex = self.texture(&normal.texture(), false);
+            mat = Rc::new(NormalMapped::new(mat, tex, normal.scale() as f64));
+        }
+
+        self.materials.insert(material.index(), mat.clone());
+        mat
+    }
+
+    fn texture(&mut self, texture: &gltf::Texture, srgb: bool) -> Rc<dyn Texture> {
+        let index = texture.source().index();
+        if let Some(tex) = self.textures.get(&(index, srgb)) {
+            return tex.clone();
+        }
+
+        let mut image = ImageTexture::from_image(to_rgb(&self.images[index]), srgb);
+        image.repeat = true;
+        let tex: Rc<dyn Texture> = Rc::new(image);
+
+        self.textures.insert((index, srgb), tex.clone());
+        tex
+    }
+
+    //only the first camera counts, a scene can have several but we render one view
+    fn camera(&mut self, camera: &gltf::Camera, world: &Mat4) {
+        if self.camera.is_some() {
+            return;
+        }
+
+        let Projection::Perspective(perspective) = camera.projection() else {
+            eprintln!("gltf: skipping an orthographic camera");
+            return;
+        };
+
+        let lookfrom = world.transform_point(Point3::new(0.0, 0.0, 0.0));
+        let forward = Vec3::unit_vector(world.transform_vector(Vec3::new(0.0, 0.0, -1.0)));
+        self.camera = Some(GltfCamera {
+            lookfrom,
+            lookat: lookfrom + forward,
+            vup: Vec3::unit_vector(world.transform_vector(Vec3::new(0.0, 1.0, 0.0))),
+            vfov: (perspective.yfov() as f64).to_degrees(),
+            aspect_ratio: perspective.aspect_ratio().map(|a| a as f64),
+        });
+    }
+
+    //range is ignored, our lights fall off with distance squared all the way
+    fn light(&mut self, light: &gltf::khr_lights_punctual::Light, world: &Mat4) {
+        let [r, g, b] = light.color();
+        let color = Color::new(r as f64, g as f64, b as f64);
+        let intensity = light.intensity() as f64 / LUMENS_PER_WATT;
+
+        let position = world.transform_point(Point3::new(0.0, 0.0, 0.0));
+        let forward = Vec3::unit_vector(world.transform_vector(Vec3::new(0.0, 0.0, -1.0)));
+
+        let light: Rc<dyn Light> = match light.kind() {
+            //candela is already per steradian, which is what PointLight wants
+            Kind::Point => Rc::new(PointLight::new(position, intensity * color)),
+            Kind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => Rc::new(SpotLight::new(
+                position,
+                position + forward,
+                intensity * color,
+                (outer_cone_angle as f64).to_degrees(),
+                (inner_cone_angle as f64).to_degrees(),
+            )),
+            //lux is per square meter, DirectionalLight points towards the light
+            Kind::Directional => Rc::new(DirectionalLight::new(-forward, intensity * color, 0.0)),
+        };
+
+        self.lights.push(light);
+    }
+}
+
+//whatever the file had, 8 bit rgb is what ImageTexture keeps. alpha is dropped, grey images get copied into all channels
+fn to_rgb(data: &gltf::image::Data) -> RgbImage {
+    let (channels, bytes) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+
+    let channel = |i: usize| -> u8 {
+        let b = &data.pixels[i * bytes..(i + 1) * bytes];
+        match bytes {
+            1 => b[0],
+            //16 bit is little endian, keep the high byte
+            2 => b[1],
+            _ => {
+                let f = f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                (f.clamp(0.0, 1.0) * 255.0).round() as u8
+            }
+        }
+    };
+
+    RgbImage::from_fn(data.width, data.height, |x, y| {
+        let first = (y as usize * data.width as usize + x as usize) * channels;
+        let rgb = match channels {
+            1 => [channel(first); 3],
+            2 => [channel(first), channel(first + 1), 0],
+            _ => [channel(first), channel(first + 1), channel(first + 2)],
+        };
+        image::Rgb(rgb)
+    })
+}
@@ -0,0 +1,403 @@
+use std::f64::consts::{LN_2, PI};
+
+use crate::{
+    bsdf::{BsdfSample, BxdfFlags},
+    color::{Color, luminance},
+    hittable::HitRecord,
+    material::{Material, fresnel_dielectric},
+    utils::random_double,
+    vec3::Vec3,
+};
+
+//how many bounces inside the fiber get their own lobe, everything after that is lumped into one last lobe
+const P_MAX: usize = 3;
+
+//Marschner/d'Eon hair scattering as done in pbrt, see https://pbr-book.org/3ed-2018/Light_Transport_I_Surface_Reflection/Hair_Scattering
+//the fiber is a glass like cylinder with pigment inside. Light either reflects off the surface (R), goes through (TT),
+//or goes in, reflects off the back wall and comes out (TRT). Each of these is a lobe p = 0, 1, 2 and the rest is p = 3
+//every lobe is split into a longitudinal part (Mp, angle along the hair) and an azimuthal part (Np, angle around the hair)
+//and Ap is how much of the light survives the fresnel bounces and the absorption inside
+
+//the local frame is x along the hair (dpdu from the curve), z the normal and y across the hair
+//h is where across the width the ray hit, -1 one edge, 0 the middle, 1 the other edge (v from the curve mapped to -1..1)
+pub struct HairMaterial {
+    sigma_a: Color,
+    eta: f64,
+    v: [f64; P_MAX + 1],
+    s: f64,
+    sin_2k_alpha: [f64; 3],
+    cos_2k_alpha: [f64; 3],
+}
+
+impl HairMaterial {
+    //beta_m is the longitudinal roughness, beta_n the azimuthal one (both 0..1), alpha is the tilt of the scales on the fiber in degrees (~2)
+    pub fn new(sigma_a: Color, beta_m: f64, beta_n: f64, alpha: f64) -> HairMaterial {
+        let beta_m = beta_m.clamp(0.0, 1.0);
+        let beta_n = beta_n.clamp(0.0, 1.0);
+
+        //roughness to variance mappings fitted in the d'Eon paper
+        let mut v = [0.0; P_MAX + 1];
+        v[0] = (0.726 * beta_m + 0.812 * beta_m * beta_m + 3.7 * beta_m.powi(20)).powi(2);
+        v[1] = 0.25 * v[0];
+        v[2] = 4.0 * v[0];
+        for p in 3..=P_MAX {
+            v[p] = v[2];
+        }
+
+        let sqrt_pi_over_8 = 0.626657069;
+        let s =
+            sqrt_pi_over_8 * (0.265 * beta_n + 1.194 * beta_n * beta_n + 5.372 * beta_n.powi(22));
+
+        //the scales tilt each lobe by 2^k alpha, precomputed with the double angle formulas
+        let mut sin_2k_alpha = [0.0; 3];
+        let mut cos_2k_alpha = [0.0; 3];
+        sin_2k_alpha[0] = alpha.to_radians().sin();
+        cos_2k_alpha[0] = (1.0 - sin_2k_alpha[0] * sin_2k_alpha[0]).max(0.0).sqrt();
+        for i in 1..3 {
+            sin_2k_alpha[i] = 2.0 * cos_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+            cos_2k_alpha[i] = cos_2k_alpha[i - 1].powi(2) - sin_2k_alpha[i - 1].powi(2);
+        }
+
+        HairMaterial {
+            sigma_a,
+            eta: 1.55,
+            v,
+            s,
+            sin_2k_alpha,
+            cos_2k_alpha,
+        }
+    }
+
+    //hair color from the two pigments, eumelanin makes it brown/black and pheomelanin red/blonde
+    //concentrations around 0.3 are blonde, 1.3 brown, 8 black
+    pub fn new_melanin(
+        eumelanin: f64,
+        pheomelanin: f64,
+        beta_m: f64,
+        beta_n: f64,
+        alpha: f64,
+    ) -> HairMaterial {
+        let eumelanin_sigma_a = Color::new(0.419, 0.697, 1.37);
+        let pheomelanin_sigma_a = Color::new(0.187, 0.4, 1.05);
+        let sigma_a = eumelanin * eumelanin_sigma_a + pheomelanin * pheomelanin_sigma_a;
+
+        HairMaterial::new(sigma_a, beta_m, beta_n, alpha)
+    }
+
+    //pick the absorption that gives roughly the color you ask for after all the multiple scattering (fit from Chiang et al.)
+    pub fn new_color(color: Color, beta_m: f64, beta_n: f64, alpha: f64) -> HairMaterial {
+        let bn = beta_n.clamp(0.0, 1.0);
+        let denom = 5.969 - 0.215 * bn + 2.532 * bn.powi(2) - 10.73 * bn.powi(3)
+            + 5.574 * bn.powi(4)
+            + 0.245 * bn.powi(5);
+
+        let mut sigma_a = Color::new(0.0, 0.0, 0.0);
+        for c in 0..3 {
+            sigma_a[c] = (color[c].max(1e-4).ln() / denom).powi(2);
+        }
+
+        HairMaterial::new(sigma_a, beta_m, beta_n, alpha)
+    }
+
+    //modified bessel function of the first kind, series expansion
+    fn i0(x: f64) -> f64 {
+        let mut val = 0.0;
+        let mut x2i = 1.0;
+        let mut ifact = 1.0;
+        let mut i4 = 1.0;
+        for i in 0..10 {
+            if i > 1 {
+                ifact *= i as f64;
+            }
+            val += x2i / (i4 * ifact * ifact);
+            x2i *= x * x;
+            i4 *= 4.0;
+        }
+        val
+    }
+
+    fn log_i0(x: f64) -> f64 {
+        if x > 12.0 {
+            x + 0.5 * (-(2.0 * PI).ln() + (1.0 / x).ln() + 1.0 / (8.0 * x))
+        } else {
+            HairMaterial::i0(x).ln()
+        }
+    }
+
+    //longitudinal scattering, for small variances the direct formula overflows so it is done in log space
+    fn mp(cos_theta_i: f64, cos_theta_o: f64, sin_theta_i: f64, sin_theta_o: f64, v: f64) -> f64 {
+        let a = cos_theta_i * cos_theta_o / v;
+        let b = sin_theta_i * sin_theta_o / v;
+
+        if v <= 0.1 {
+            (HairMaterial::log_i0(a) - b - 1.0 / v + LN_2 + (1.0 / (2.0 * v)).ln()).exp()
+        } else {
+            ((-b).exp() * HairMaterial::i0(a)) / ((1.0 / v).sinh() * 2.0 * v)
+        }
+    }
+
+    //attenuation of each lobe, t is the transmittance of one pass through the inside of the fiber
+    fn ap(cos_theta_o: f64, eta: f64, h: f64, t: Color) -> [Color; P_MAX + 1] {
+        let cos_gamma_o = (1.0 - h * h).max(0.0).sqrt();
+        let cos_theta = cos_theta_o * cos_gamma_o;
+        let f = fresnel_dielectric(cos_theta, eta);
+
+        let one = Color::new(1.0, 1.0, 1.0);
+        let mut ap = [Color::new(0.0, 0.0, 0.0); P_MAX + 1];
+        ap[0] = Color::new(f, f, f);
+        ap[1] = (1.0 - f) * (1.0 - f) * t;
+        for p in 2..P_MAX {
+            ap[p] = ap[p - 1] * t * f;
+        }
+
+        //geometric series of all the longer paths
+        let tf = t * f;
+        let rest = ap[P_MAX - 1] * tf;
+        for c in 0..3 {
+            ap[P_MAX][c] = rest[c] / (one[c] - tf[c]);
+        }
+
+        ap
+    }
+
+    //net azimuthal rotation of the lobe p
+    fn phi(p: usize, gamma_o: f64, gamma_t: f64) -> f64 {
+        2.0 * p as f64 * gamma_t - 2.0 * gamma_o + p as f64 * PI
+    }
+
+    fn logistic(x: f64, s: f64) -> f64 {
+        let x = x.abs();
+        (-x / s).exp() / (s * (1.0 + (-x / s).exp()).powi(2))
+    }
+
+    fn logistic_cdf(x: f64, s: f64) -> f64 {
+        1.0 / (1.0 + (-x / s).exp())
+    }
+
+    fn trimmed_logistic(x: f64, s: f64, a: f64, b: f64) -> f64 {
+        HairMaterial::logistic(x, s)
+            / (HairMaterial::logistic_cdf(b, s) - HairMaterial::logistic_cdf(a, s))
+    }
+
+    fn sample_trimmed_logistic(u: f64, s: f64, a: f64, b: f64) -> f64 {
+        let k = HairMaterial::logistic_cdf(b, s) - HairMaterial::logistic_cdf(a, s);
+        let x = -s * (1.0 / (u * k + HairMaterial::logistic_cdf(a, s)) - 1.0).ln();
+        x.clamp(a, b)
+    }
+
+    //azimuthal scattering, a logistic distribution centered on where the lobe exits
+    fn np(phi: f64, p: usize, s: f64, gamma_o: f64, gamma_t: f64) -> f64 {
+        let mut dphi = phi - HairMaterial::phi(p, gamma_o, gamma_t);
+        while dphi > PI {
+            dphi -= 2.0 * PI;
+        }
+        while dphi < -PI {
+            dphi += 2.0 * PI;
+        }
+        HairMaterial::trimmed_logistic(dphi, s, -PI, PI)
+    }
+
+    //the scales tilt the outgoing direction of each lobe a little (R up, TT and TRT down)
+    fn tilt(&self, p: usize, sin_theta_o: f64, cos_theta_o: f64) -> (f64, f64) {
+        let (sin_op, cos_op) = match p {
+            0 => (
+                sin_theta_o * self.cos_2k_alpha[1] - cos_theta_o * self.sin_2k_alpha[1],
+                cos_theta_o * self.cos_2k_alpha[1] + sin_theta_o * self.sin_2k_alpha[1],
+            ),
+            1 => (
+                sin_theta_o * self.cos_2k_alpha[0] + cos_theta_o * self.sin_2k_alpha[0],
+                cos_theta_o * self.cos_2k_alpha[0] - sin_theta_o * self.sin_2k_alpha[0],
+            ),
+            2 => (
+                sin_theta_o * self.cos_2k_alpha[2] + cos_theta_o * self.sin_2k_alpha[2],
+                cos_theta_o * self.cos_2k_alpha[2] - sin_theta_o * self.sin_2k_alpha[2],
+            ),
+            _ => (sin_theta_o, cos_theta_o),
+        };
+
+        (sin_op, cos_op.abs())
+    }
+
+    //everything about the refracted ray inside the fiber that f, sample and pdf all need
+    //returns gamma_t and the transmittance of one pass through the fiber
+    fn inside(&self, sin_theta_o: f64, cos_theta_o: f64, h: f64) -> (f64, Color) {
+        let sin_theta_t = sin_theta_o / self.eta;
+        let cos_theta_t = (1.0 - sin_theta_t * sin_theta_t).max(0.0).sqrt();
+
+        //modified index of refraction for the projected 2d problem around the hair
+        let etap = (self.eta * self.eta - sin_theta_o * sin_theta_o).sqrt() / cos_theta_o;
+        let sin_gamma_t = (h / etap).clamp(-1.0, 1.0);
+        let cos_gamma_t = (1.0 - sin_gamma_t * sin_gamma_t).max(0.0).sqrt();
+        let gamma_t = sin_gamma_t.asin();
+
+        let dist = 2.0 * cos_gamma_t / cos_theta_t;
+        let t = Color::new(
+            (-self.sigma_a.x() * dist).exp(),
+            (-self.sigma_a.y() * dist).exp(),
+            (-self.sigma_a.z() * dist).exp(),
+        );
+
+        (gamma_t, t)
+    }
+
+    //how often each lobe is picked when sampling, proportional to how bright it is
+    fn ap_pdf(&self, cos_theta_o: f64, h: f64) -> [f64; P_MAX + 1] {
+        let sin_theta_o = (1.0 - cos_theta_o * cos_theta_o).max(0.0).sqrt();
+        let (_, t) = self.inside(sin_theta_o, cos_theta_o, h);
+        let ap = HairMaterial::ap(cos_theta_o, self.eta, h, t);
+
+        let sum: f64 = ap.iter().map(|a| luminance(*a)).sum();
+        ap.map(|a| if sum > 0.0 { luminance(a) / sum } else { 0.25 })
+    }
+
+    //the bsdf times |cos theta_i| (what the light transport actually multiplies with), in the local hair frame
+    pub fn f_cos(&self, wo: Vec3, wi: Vec3, h: f64) -> Color {
+        let gamma_o = h.clamp(-1.0, 1.0).asin();
+
+        let sin_theta_o = wo.x();
+        let cos_theta_o = (1.0 - sin_theta_o * sin_theta_o).max(0.0).sqrt();
+        let phi_o = wo.z().atan2(wo.y());
+
+        let sin_theta_i = wi.x();
+        let cos_theta_i = (1.0 - sin_theta_i * sin_theta_i).max(0.0).sqrt();
+        let phi_i = wi.z().atan2(wi.y());
+
+        let (gamma_t, t) = self.inside(sin_theta_o, cos_theta_o, h);
+        let phi = phi_i - phi_o;
+        let ap = HairMaterial::ap(cos_theta_o, self.eta, h, t);
+
+        let mut fsum = Color::new(0.0, 0.0, 0.0);
+        for (p, a) in ap.iter().enumerate().take(P_MAX) {
+            let (sin_op, cos_op) = self.tilt(p, sin_theta_o, cos_theta_o);
+            fsum = fsum
+                + HairMaterial::mp(cos_theta_i, cos_op, sin_theta_i, sin_op, self.v[p])
+                    * HairMaterial::np(phi, p, self.s, gamma_o, gamma_t)
+                    * *a;
+        }
+
+        //the leftover lobe is spread uniformly around the hair
+        fsum = fsum
+            + HairMaterial::mp(
+                cos_theta_i,
+                cos_theta_o,
+                sin_theta_i,
+                sin_theta_o,
+                self.v[P_MAX],
+            ) * ap[P_MAX]
+                / (2.0 * PI);
+
+        fsum
+    }
+
+    pub fn pdf(&self, wo: Vec3, wi: Vec3, h: f64) -> f64 {
+        let gamma_o = h.clamp(-1.0, 1.0).asin();
+
+        let sin_theta_o = wo.x();
+        let cos_theta_o = (1.0 - sin_theta_o * sin_theta_o).max(0.0).sqrt();
+        let phi_o = wo.z().atan2(wo.y());
+
+        let sin_theta_i = wi.x();
+        let cos_theta_i = (1.0 - sin_theta_i * sin_theta_i).max(0.0).sqrt();
+        let phi_i = wi.z().atan2(wi.y());
+
+        let (gamma_t, _) = self.inside(sin_theta_o, cos_theta_o, h);
+        let ap_pdf = self.ap_pdf(cos_theta_o, h);
+        let dphi = phi_i - phi_o;
+
+        let mut pdf = 0.0;
+        for (p, a_pdf) in ap_pdf.iter().enumerate().take(P_MAX) {
+            let (sin_op, cos_op) = self.tilt(p, sin_theta_o, cos_theta_o);
+            pdf += HairMaterial::mp(cos_theta_i, cos_op, sin_theta_i, sin_op, self.v[p])
+                * a_pdf
+                * HairMaterial::np(dphi, p, self.s, gamma_o, gamma_t);
+        }
+        pdf += HairMaterial::mp(
+            cos_theta_i,
+            cos_theta_o,
+            sin_theta_i,
+            sin_theta_o,
+            self.v[P_MAX],
+        ) * ap_pdf[P_MAX]
+            / (2.0 * PI);
+
+        pdf
+    }
+
+    //pick a lobe, sample the longitudinal angle from Mp and the azimuthal one from Np
+    //returns the local incoming direction, the pdf of the whole mixture and f * |cos|
+    pub fn sample(&self, wo: Vec3, h: f64, u: [f64; 4]) -> (Vec3, f64, Color) {
+        let gamma_o = h.clamp(-1.0, 1.0).asin();
+
+        let sin_theta_o = wo.x();
+        let cos_theta_o = (1.0 - sin_theta_o * sin_theta_o).max(0.0).sqrt();
+        let phi_o = wo.z().atan2(wo.y());
+
+        let ap_pdf = self.ap_pdf(cos_theta_o, h);
+        let mut u0 = u[0];
+        let mut p = 0;
+        while p < P_MAX {
+            if u0 < ap_pdf[p] {
+                break;
+            }
+            u0 -= ap_pdf[p];
+            p += 1;
+        }
+
+        let (sin_op, cos_op) = self.tilt(p, sin_theta_o, cos_theta_o);
+
+        let u2 = u[2].max(1e-5);
+        let cos_theta = 1.0 + self.v[p] * (u2 + (1.0 - u2) * (-2.0 / self.v[p]).exp()).ln();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let cos_phi = (2.0 * PI * u[3]).cos();
+        let sin_theta_i = (-cos_theta * sin_op + sin_theta * cos_phi * cos_op).clamp(-1.0, 1.0);
+        let cos_theta_i = (1.0 - sin_theta_i * sin_theta_i).max(0.0).sqrt();
+
+        let (gamma_t, _) = self.inside(sin_theta_o, cos_theta_o, h);
+        let dphi = if p < P_MAX {
+            HairMaterial::phi(p, gamma_o, gamma_t)
+                + HairMaterial::sample_trimmed_logistic(u[1], self.s, -PI, PI)
+        } else {
+            2.0 * PI * u[1]
+        };
+
+        let phi_i = phi_o + dphi;
+        let wi = Vec3::new(
+            sin_theta_i,
+            cos_theta_i * phi_i.cos(),
+            cos_theta_i * phi_i.sin(),
+        );
+
+        (wi, self.pdf(wo, wi, h), self.f_cos(wo, wi, h))
+    }
+}
+
+//the frame the integrator builds already has x along dpdu, which is exactly the frame the lobes are written in
+impl Material for HairMaterial {
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::GLOSSY_REFLECTION | BxdfFlags::GLOSSY_TRANSMISSION
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        self.f_cos(wo, wi, 2.0 * rec.v - 1.0)
+    }
+
+    //hair needs 4 numbers, the last one comes from the rng
+    fn sample(&self, rec: &HitRecord, wo: Vec3, uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        let (wi, pdf, f_cos) =
+            self.sample(wo, 2.0 * rec.v - 1.0, [uc, u[0], u[1], random_double()]);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample::new(
+            wi,
+            f_cos,
+            pdf,
+            BxdfFlags::GLOSSY_REFLECTION | BxdfFlags::GLOSSY_TRANSMISSION,
+        ))
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        HairMaterial::pdf(self, wo, wi, 2.0 * rec.v - 1.0)
+    }
+}
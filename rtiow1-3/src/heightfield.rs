@@ -0,0 +1,303 @@
+use std::rc::Rc;
+
+use image::ImageReader;
+
+use crate::aabb::AABB;
+use crate::hittable::*;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::triangle::{intersect_triangle, shade_triangle};
+use crate::vec3::{Point3, Vec3};
+
+//terrain from a grid of heights. The grid covers corner.x .. corner.x + size.x and corner.z .. corner.z + size.z,
+//heights are 0..1 and get stretched to corner.y .. corner.y + size.y. Every cell between 4 samples is two triangles,
+//with normals from the slope at each sample so it shades smooth, u v run 0..1 over the whole thing
+//a ray only looks at the cells it can hit: a min/max pyramid (each level has the lowest and highest height of 2x2
+//blocks of the level below) is walked from the top and whole blocks are skipped when the ray passes over or under them
+pub struct Heightfield {
+    corner: Point3,
+    size: Vec3,
+    nx: usize,
+    nz: usize,
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    //levels[0] is per cell, the last one is a single block over everything
+    levels: Vec<Level>,
+    mat: Rc<dyn Material>,
+    bbox: AABB,
+}
+
+struct Level {
+    width: usize,
+    depth: usize,
+    min_max: Vec<(f64, f64)>,
+}
+
+impl Heightfield {
+    //nx * nz heights, row by row along x
+    pub fn new(
+        heights: Vec<f64>,
+        nx: usize,
+        nz: usize,
+        corner: Point3,
+        size: Vec3,
+        mat: Rc<dyn Material>,
+    ) -> Heightfield {
+        assert!(
+            nx >= 2 && nz >= 2 && heights.len() == nx * nz,
+            "heightfield needs at least 2x2 samples and nx * nz heights"
+        );
+
+        let heights: Vec<f64> = heights.iter().map(|h| corner.y() + h * size.y()).collect();
+
+        let mut field = Heightfield {
+            corner,
+            size,
+            nx,
+            nz,
+            heights,
+            normals: Vec::new(),
+            levels: Vec::new(),
+            mat,
+            bbox: AABB::new_point(corner, corner + size),
+        };
+        field.normals = (0..nx * nz)
+            .map(|k| field.vertex_normal(k % nx, k / nx))
+            .collect();
+        field.build_levels();
+
+        let (min, max) = field.levels.last().unwrap().min_max[0];
+        field.bbox = AABB::new_point(
+            Point3::new(corner.x(), min, corner.z()),
+            Point3::new(corner.x() + size.x(), max, corner.z() + size.z()),
+        );
+        field
+    }
+
+    //height 0..1 for every (u, v) in 0..1, sampled on an nx * nz grid. Good for noise: |u, v| perlin.turb(...)
+    pub fn from_fn(
+        nx: usize,
+        nz: usize,
+        corner: Point3,
+        size: Vec3,
+        height: impl Fn(f64, f64) -> f64,
+        mat: Rc<dyn Material>,
+    ) -> Heightfield {
+        let heights = (0..nx * nz)
+            .map(|k| {
+                let u = (k % nx) as f64 / (nx - 1) as f64;
+                let v = (k / nx) as f64 / (nz - 1) as f64;
+                height(u, v)
+            })
+            .collect();
+        Heightfield::new(heights, nx, nz, corner, size, mat)
+    }
+
+    //a grayscale image, one sample per pixel with white as the highest. The top of the image is the far (+z) side,
+    //the same way v goes on an ImageTexture so a color map with the same size lines up with it
+    pub fn from_image(
+        filename: &str,
+        corner: Point3,
+        size: Vec3,
+        mat: Rc<dyn Material>,
+    ) -> image::ImageResult<Heightfield> {
+        let image = ImageReader::open(filename)?.decode()?.to_luma16();
+        let (nx, nz) = (image.width() as usize, image.height() as usize);
+
+        let heights = (0..nx * nz)
+            .map(|k| {
+                let (i, j) = (k % nx, nz - 1 - k / nx);
+                image.get_pixel(i as u32, j as u32)[0] as f64 / 65535.0
+            })
+            .collect();
+        Ok(Heightfield::new(heights, nx, nz, corner, size, mat))
+    }
+
+    fn height(&self, i: usize, j: usize) -> f64 {
+        self.heights[j * self.nx + i]
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        Point3::new(
+            self.corner.x() + self.size.x() * i as f64 / (self.nx - 1) as f64,
+            self.height(i, j),
+            self.corner.z() + self.size.z() * j as f64 / (self.nz - 1) as f64,
+        )
+    }
+
+    //from the slope between the neighbours (one sided at the edges)
+    fn vertex_normal(&self, i: usize, j: usize) -> Vec3 {
+        let (i0, i1) = (i.saturating_sub(1), (i + 1).min(self.nx - 1));
+        let (j0, j1) = (j.saturating_sub(1), (j + 1).min(self.nz - 1));
+        let dx = self.size.x() * (i1 - i0) as f64 / (self.nx - 1) as f64;
+        let dz = self.size.z() * (j1 - j0) as f64 / (self.nz - 1) as f64;
+        let slope_x = (self.height(i1, j) - self.height(i0, j)) / dx;
+        let slope_z = (self.height(i, j1) - self.height(i, j0)) / dz;
+
+        Vec3::unit_vector(Vec3::new(-slope_x, 1.0, -slope_z))
+    }
+
+    fn build_levels(&mut self) {
+        let (width, depth) = (self.nx - 1, self.nz - 1);
+        let min_max = (0..width * depth)
+            .map(|k| {
+                let (i, j) = (k % width, k / width);
+                let h = [
+                    self.height(i, j),
+                    self.height(i + 1, j),
+                    self.height(i, j + 1),
+                    self.height(i + 1, j + 1),
+                ];
+                (
+                    h.iter().cloned().fold(f64::INFINITY, f64::min),
+                    h.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+                )
+            })
+            .collect();
+        self.levels.push(Level {
+            width,
+            depth,
+            min_max,
+        });
+
+        while let Some(below) = self.levels.last()
+            && (below.width > 1 || below.depth > 1)
+        {
+            let (width, depth) = (below.width.div_ceil(2), below.depth.div_ceil(2));
+            let mut min_max = vec![(f64::INFINITY, f64::NEG_INFINITY); width * depth];
+            for j in 0..below.depth {
+                for i in 0..below.width {
+                    let (lo, hi) = below.min_max[j * below.width + i];
+                    let up = &mut min_max[(j / 2) * width + i / 2];
+                    up.0 = up.0.min(lo);
+                    up.1 = up.1.max(hi);
+                }
+            }
+            self.levels.push(Level {
+                width,
+                depth,
+                min_max,
+            });
+        }
+    }
+
+    //block (i, j) of a level covers cells i * 2^level .. (i + 1) * 2^level along x and the same along z
+    fn block_bbox(&self, level: usize, i: usize, j: usize) -> AABB {
+        let cells = 1 << level;
+        let (lo, hi) = self.levels[level].min_max[j * self.levels[level].width + i];
+        let a = self.vertex(i * cells, j * cells);
+        let b = self.vertex(
+            ((i + 1) * cells).min(self.nx - 1),
+            ((j + 1) * cells).min(self.nz - 1),
+        );
+        AABB::new_point(Point3::new(a.x(), lo, a.z()), Point3::new(b.x(), hi, b.z()))
+    }
+
+    fn hit_block(
+        &self,
+        r: &Ray,
+        inv_dir: Vec3,
+        (level, i, j): (usize, usize, usize),
+        closest: &mut f64,
+        ray_t: Interval,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let t = Interval::new(ray_t.min, *closest);
+        if !self.block_bbox(level, i, j).hit_inv(r.origin(), inv_dir, t) {
+            return false;
+        }
+
+        if level == 0 {
+            return self.hit_cell(r, i, j, closest, ray_t, rec);
+        }
+
+        let below = &self.levels[level - 1];
+        let mut hit_anything = false;
+        for (ci, cj) in [
+            (2 * i, 2 * j),
+            (2 * i + 1, 2 * j),
+            (2 * i, 2 * j + 1),
+            (2 * i + 1, 2 * j + 1),
+        ] {
+            if ci < below.width
+                && cj < below.depth
+                && self.hit_block(r, inv_dir, (level - 1, ci, cj), closest, ray_t, rec)
+            {
+                hit_anything = true;
+            }
+        }
+        hit_anything
+    }
+
+    //the two triangles of a cell, split along the (i, j) - (i + 1, j + 1) diagonal
+    fn hit_cell(
+        &self,
+        r: &Ray,
+        i: usize,
+        j: usize,
+        closest: &mut f64,
+        ray_t: Interval,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let corners = [(i, j), (i + 1, j + 1), (i + 1, j), (i, j + 1)];
+        let mut hit_anything = false;
+
+        for tri in [[0, 1, 2], [0, 3, 1]] {
+            let (ia, ib, ic) = (corners[tri[0]], corners[tri[1]], corners[tri[2]]);
+            let a = self.vertex(ia.0, ia.1);
+            let e1 = self.vertex(ib.0, ib.1) - a;
+            let e2 = self.vertex(ic.0, ic.1) - a;
+
+            let Some((t, u, v)) = intersect_triangle(r, a, e1, e2) else {
+                continue;
+            };
+            if !ray_t.surrounds(t) || t >= *closest {
+                continue;
+            }
+
+            *closest = t;
+            hit_anything = true;
+
+            rec.t = t;
+            rec.p = r.at(t);
+            rec.mat = self.mat.clone();
+            //both triangles are wound so the normal points up
+            rec.set_face_normal(r, Vec3::unit_vector(Vec3::cross(e1, e2)));
+
+            let normals = [ia, ib, ic].map(|(x, z)| self.normals[z * self.nx + x]);
+            let uvs = [ia, ib, ic].map(|(x, z)| {
+                [
+                    x as f64 / (self.nx - 1) as f64,
+                    z as f64 / (self.nz - 1) as f64,
+                ]
+            });
+            shade_triangle(
+                rec,
+                [1.0 - u - v, u, v],
+                e1,
+                e2,
+                Some(normals),
+                Some(uvs),
+                None,
+            );
+        }
+
+        hit_anything
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let d = r.direction();
+        let inv_dir = Vec3::new(1.0 / d.x(), 1.0 / d.y(), 1.0 / d.z());
+        let mut closest = ray_t.max;
+        let top = self.levels.len() - 1;
+
+        self.hit_block(r, inv_dir, (top, 0, 0), &mut closest, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
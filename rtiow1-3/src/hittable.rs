@@ -1,7 +1,10 @@
 use crate::aabb::AABB;
+use crate::color::Color;
 use crate::interval::*;
+use crate::mat4::{Mat4, Quat};
 use crate::material::Lambertian;
 use crate::material::Material;
+use crate::medium_interface::MediumInterface;
 use crate::ray::Ray;
 use crate::vec3::{Point3, Vec3};
 use std::f64::INFINITY;
@@ -20,6 +23,75 @@ pub trait Hittable {
     fn random(&self, _origin: Vec3) -> Vec3 {
         Vec3::new(1.0, 0.0, 0.0)
     }
+
+    //every place the ray crosses the surface inside ray_t in order of t, added to hits. front_face says if the ray
+    //goes into the object (true) or comes out of it there, which is what csg and volumes need to know where the inside is
+    //the default walks along the ray with hit, shapes that get all their crossings from one solve can do it directly
+    fn hit_all(&self, r: &Ray, ray_t: Interval, hits: &mut Vec<HitRecord>) {
+        let mut rec = HitRecord::new();
+        let mut t_min = ray_t.min;
+
+        for _ in 0..MAX_CROSSINGS {
+            if !self.hit(r, Interval::new(t_min, ray_t.max), &mut rec) {
+                break;
+            }
+            t_min = step_past(rec.t);
+            hits.push(rec.clone());
+        }
+    }
+}
+
+//hit_all gives up after this many, only a pathological mesh gets crossed this often by one ray
+pub const MAX_CROSSINGS: usize = 64;
+
+//the next t to look from after a hit at t, far enough to not find the same surface again
+pub fn step_past(t: f64) -> f64 {
+    t + 1e-6 * t.abs().max(1.0)
+}
+
+//an Rc of a hittable is a hittable too, so containers that are generic over the object type (LinearBvh) can hold
+//Rc<dyn Hittable> like everything else does, or concrete types when they dont need the dynamic dispatch
+impl<T: Hittable + ?Sized> Hittable for Rc<T> {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        (**self).hit(r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, dir: Vec3) -> f64 {
+        (**self).pdf_value(origin, dir)
+    }
+
+    fn random(&self, origin: Vec3) -> Vec3 {
+        (**self).random(origin)
+    }
+
+    fn hit_all(&self, r: &Ray, ray_t: Interval, hits: &mut Vec<HitRecord>) {
+        (**self).hit_all(r, ray_t, hits)
+    }
+}
+
+//pdf_value for shapes whose random picks a uniform point on their whole surface: per crossing it is dist^2 / (cos * area)
+//(area to solid angle), summed over every place the direction passes through the surface because a closed shape is
+//crossed more than once and random can land on any of those points
+pub fn area_pdf_value(object: &dyn Hittable, origin: Point3, dir: Vec3, area: f64) -> f64 {
+    let mut hits = Vec::new();
+    object.hit_all(
+        &Ray::new(origin, dir, 0.0),
+        Interval::new(0.001, f64::INFINITY),
+        &mut hits,
+    );
+
+    let unit_dir = Vec3::unit_vector(dir);
+    hits.iter()
+        .map(|rec| {
+            let dsq = rec.t * rec.t * dir.squared_length();
+            let cos = Vec3::dot(unit_dir, rec.normal).abs();
+            if cos > 0.0 { dsq / (cos * area) } else { 0.0 }
+        })
+        .sum()
 }
 
 //https://stackoverflow.com/questions/49834414/what-is-the-rust-equivalent-of-cs-shared-ptr
@@ -30,7 +102,19 @@ pub trait Hittable {
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
+    //what materials shade with, the interpolated vertex normal on smooth meshes. Always on the same side as normal
+    pub shading_normal: Vec3,
+    //direction of increasing u on the surface, only filled by primitives that have a meaningful one (curves for hair)
+    pub dpdu: Vec3,
+    //which way the bitangent goes, it is normal x dpdu times this. -1 where the texture is mirrored (normal maps need it)
+    pub tangent_sign: f64,
+    //interpolated vertex color on meshes that have them, the diffuse materials multiply their albedo by it
+    pub vertex_color: Option<Color>,
     pub mat: Rc<dyn Material>,
+    //the media on either side, for surfaces wrapped in a MediumBoundary
+    pub interface: Option<Rc<MediumInterface>>,
+    //index of refraction on the outside of the surface, the camera fills it in from what the path is inside of
+    pub outer_ior: f64,
     pub t: f64,
     pub u: f64,
     pub v: f64,
@@ -42,7 +126,13 @@ impl HitRecord {
         HitRecord {
             p: Point3::new(0., 0., 0.),
             normal: Vec3::new(0., 0., 0.),
+            shading_normal: Vec3::new(0., 0., 0.),
+            dpdu: Vec3::new(0., 0., 0.),
+            tangent_sign: 1.0,
+            vertex_color: None,
             mat: Rc::new(Lambertian::new(Vec3::new(0., 0., 0.))),
+            interface: None,
+            outer_ior: 1.0,
             t: 0.,
             u: 0.,
             v: 0.,
@@ -50,6 +140,7 @@ impl HitRecord {
         }
     }
     //we want the normal to be always against the ray, which is why there is a dot check
+    //the record gets reused between objects in hittable_list, so the tangent, vertex color and medium interface are cleared here and whatever has one sets it after
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: Vec3) -> () {
         let front_face = Vec3::dot(r.direction(), outward_normal) < 0.;
         self.front_face = front_face;
@@ -58,6 +149,26 @@ impl HitRecord {
         } else {
             -outward_normal
         };
+        self.shading_normal = self.normal;
+        self.dpdu = Vec3::new(0., 0., 0.);
+        self.tangent_sign = 1.0;
+        self.vertex_color = None;
+        self.interface = None;
+    }
+
+    //call after set_face_normal. The vertex normal can point to the other side of the surface than the real one
+    //(the ray hit the back of a smooth mesh), it gets flipped over to the side the ray is on like normal is
+    pub fn set_shading_normal(&mut self, n: Vec3) {
+        if n.near_zero() {
+            return;
+        }
+
+        let n = Vec3::unit_vector(n);
+        self.shading_normal = if Vec3::dot(n, self.normal) < 0.0 {
+            -n
+        } else {
+            n
+        };
     }
 }
 
@@ -181,6 +292,18 @@ impl Hittable for RotateY {
             (-self.sin_theta * rec.normal.x()) + (self.cos_theta * rec.normal.z()),
         );
 
+        rec.shading_normal = Vec3::new(
+            (self.cos_theta * rec.shading_normal.x()) + (self.sin_theta * rec.shading_normal.z()),
+            rec.shading_normal.y(),
+            (-self.sin_theta * rec.shading_normal.x()) + (self.cos_theta * rec.shading_normal.z()),
+        );
+
+        rec.dpdu = Vec3::new(
+            (self.cos_theta * rec.dpdu.x()) + (self.sin_theta * rec.dpdu.z()),
+            rec.dpdu.y(),
+            (-self.sin_theta * rec.dpdu.x()) + (self.cos_theta * rec.dpdu.z()),
+        );
+
         true
     }
 
@@ -188,3 +311,300 @@ impl Hittable for RotateY {
         self.bbox
     }
 }
+
+//any affine transform (rotation around any axis, non uniform scale, shear, all of it at once) as one wrapper
+//like Translate and RotateY the ray is moved into the object's space instead of moving the object. t doesnt change between
+//the two spaces (the direction isnt normalized) so the hit distance can be compared with everything else as it is
+//the object is an Rc so the same bvh can be placed many times, each copy only costs the two matrices and a box
+pub struct Transform {
+    object: Rc<dyn Hittable>,
+    m: Mat4,
+    inv: Mat4,
+    bbox: AABB,
+}
+
+impl Transform {
+    //m moves the object's points into the world, it has to be invertible
+    pub fn new(object: Rc<dyn Hittable>, m: Mat4) -> Transform {
+        let inv = m.inverse().expect("Transform matrix is not invertible");
+
+        let bbox = transformed_bbox(&object.bounding_box(), &m);
+
+        Transform {
+            object,
+            m,
+            inv,
+            bbox,
+        }
+    }
+}
+
+//the box around a box after m moved it. Arvo's trick: each corner coordinate of the new box is the translation plus the
+//smaller (or larger) of the two products per matrix entry, the same box you get from transforming all 8 corners
+fn transformed_bbox(bbox: &AABB, m: &Mat4) -> AABB {
+    let mut min = Point3::new(m.m[0][3], m.m[1][3], m.m[2][3]);
+    let mut max = min;
+    for i in 0..3 {
+        for j in 0..3 {
+            let axis = bbox.axis_interval(j);
+            let a = m.m[i][j] * axis.min;
+            let b = m.m[i][j] * axis.max;
+            min[i] += a.min(b);
+            max[i] += a.max(b);
+        }
+    }
+
+    AABB::new_point(min, max)
+}
+
+//the hit for both transforms: the ray goes into the object's space and the record comes back out with m
+//t stays the same because the local direction isnt normalized, the local ray reaches the same point at the same t
+fn transform_hit(
+    m: &Mat4,
+    inv: &Mat4,
+    object: &dyn Hittable,
+    r: &Ray,
+    ray_t: Interval,
+    rec: &mut HitRecord,
+) -> bool {
+    let local_r = Ray::new(
+        inv.transform_point(r.origin()),
+        inv.transform_vector(r.direction()),
+        r.time(),
+    );
+
+    if !object.hit(&local_r, ray_t, rec) {
+        return false;
+    }
+
+    //front_face stays right: the inverse transpose keeps the sign of normal . direction
+    rec.p = m.transform_point(rec.p);
+    rec.normal = Vec3::unit_vector(inv.transform_normal(rec.normal));
+    rec.shading_normal = Vec3::unit_vector(inv.transform_normal(rec.shading_normal));
+    rec.dpdu = m.transform_vector(rec.dpdu);
+
+    true
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        transform_hit(&self.m, &self.inv, self.object.as_ref(), r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+//where an animated object is at one moment: scale first, then rotate, then move
+#[derive(Copy, Clone, Debug)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f64, translation: Vec3, rotation: Quat, scale: Vec3) -> Keyframe {
+        Keyframe {
+            time,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::translate(self.translation) * self.rotation.to_mat4() * Mat4::scale(self.scale)
+    }
+
+    //the inverse put together directly instead of inverting the matrix on every ray, the rotation inverse is its transpose
+    fn inverse_matrix(&self) -> Mat4 {
+        let s = self.scale;
+        Mat4::scale(Vec3::new(1.0 / s.x(), 1.0 / s.y(), 1.0 / s.z()))
+            * self.rotation.to_mat4().transpose()
+            * Mat4::translate(-self.translation)
+    }
+
+    //two keys at the same time are a jump, b is the pose from then on
+    fn lerp(a: &Keyframe, b: &Keyframe, time: f64) -> Keyframe {
+        if b.time <= a.time {
+            return *b;
+        }
+
+        let t = (time - a.time) / (b.time - a.time);
+        Keyframe {
+            time,
+            translation: (1.0 - t) * a.translation + t * b.translation,
+            rotation: Quat::slerp(a.rotation, b.rotation, t),
+            scale: (1.0 - t) * a.scale + t * b.scale,
+        }
+    }
+}
+
+//a Transform that changes over time: keyframes (sorted by time) are blended at the ray's time, linearly for the translation
+//and scale and with slerp for the rotation. Before the first key and after the last one the object stands still
+//this is motion blur for anything, Sphere::new_to is the same idea for one moving sphere
+pub struct AnimatedTransform {
+    object: Rc<dyn Hittable>,
+    keys: Vec<Keyframe>,
+    bbox: AABB,
+}
+
+impl AnimatedTransform {
+    //how many in between poses per segment go into the bounding box
+    const BBOX_STEPS: usize = 64;
+
+    pub fn new(object: Rc<dyn Hittable>, mut keys: Vec<Keyframe>) -> AnimatedTransform {
+        assert!(
+            !keys.is_empty(),
+            "AnimatedTransform needs at least one keyframe"
+        );
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        //the box has to hold every pose the object goes through, not just the keys. Sampling the segments finely gets
+        //nearly all of it, a rotation can still bulge out between two samples by at most r (1 - cos(step / 2)) so the box
+        //gets padded by that much
+        let object_bbox = object.bounding_box();
+        let mut bbox = transformed_bbox(&object_bbox, &keys[0].matrix());
+        let mut pad: f64 = 0.0;
+        for pair in keys.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            //a jump has nothing in between, lerping over it would be 0 / 0
+            if b.time <= a.time {
+                bbox = AABB::new_boxes(&bbox, &transformed_bbox(&object_bbox, &b.matrix()));
+                continue;
+            }
+
+            for step in 1..=AnimatedTransform::BBOX_STEPS {
+                let time =
+                    a.time + (b.time - a.time) * step as f64 / AnimatedTransform::BBOX_STEPS as f64;
+                let pose = Keyframe::lerp(a, b, time);
+                bbox = AABB::new_boxes(&bbox, &transformed_bbox(&object_bbox, &pose.matrix()));
+            }
+
+            let step_angle =
+                Quat::angle_between(a.rotation, b.rotation) / AnimatedTransform::BBOX_STEPS as f64;
+            let max_scale = a
+                .scale
+                .x()
+                .abs()
+                .max(a.scale.y().abs())
+                .max(a.scale.z().abs())
+                .max(
+                    b.scale
+                        .x()
+                        .abs()
+                        .max(b.scale.y().abs())
+                        .max(b.scale.z().abs()),
+                );
+            let radius = max_scale
+                * (0..8)
+                    .map(|i| {
+                        Vec3::new(
+                            if i & 1 == 0 {
+                                object_bbox.x.min
+                            } else {
+                                object_bbox.x.max
+                            },
+                            if i & 2 == 0 {
+                                object_bbox.y.min
+                            } else {
+                                object_bbox.y.max
+                            },
+                            if i & 4 == 0 {
+                                object_bbox.z.min
+                            } else {
+                                object_bbox.z.max
+                            },
+                        )
+                        .length()
+                    })
+                    .fold(0.0, f64::max);
+            pad = pad.max(radius * (1.0 - (0.5 * step_angle).cos()));
+        }
+
+        AnimatedTransform {
+            object,
+            keys,
+            bbox: AABB::new(
+                bbox.x.expand(2.0 * pad),
+                bbox.y.expand(2.0 * pad),
+                bbox.z.expand(2.0 * pad),
+            ),
+        }
+    }
+
+    fn pose(&self, time: f64) -> Keyframe {
+        let last = self.keys.len() - 1;
+        if time <= self.keys[0].time {
+            return self.keys[0];
+        }
+        if time >= self.keys[last].time {
+            return self.keys[last];
+        }
+
+        //the first key after time, there are few keys so a search through them is fine
+        let next = self.keys.partition_point(|k| k.time <= time);
+        Keyframe::lerp(&self.keys[next - 1], &self.keys[next], time)
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let pose = self.pose(r.time());
+        transform_hit(
+            &pose.matrix(),
+            &pose.inverse_matrix(),
+            self.object.as_ref(),
+            r,
+            ray_t,
+            rec,
+        )
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+
+    #[test]
+    fn step_keys_keep_the_whole_box() {
+        let sphere = Rc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        ));
+        let key = |time, x| {
+            Keyframe::new(
+                time,
+                Vec3::new(x, 0.0, 0.0),
+                Quat::IDENTITY,
+                Vec3::new(1.0, 1.0, 1.0),
+            )
+        };
+
+        //still at 0, jumps to 10 halfway through, and jumps again at the end
+        let keys = vec![
+            key(0.0, 0.0),
+            key(0.5, 0.0),
+            key(0.5, 10.0),
+            key(1.0, 5.0),
+            key(1.0, -4.0),
+        ];
+        let animated = AnimatedTransform::new(sphere, keys);
+        let bbox = animated.bounding_box();
+        assert!(bbox.x.min <= -5.0 && bbox.x.max >= 11.0, "{:?}", bbox.x);
+        assert!(bbox.y.min <= -1.0 && bbox.y.max >= 1.0);
+
+        let pose = animated.pose(0.75);
+        assert!((pose.translation.x() - 7.5).abs() < 1e-12);
+        assert_eq!(animated.pose(1.0).translation.x(), -4.0);
+    }
+}
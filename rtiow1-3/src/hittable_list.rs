@@ -71,6 +71,11 @@ impl Hittable for HittableList {
     }
 
     fn pdf_value(&self, origin: Point3, dir: Vec3) -> f64 {
+        //scenes without lights pass an empty list
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+
         let w = 1.0 / self.objects.len() as f64;
         let mut sum = 0.0;
 
@@ -82,6 +87,10 @@ impl Hittable for HittableList {
     }
 
     fn random(&self, origin: Vec3) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+
         self.objects[random_int(0, self.objects.len() as i64 - 1) as usize].random(origin)
     }
 }
@@ -0,0 +1,193 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use crate::material::EmissionProfile;
+use crate::vec3::Vec3;
+
+//measured light distribution from an IES LM-63 photometric file (what lamp makers ship for their fixtures)
+//only type C photometry, which is what basically every file out there uses: vertical angle 0 is straight down
+//out of the fixture (we put that on the emitter normal) and horizontal angles go around it starting at dpdu
+//the candela values get normalized so the brightest direction is 1, how bright the light is stays up to the material
+//https://docs.agi32.com/PhotometricToolbox/Content/Open_Tool/iesna_lm-63_format.htm
+pub struct IesProfile {
+    vertical: Vec<f64>,
+    horizontal: Vec<f64>,
+    //candela[h][v]
+    candela: Vec<Vec<f64>>,
+}
+
+impl IesProfile {
+    pub fn load(path: &str) -> std::io::Result<IesProfile> {
+        IesProfile::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> std::io::Result<IesProfile> {
+        let bad = |msg: &str| Error::new(ErrorKind::InvalidData, format!("ies: {}", msg));
+
+        //header is keyword lines until TILT=, everything after that is just whitespace/comma separated numbers
+        let mut lines = text.lines();
+        let tilt = loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => {
+                    break line.trim_start()[5..].trim().to_string();
+                }
+                Some(_) => continue,
+                None => return Err(bad("no TILT line")),
+            }
+        };
+
+        let rest: Vec<&str> = lines.collect();
+        let mut numbers = rest
+            .iter()
+            .flat_map(|l| l.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<f64>());
+        let mut next = || -> std::io::Result<f64> {
+            match numbers.next() {
+                Some(Ok(x)) => Ok(x),
+                Some(Err(_)) => Err(bad("not a number")),
+                None => Err(bad("file ends too early")),
+            }
+        };
+
+        //tilt changes the output with the lamp's angle, we dont rotate lamps so it is read and ignored
+        match tilt.as_str() {
+            "NONE" => {}
+            "INCLUDE" => {
+                let _geometry = next()?;
+                let pairs = next()? as usize;
+                for _ in 0..2 * pairs {
+                    next()?;
+                }
+            }
+            _ => return Err(bad("TILT from a separate file is not supported")),
+        }
+
+        let _lamps = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let n_vertical = next()? as usize;
+        let n_horizontal = next()? as usize;
+        let photometric_type = next()? as i32;
+        let _units = next()?;
+        let _width = next()?;
+        let _length = next()?;
+        let _height = next()?;
+        let _ballast = next()?;
+        let _future_use = next()?;
+        let _watts = next()?;
+
+        if photometric_type != 1 {
+            return Err(bad("only type C photometry is supported"));
+        }
+        if n_vertical == 0 || n_horizontal == 0 {
+            return Err(bad("no angles"));
+        }
+
+        let mut vertical = Vec::with_capacity(n_vertical);
+        for _ in 0..n_vertical {
+            vertical.push(next()?);
+        }
+        let mut horizontal = Vec::with_capacity(n_horizontal);
+        for _ in 0..n_horizontal {
+            horizontal.push(next()?);
+        }
+
+        let mut max: f64 = 0.0;
+        let mut candela = Vec::with_capacity(n_horizontal);
+        for _ in 0..n_horizontal {
+            let mut row = Vec::with_capacity(n_vertical);
+            for _ in 0..n_vertical {
+                let c = (next()? * multiplier).max(0.0);
+                max = max.max(c);
+                row.push(c);
+            }
+            candela.push(row);
+        }
+
+        if max > 0.0 {
+            for row in candela.iter_mut() {
+                for c in row.iter_mut() {
+                    *c /= max;
+                }
+            }
+        }
+
+        Ok(IesProfile {
+            vertical,
+            horizontal,
+            candela,
+        })
+    }
+
+    //index of the interval angle falls in and how far into it, angles has to be sorted
+    fn locate(angles: &[f64], angle: f64) -> (usize, f64) {
+        if angles.len() == 1 || angle <= angles[0] {
+            return (0, 0.0);
+        }
+
+        let last = angles.len() - 1;
+        if angle >= angles[last] {
+            return (last - 1, 1.0);
+        }
+
+        let i = angles.partition_point(|&a| a <= angle) - 1;
+        let span = angles[i + 1] - angles[i];
+        let t = if span > 0.0 {
+            (angle - angles[i]) / span
+        } else {
+            0.0
+        };
+        (i, t)
+    }
+
+    fn lookup_vertical(&self, h: usize, v: usize, tv: f64) -> f64 {
+        let row = &self.candela[h];
+        if row.len() == 1 {
+            return row[0];
+        }
+        row[v] * (1.0 - tv) + row[v + 1] * tv
+    }
+
+    //the file only stores the part that isnt a mirror of another part, the last horizontal angle says which symmetry
+    fn fold_horizontal(&self, phi: f64) -> f64 {
+        let last = self.horizontal[self.horizontal.len() - 1];
+        if last <= 0.0 {
+            0.0
+        } else if last <= 90.0 {
+            let phi = phi % 180.0;
+            if phi > 90.0 { 180.0 - phi } else { phi }
+        } else if last <= 180.0 {
+            if phi > 180.0 { 360.0 - phi } else { phi }
+        } else {
+            phi
+        }
+    }
+}
+
+impl EmissionProfile for IesProfile {
+    fn scale(&self, dir: Vec3) -> f64 {
+        let theta = dir.z().clamp(-1.0, 1.0).acos().to_degrees();
+
+        //outside the measured vertical range the fixture doesnt emit (files with 0..90 are downlights)
+        let v_min = self.vertical[0];
+        let v_max = self.vertical[self.vertical.len() - 1];
+        if theta < v_min - 1e-6 || theta > v_max + 1e-6 {
+            return 0.0;
+        }
+
+        let mut phi = dir.y().atan2(dir.x()).to_degrees();
+        if phi < 0.0 {
+            phi += 360.0;
+        }
+        let phi = self.fold_horizontal(phi);
+
+        let (v, tv) = IesProfile::locate(&self.vertical, theta);
+        if self.horizontal.len() == 1 {
+            return self.lookup_vertical(0, v, tv);
+        }
+
+        let (h, th) = IesProfile::locate(&self.horizontal, phi);
+        self.lookup_vertical(h, v, tv) * (1.0 - th) + self.lookup_vertical(h + 1, v, tv) * th
+    }
+}
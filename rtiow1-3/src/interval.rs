@@ -20,6 +20,14 @@ impl Interval {
         }
     }
 
+    //the part both have in common, EMPTY-like (min > max) when they dont overlap
+    pub fn intersect(a: &Interval, b: &Interval) -> Interval {
+        Interval {
+            min: a.min.max(b.min),
+            max: a.max.min(b.max),
+        }
+    }
+
     pub fn size(&self) -> f64 {
         self.max - self.min
     }
@@ -0,0 +1,218 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::material::{EmissionProfile, SpotProfile};
+use crate::onb::ONB;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+//a light the integrator can aim shadow rays at, without it being geometry in the world
+//area lights are still just hittables with a DiffuseLight (they are reached through pdf_value/random),
+//these are for lights a ray can never hit (points, spots) or that live at infinity (the sun, the sky)
+pub struct LightSample {
+    //unit direction from the shaded point towards the light
+    pub wi: Vec3,
+    //incoming radiance along wi (for delta lights that is the irradiance, there is no solid angle to divide by)
+    pub li: Color,
+    //solid angle pdf of wi, delta lights say 1 and have is_delta set
+    pub pdf: f64,
+    //how far the shadow ray has to go, infinity for lights at infinity
+    pub dist: f64,
+}
+
+pub trait Light {
+    //pick a direction towards the light as seen from p, u are two random numbers
+    fn sample_li(&self, p: Point3, u: [f64; 2]) -> Option<LightSample>;
+
+    //the pdf sample_li would have for wi, used to weight bsdf samples that escape and see the light
+    //delta lights cant be hit by a bsdf sample so they keep the default
+    fn pdf_li(&self, _p: Point3, _wi: Vec3) -> f64 {
+        0.0
+    }
+
+    //radiance a ray that left the scene sees coming from this light
+    fn le(&self, _r: &Ray) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    //no area and no solid angle, only light sampling can find it (no MIS)
+    fn is_delta(&self) -> bool;
+}
+
+//light from a single point, same in every direction. intensity is in watts per steradian, falls off with distance squared
+pub struct PointLight {
+    pub position: Point3,
+    pub intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+
+    //a point light sends intensity into the whole sphere, so watts = 4 pi * intensity
+    pub fn new_power(position: Point3, color: Color, watts: f64) -> PointLight {
+        PointLight::new(position, watts / (4.0 * PI) * color)
+    }
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, p: Point3, _u: [f64; 2]) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let dist_squared = to_light.squared_length();
+        if dist_squared == 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            wi: to_light / dist_squared.sqrt(),
+            li: self.intensity / dist_squared,
+            pdf: 1.0,
+            dist: dist_squared.sqrt(),
+        })
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+}
+
+//point light that only shines into a cone, with the same smooth edge the area light spot profile has (half angles in degrees)
+pub struct SpotLight {
+    pub position: Point3,
+    pub intensity: Color,
+    frame: ONB,
+    profile: SpotProfile,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point3,
+        target: Point3,
+        intensity: Color,
+        total_width: f64,
+        falloff_start: f64,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            intensity,
+            frame: ONB::new(target - position),
+            profile: SpotProfile::new(total_width, falloff_start),
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_li(&self, p: Point3, _u: [f64; 2]) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let dist_squared = to_light.squared_length();
+        if dist_squared == 0.0 {
+            return None;
+        }
+
+        let dist = dist_squared.sqrt();
+        let wi = to_light / dist;
+
+        let scale = self.profile.scale(self.frame.to_local(-wi));
+        if scale == 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            wi,
+            li: scale * self.intensity / dist_squared,
+            pdf: 1.0,
+            dist,
+        })
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+}
+
+//the sun, far enough away that only the direction matters. irradiance is what a surface facing it gets (watts per m^2)
+//with angular_diameter 0 it is a perfectly sharp delta light, anything above gives soft shadows (the real sun is about 0.53 degrees)
+//and then it is also a small disk in the sky that escaping rays can see
+pub struct DirectionalLight {
+    //towards the sun
+    direction: Vec3,
+    irradiance: Color,
+    cos_max: f64,
+    frame: ONB,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Color, angular_diameter: f64) -> DirectionalLight {
+        let direction = Vec3::unit_vector(direction);
+        DirectionalLight {
+            direction,
+            irradiance,
+            cos_max: (0.5 * angular_diameter.clamp(0.0, 180.0))
+                .to_radians()
+                .cos(),
+            frame: ONB::new(direction),
+        }
+    }
+
+    fn cone_solid_angle(&self) -> f64 {
+        2.0 * PI * (1.0 - self.cos_max)
+    }
+
+    //a uniformly bright disk of radiance L gives L * pi * sin^2 of irradiance head on, so this gets the irradiance back
+    fn radiance(&self) -> Color {
+        let sin2 = (1.0 - self.cos_max * self.cos_max).max(1e-12);
+        self.irradiance / (PI * sin2)
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample_li(&self, _p: Point3, u: [f64; 2]) -> Option<LightSample> {
+        if self.is_delta() {
+            return Some(LightSample {
+                wi: self.direction,
+                li: self.irradiance,
+                pdf: 1.0,
+                dist: f64::INFINITY,
+            });
+        }
+
+        //uniform in the cone around the sun direction
+        let cos_theta = 1.0 - u[0] * (1.0 - self.cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u[1];
+        let local = Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta);
+
+        Some(LightSample {
+            wi: self.frame.transform(local),
+            li: self.radiance(),
+            pdf: 1.0 / self.cone_solid_angle(),
+            dist: f64::INFINITY,
+        })
+    }
+
+    fn pdf_li(&self, _p: Point3, wi: Vec3) -> f64 {
+        if self.is_delta() || Vec3::dot(Vec3::unit_vector(wi), self.direction) < self.cos_max {
+            return 0.0;
+        }
+
+        1.0 / self.cone_solid_angle()
+    }
+
+    fn le(&self, r: &Ray) -> Color {
+        if self.is_delta()
+            || Vec3::dot(Vec3::unit_vector(r.direction()), self.direction) < self.cos_max
+        {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        self.radiance()
+    }
+
+    fn is_delta(&self) -> bool {
+        self.cos_max >= 1.0
+    }
+}
@@ -1,39 +1,86 @@
 #![allow(dead_code)]
 
 mod aabb;
+mod bsdf;
 mod bvh;
 mod camera;
+mod capsule;
 mod color;
+mod cone;
 mod constant_medium;
+mod csg;
+mod curve;
+mod cylinder;
+mod disk;
+mod gltf_import;
+mod hair;
+mod heightfield;
 mod hittable;
 mod hittable_list;
+mod ies;
 mod interval;
+mod light;
+mod mat4;
 mod material;
+mod medium;
+mod medium_interface;
+mod mesh;
+mod obj;
 mod onb;
 mod pdf;
 mod perlin;
+mod phase;
+mod ply;
 mod quad;
 mod ray;
+mod sdf;
+mod sky;
 mod sphere;
 mod texture;
+mod torus;
 mod triangle;
 mod utils;
+mod vdb;
 mod vec3;
 
-use bvh::BvhNode;
+use aabb::AABB;
+use bvh::LinearBvh;
 use camera::Camera;
-use color::Color;
-use hittable::{RotateY, Translate};
+use capsule::Capsule;
+use color::{Color, blackbody};
+use cone::Cone;
+use csg::{Csg, CsgOp};
+use curve::{Curve, CurveType};
+use cylinder::Cylinder;
+use gltf_import::load_gltf;
+use hair::HairMaterial;
+use heightfield::Heightfield;
+use hittable::{AnimatedTransform, Hittable, Keyframe, RotateY, Transform, Translate};
 use hittable_list::HittableList;
-use material::DiffuseLight;
+use ies::IesProfile;
+use light::{DirectionalLight, PointLight, SpotLight};
+use mat4::{Mat4, Quat};
 use material::{Dielectric, Material};
+use material::{DiffuseLight, SpotProfile};
 use material::{Lambertian, Metal};
+use material::{OrenNayar, RetroReflective};
+use material::{ThinDielectric, ThinFilm};
+use medium::{Density, GridDensity, HeterogeneousMedium, HomogeneousMedium};
+use medium::{NoiseDensity, ProceduralDensity};
+use medium_interface::{MediumBoundary, MediumInterface};
+use obj::{ImportTransform, load_obj};
+use perlin::Perlin;
+use phase::{DoubleHenyeyGreenstein, HenyeyGreenstein, Rayleigh};
+use ply::load_ply;
 use quad::*;
+use sdf::SdfPrimitive;
+use sky::PreethamSky;
 use sphere::Sphere;
 use std::rc::Rc;
 use texture::{CheckerTexture, ImageTexture, NoiseTexture};
-use triangle::*;
+use torus::Torus;
 use utils::{random_double, random_double_range};
+use vdb::load_vdb;
 use vec3::{Point3, Vec3};
 
 fn till_final() {
@@ -42,8 +89,8 @@ fn till_final() {
     let material_ground = Rc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
     let material_center = Rc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
 
-    let material_left = Rc::new(Dielectric::new(1.5, 1.0));
-    let material_bubble = Rc::new(Dielectric::new(1.0 / 1.5, 1.0));
+    //soap bubble, a single film of soapy water instead of the hollow glass trick (air sphere inside a glass sphere)
+    let material_left = Rc::new(ThinDielectric::new_film(ThinFilm::new(380.0, 1.33)));
 
     let material_right = Rc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 1.0));
 
@@ -65,12 +112,6 @@ fn till_final() {
         material_left,
     )));
 
-    world.add(Rc::new(Sphere::new(
-        Point3::new(-1.0, 0.0, -1.0),
-        0.4,
-        material_bubble,
-    )));
-
     world.add(Rc::new(Sphere::new(
         Point3::new(1.0, 0.0, -1.0),
         0.5,
@@ -157,16 +198,20 @@ fn bouncing_spheres() {
         material3,
     )));
 
-    world = HittableList::new_list(Rc::new(BvhNode::new(world)));
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
 
     let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 20.0, 0.6, 10.0);
 
     cam.lookfrom = Point3::new(13., 2., 3.);
     cam.lookat = Point3::new(0., 0., 0.);
     cam.vup = Vec3::new(0., 1., 0.);
-    cam.background = Color::new(0.7, 0.8, 1.);
 
-    //cam.render(&world).unwrap();
+    //afternoon sun from behind the camera's left, the sky is what rays that miss see now
+    cam.background = Color::new(0., 0., 0.);
+    cam.scene_lights
+        .push(Rc::new(PreethamSky::new(35.0, 160.0, 3.0)));
+
+    cam.render(&world, Rc::new(HittableList::new())).unwrap();
 }
 
 fn checkered_spheres() {
@@ -190,7 +235,7 @@ fn checkered_spheres() {
         Rc::new(Lambertian::new_tex(checker)),
     )));
 
-    world = HittableList::new_list(Rc::new(BvhNode::new(world)));
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
 
     let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 20.0, 0., 10.0);
 
@@ -421,7 +466,7 @@ fn cornell_box() {
     let sphere = Rc::new(Sphere::new(Point3::new(190.0, 90.0, 190.0), 90.0, glass));
     world.add(sphere);
 
-    world = HittableList::new_list(Rc::new(BvhNode::new(world)));
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
 
     //let mut hits = HittableList::new();
 
@@ -535,7 +580,7 @@ fn cornell_smoke() {
         Color::new(1., 1., 1.),
     )));
 
-    world = HittableList::new_list(Rc::new(BvhNode::new(world)));
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
 
     let mut cam = Camera::new(1.0, 600, 200, 50, 40.0, 0.0, 10.0);
 
@@ -574,15 +619,16 @@ fn final_scene() {
 
     let mut world: HittableList = HittableList::new();
 
-    world.add(Rc::new(BvhNode::new(boxes1)));
+    world.add(Rc::new(LinearBvh::new(boxes1)));
 
     let light = Rc::new(DiffuseLight::new(Color::new(7., 7., 7.)));
-    world.add(Rc::new(Quad::new(
+    let light_quad = Rc::new(Quad::new(
         Point3::new(123.0, 554.0, 147.0),
         Vec3::new(300.0, 0.0, 0.0),
         Vec3::new(0.0, 0.0, 265.0),
         light.clone(),
-    )));
+    ));
+    world.add(light_quad.clone());
 
     let center1 = Point3::new(400.0, 400.0, 200.0);
     let center2 = center1 + Vec3::new(30.0, 0.0, 0.0);
@@ -662,7 +708,7 @@ fn final_scene() {
     }
 
     world.add(Rc::new(Translate::new(
-        Rc::new(RotateY::new(Rc::new(BvhNode::new(boxes2)), 15.0)),
+        Rc::new(RotateY::new(Rc::new(LinearBvh::new(boxes2)), 15.0)),
         Vec3::new(-100.0, 270.0, 395.0),
     )));
 
@@ -671,11 +717,15 @@ fn final_scene() {
     cam.lookfrom = Point3::new(478.0, 278.0, -600.0);
     cam.lookat = Point3::new(278.0, 278.0, 0.0);
     cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    //morning sun from behind the camera's left on top of the light panel, the sky shows through the thin fog
     cam.background = Color::new(0.0, 0.0, 0.0);
+    cam.scene_lights
+        .push(Rc::new(PreethamSky::new(40.0, 315.0, 3.0)));
 
     cam.defocus_angle = 0.0;
 
-    //cam.render(&world).unwrap();
+    cam.render(&world, light_quad).unwrap();
 }
 
 fn cornell_car() {
@@ -710,12 +760,13 @@ fn cornell_car() {
     )));
 
     //lgiht
-    world.add(Rc::new(Quad::new(
+    let light_quad = Rc::new(Quad::new(
         Vec3::new(center_x + 260.0, box_height - 1.0, center_y + 105.0),
         Vec3::new(-520.0, 0.0, 0.0),
         Vec3::new(0.0, 0.0, -210.0),
         light.clone(),
-    )));
+    ));
+    world.add(light_quad.clone());
 
     //bottom
     world.add(Rc::new(Quad::new(
@@ -741,50 +792,23 @@ fn cornell_car() {
         white.clone(),
     )));
 
-    let mut triangles = HittableList::new();
-
-    let (models, _materials) = tobj::load_obj(
+    //materials come from the mtl next to the obj, every part keeps its own
+    let car_parts = load_obj(
         "porsche_911_with_interior.obj",
-        &tobj::OFFLINE_RENDERING_LOAD_OPTIONS,
+        Some(ImportTransform {
+            scale: Vec3::new(200.0, 200.0, 200.0),
+            offset: Vec3::new(590.0, 28.0, 640.0), //completely madeup by just looking
+            ..Default::default()
+        }),
     )
     .expect("Failed to load OBJ");
 
-    let car_material = Rc::new(Metal::new(Vec3::new(0.1, 0.1, 0.1), 0.0));
-    let scale = 200.0;
-    let offset = Vec3::new(590.0, 28.0, 640.0); //completely madeup by just looking
-
-    for (_i, m) in models.iter().enumerate() {
-        let mesh = &m.mesh;
-        for idx in mesh.indices.chunks(3) {
-            let v0 = idx[0] as usize;
-            let v1 = idx[1] as usize;
-            let v2 = idx[2] as usize;
-
-            let a = Point3::new(
-                mesh.positions[3 * v0] as f64 * scale + offset[0],
-                mesh.positions[3 * v0 + 1] as f64 * scale + offset[1],
-                mesh.positions[3 * v0 + 2] as f64 * scale + offset[2],
-            );
-
-            let b = Point3::new(
-                mesh.positions[3 * v1] as f64 * scale + offset[0],
-                mesh.positions[3 * v1 + 1] as f64 * scale + offset[1],
-                mesh.positions[3 * v1 + 2] as f64 * scale + offset[2],
-            );
-
-            let c = Point3::new(
-                mesh.positions[3 * v2] as f64 * scale + offset[0],
-                mesh.positions[3 * v2 + 1] as f64 * scale + offset[1],
-                mesh.positions[3 * v2 + 2] as f64 * scale + offset[2],
-            );
-            triangles.add(Rc::new(RotateY::new(
-                Rc::new(Triangle::new(a, b, c, car_material.clone())),
-                40.0,
-            )));
-        }
-    }
-
-    world.add(Rc::new(BvhNode::new(triangles)));
+    //one rotation for the whole car instead of one per part
+    let car = Rc::new(LinearBvh::new(car_parts));
+    world.add(Rc::new(Transform::new(
+        car.clone(),
+        Mat4::rotate(Vec3::new(0.0, 1.0, 0.0), 40.0),
+    )));
 
     let mut cam = Camera::new(16.0 / 9.0, 700, 20, 10, 40.0, 0.0, 10.0);
     cam.lookfrom = Point3::new(center_x, center_y, -800.0);
@@ -792,7 +816,1141 @@ fn cornell_car() {
     cam.vup = Vec3::new(0.0, 1.0, 0.0);
     cam.background = Color::new(0.7, 0.8, 1.);
     cam.defocus_angle = 0.0;
-    //cam.render(&world).unwrap();
+    cam.render(&world, light_quad).unwrap();
+
+    //how much work the car tree did per ray
+    car.print_stats();
+}
+
+//lambertian vs oren-nayar vs retro reflective under the same light
+//the light sits right behind the camera so the rough and retro spheres should look flatter than the lambertian one
+fn rough_diffuse() {
+    let mut world: HittableList = HittableList::new();
+
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, 1.0, -2.5),
+        1.0,
+        Rc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))),
+    )));
+
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Rc::new(OrenNayar::new(Color::new(0.8, 0.8, 0.8), 40.0)),
+    )));
+
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 2.5),
+        1.0,
+        Rc::new(RetroReflective::new(Color::new(0.8, 0.8, 0.8), 20.0, 0.6)),
+    )));
+
+    let light = Rc::new(Quad::new(
+        Point3::new(16.0, 2.0, -1.0),
+        Vec3::new(0.0, 0.0, 2.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        Rc::new(DiffuseLight::new(Color::new(40., 40., 40.))),
+    ));
+    world.add(light.clone());
+
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 30.0, 0., 10.0);
+
+    cam.lookfrom = Point3::new(13., 2., 0.);
+    cam.lookat = Point3::new(0., 1., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0., 0., 0.);
+
+    cam.render(&world, light).unwrap();
+}
+
+//a fuzzy ball, every strand is a bezier curve that comes out of the sphere and droops a little
+fn fur_ball() {
+    let mut world: HittableList = HittableList::new();
+
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+
+    let center = Point3::new(0.0, 1.2, 0.0);
+    let radius = 1.0;
+    let hair = Rc::new(HairMaterial::new_melanin(1.3, 0.0, 0.3, 0.3, 2.0));
+
+    world.add(Rc::new(Sphere::new(
+        center,
+        radius,
+        Rc::new(Lambertian::new(Color::new(0.3, 0.2, 0.1))),
+    )));
+
+    let mut strands: HittableList = HittableList::new();
+    for _i in 0..4000 {
+        let d = Vec3::random_unit_vector();
+        let root = center + radius * d;
+        let length = random_double_range(0.3, 0.5);
+        let droop = Vec3::new(0.0, -0.15, 0.0);
+
+        strands.add(Rc::new(Curve::new(
+            [
+                root,
+                root + 0.35 * length * d,
+                root + 0.7 * length * d + 0.5 * droop,
+                root + length * d + droop,
+            ],
+            0.012,
+            0.002,
+            CurveType::Flat,
+            hair.clone(),
+        )));
+    }
+    world.add(Rc::new(LinearBvh::new(strands)));
+
+    let light = Rc::new(Quad::new(
+        Point3::new(-2.0, 6.0, -2.0),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 4.0),
+        Rc::new(DiffuseLight::new(Color::new(8., 8., 8.))),
+    ));
+    world.add(light.clone());
+
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 30.0, 0., 10.0);
+
+    cam.lookfrom = Point3::new(0., 2., 7.);
+    cam.lookat = Point3::new(0., 1.2, 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0.1, 0.1, 0.1);
+
+    cam.render(&world, light).unwrap();
+}
+
+//three ceiling lights over a floor and a back wall: a warm spot, a two sided daylight panel and a measured ies light
+//all three are 60 watt so the difference is only in where the light goes
+fn light_profiles() {
+    let mut world: HittableList = HittableList::new();
+    let mut lights: HittableList = HittableList::new();
+
+    let white = Rc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+
+    world.add(Rc::new(Quad::new(
+        Point3::new(-10.0, 0.0, -10.0),
+        Vec3::new(0.0, 0.0, 20.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        white.clone(),
+    )));
+
+    world.add(Rc::new(Quad::new(
+        Point3::new(-10.0, 0.0, -3.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        Vec3::new(0.0, 10.0, 0.0),
+        white.clone(),
+    )));
+
+    for x in [-4.0, 0.0, 4.0] {
+        world.add(Rc::new(Sphere::new(
+            Point3::new(x, 0.6, 0.0),
+            0.6,
+            white.clone(),
+        )));
+    }
+
+    //u then v gives a normal pointing down
+    let ceiling_light = |x: f64, mat: Rc<dyn Material>| {
+        Rc::new(Quad::new(
+            Point3::new(x - 0.25, 4.0, -0.25),
+            Vec3::new(0.5, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.5),
+            mat,
+        ))
+    };
+
+    //the three lights are the same size, the power is spread over the area of one of them
+    let area = ceiling_light(0.0, white.clone()).area();
+
+    let mut spot = DiffuseLight::new_power(blackbody(2700.0), 60.0, area, false);
+    spot.profile = Some(Rc::new(SpotProfile::new(30.0, 20.0)));
+
+    let panel = DiffuseLight::new_power(blackbody(6500.0), 60.0, area, true);
+
+    //no ies file ships with the repo, drop one in next to earthmap.jpg to try it
+    let mut measured = DiffuseLight::new_lumens(blackbody(4000.0), 60.0 * 683.0, area, false);
+    match IesProfile::load("light.ies") {
+        Ok(profile) => measured.profile = Some(Rc::new(profile)),
+        Err(e) => eprintln!("couldnt load light.ies ({}), using a plain area light", e),
+    }
+
+    for (x, mat) in [
+        (-4.0, Rc::new(spot) as Rc<dyn Material>),
+        (0.0, Rc::new(panel)),
+        (4.0, Rc::new(measured)),
+    ] {
+        let light = ceiling_light(x, mat);
+        world.add(light.clone());
+        lights.add(light);
+    }
+
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 40.0, 0., 10.0);
+
+    cam.lookfrom = Point3::new(0., 3., 12.);
+    cam.lookat = Point3::new(0., 2., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0., 0., 0.);
+
+    cam.render(&world, Rc::new(lights)).unwrap();
+}
+
+//no area lights at all here, a late afternoon sun with soft shadows plus a point light and a spot
+fn punctual_lights() {
+    let mut world: HittableList = HittableList::new();
+
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, 1.0, -2.5),
+        1.0,
+        Rc::new(Lambertian::new(Color::new(0.7, 0.3, 0.3))),
+    )));
+
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Rc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.2)),
+    )));
+
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 2.5),
+        1.0,
+        Rc::new(Lambertian::new(Color::new(0.3, 0.3, 0.7))),
+    )));
+
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 30.0, 0., 10.0);
+
+    cam.lookfrom = Point3::new(13., 2., 3.);
+    cam.lookat = Point3::new(0., 1., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0.05, 0.07, 0.1);
+
+    cam.scene_lights.push(Rc::new(DirectionalLight::new(
+        Vec3::new(-1.0, 0.6, 0.8),
+        2.0 * blackbody(4500.0),
+        0.53,
+    )));
+    cam.scene_lights.push(Rc::new(PointLight::new_power(
+        Point3::new(3.0, 3.0, 4.0),
+        blackbody(2700.0),
+        400.0,
+    )));
+    cam.scene_lights.push(Rc::new(SpotLight::new(
+        Point3::new(4.0, 6.0, -3.0),
+        Point3::new(0.0, 0.0, -2.5),
+        Color::new(40.0, 40.0, 40.0),
+        20.0,
+        15.0,
+    )));
+
+    cam.render(&world, Rc::new(HittableList::new())).unwrap();
+}
+
+//whatever scene.glb has in it, with its own camera and lights. A file without lights is lit by the background
+fn gltf_scene() {
+    let scene = load_gltf("scene.glb").expect("Failed to load glTF");
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 40.0, 0., 10.0);
+    match &scene.camera {
+        Some(camera) => camera.apply(&mut cam),
+        None => {
+            cam.lookfrom = Point3::new(0., 1., 5.);
+            cam.lookat = Point3::new(0., 0., 0.);
+        }
+    }
+    cam.background = Color::new(0.35, 0.4, 0.5);
+    cam.scene_lights = scene.lights;
+
+    cam.render(&scene.world, Rc::new(HittableList::new()))
+        .unwrap();
+}
+
+//a scanned model from a PLY file (the stanford bunny, dragon...) standing on a floor, the camera is fit to whatever size it has
+//scans often have vertex colors, the white lambertian picks them up
+fn ply_model() {
+    let model = load_ply(
+        "bunny.ply",
+        Rc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))),
+    )
+    .expect("Failed to load PLY");
+    println!("ply: {} triangles", model.triangle_count());
+
+    let model = model.into_bvh();
+    let bbox = model.bounding_box();
+    let center = bbox.centroid();
+    let size = bbox.x.size().max(bbox.y.size()).max(bbox.z.size());
+
+    let mut world = HittableList::new();
+    world.add(Rc::new(model));
+    world.add(Rc::new(Quad::new(
+        Point3::new(
+            center.x() - 50.0 * size,
+            bbox.y.min,
+            center.z() - 50.0 * size,
+        ),
+        Vec3::new(100.0 * size, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 100.0 * size),
+        Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 30.0, 0., 10.0);
+    cam.lookfrom = center + Vec3::new(0.4, 0.5, 2.0) * size;
+    cam.lookat = center;
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0.7, 0.8, 1.0);
+
+    cam.render(&world, Rc::new(HittableList::new())).unwrap();
+}
+
+//one small model (a box with a few balls on it) placed 400 times with random rotations, squashes and shears
+//every copy is a Transform pointing at the same bvh, so the memory is the model once plus two matrices per copy
+fn instancing() {
+    let mut model = HittableList::new();
+    model.add(boxx(
+        Point3::new(-0.5, 0.0, -0.5),
+        Point3::new(0.5, 0.6, 0.5),
+        Rc::new(Lambertian::new(Color::new(0.7, 0.3, 0.2))),
+    ));
+    model.add(Rc::new(Sphere::new(
+        Point3::new(0.0, 0.9, 0.0),
+        0.3,
+        Rc::new(Metal::new(Color::new(0.8, 0.8, 0.85), 0.05)),
+    )));
+    model.add(Rc::new(Sphere::new(
+        Point3::new(0.35, 0.75, 0.35),
+        0.15,
+        Rc::new(Lambertian::new(Color::new(0.2, 0.4, 0.8))),
+    )));
+    let model: Rc<dyn Hittable> = Rc::new(LinearBvh::new(model));
+
+    let mut world = HittableList::new();
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+
+    let mut copies = HittableList::new();
+    for i in 0..20 {
+        for j in 0..20 {
+            let axis = Vec3::random_unit_vector();
+            let mut shear = Mat4::IDENTITY;
+            shear.m[0][1] = random_double_range(-0.5, 0.5);
+
+            let m = Mat4::translate(Vec3::new(
+                2.0 * (i as f64 - 9.5),
+                random_double_range(0.5, 1.0),
+                2.0 * (j as f64 - 9.5),
+            )) * Mat4::rotate(axis, random_double_range(0.0, 360.0))
+                * shear
+                * Mat4::scale(Vec3::new(
+                    random_double_range(0.5, 1.2),
+                    random_double_range(0.5, 1.5),
+                    random_double_range(0.5, 1.2),
+                ));
+            copies.add(Rc::new(Transform::new(model.clone(), m)));
+        }
+    }
+    world.add(Rc::new(LinearBvh::new(copies)));
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 35.0, 0., 10.0);
+    cam.lookfrom = Point3::new(0., 14., 26.);
+    cam.lookat = Point3::new(0., 0., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0.7, 0.8, 1.0);
+
+    cam.render(&world, Rc::new(HittableList::new())).unwrap();
+}
+
+//motion blur on things that arent spheres: a box that flies a bent path while spinning (3 keyframes) and a ball that
+//swells up, the static ball in the middle is there to show what sharp looks like
+fn animated_motion() {
+    let mut world = HittableList::new();
+
+    let checker = Rc::new(CheckerTexture::new_color(
+        0.5,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Rc::new(Lambertian::new_tex(checker)),
+    )));
+    world.add(Rc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Rc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.0)),
+    )));
+
+    let cube = boxx(
+        Point3::new(-0.5, -0.5, -0.5),
+        Point3::new(0.5, 0.5, 0.5),
+        Rc::new(Lambertian::new(Color::new(0.8, 0.3, 0.1))),
+    );
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let one = Vec3::new(1.0, 1.0, 1.0);
+    world.add(Rc::new(AnimatedTransform::new(
+        cube,
+        vec![
+            Keyframe::new(0.0, Vec3::new(-3.5, 0.6, 1.0), Quat::IDENTITY, one),
+            Keyframe::new(
+                0.5,
+                Vec3::new(-2.5, 2.0, 0.0),
+                Quat::from_axis_angle(up, 60.0),
+                one,
+            ),
+            Keyframe::new(
+                1.0,
+                Vec3::new(-2.0, 0.6, -1.5),
+                Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 120.0),
+                one,
+            ),
+        ],
+    )));
+
+    let ball = Rc::new(Sphere::new(
+        Point3::new(0.0, 0.0, 0.0),
+        1.0,
+        Rc::new(Lambertian::new(Color::new(0.2, 0.4, 0.8))),
+    ));
+    world.add(Rc::new(AnimatedTransform::new(
+        ball,
+        vec![
+            Keyframe::new(0.0, Vec3::new(2.5, 0.5, 0.0), Quat::IDENTITY, 0.5 * one),
+            Keyframe::new(1.0, Vec3::new(2.5, 1.0, 0.0), Quat::IDENTITY, one),
+        ],
+    )));
+
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 30.0, 0., 10.0);
+    cam.lookfrom = Point3::new(0., 3., 12.);
+    cam.lookat = Point3::new(0., 1., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0.7, 0.8, 1.0);
+    cam.shutter_open = 0.0;
+    cam.shutter_close = 1.0;
+
+    cam.render(&world, Rc::new(HittableList::new())).unwrap();
+}
+
+//one of each analytic shape, the torus and the disk overhead are the lights and get sampled directly
+fn analytic_shapes() {
+    let mut world: HittableList = HittableList::new();
+    let checker = Rc::new(CheckerTexture::new_color(
+        0.5,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Rc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -20.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 40.0),
+        Rc::new(Lambertian::new_tex(checker)),
+    )));
+
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    world.add(Rc::new(Cylinder::new(
+        Point3::new(-4.5, 0.0, 0.0),
+        2.0 * up,
+        0.8,
+        true,
+        Rc::new(Lambertian::new(Color::new(0.8, 0.3, 0.1))),
+    )));
+    world.add(Rc::new(Cylinder::new(
+        Point3::new(-2.0, 0.6, 1.5),
+        Vec3::new(1.2, 0.0, -0.8),
+        0.6,
+        false,
+        Rc::new(Lambertian::new(Color::new(0.2, 0.5, 0.8))),
+    )));
+    world.add(Rc::new(Cone::new(
+        Point3::new(-1.5, 0.0, -1.5),
+        2.5 * up,
+        1.0,
+        true,
+        Rc::new(Metal::new(Color::new(0.8, 0.7, 0.4), 0.1)),
+    )));
+    world.add(Rc::new(Torus::new(
+        Point3::new(1.2, 0.5, 1.0),
+        Vec3::new(0.3, 1.0, 0.2),
+        1.0,
+        0.35,
+        Rc::new(Lambertian::new(Color::new(0.7, 0.2, 0.6))),
+    )));
+    world.add(Rc::new(Capsule::new(
+        Point3::new(3.5, 0.6, -1.0),
+        Point3::new(4.5, 2.5, 0.5),
+        0.6,
+        Rc::new(Dielectric::new(1.5, 0.0)),
+    )));
+    //x then -z gives a normal pointing up
+    world.add(Rc::new(Quad::new_annulus(
+        Point3::new(4.0, 0.01, 2.5),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -1.0),
+        0.5,
+        Rc::new(Lambertian::new(Color::new(0.9, 0.8, 0.2))),
+    )));
+
+    let mut lights = HittableList::new();
+    let ring: Rc<dyn Hittable> = Rc::new(Torus::new(
+        Point3::new(0.0, 7.0, 0.0),
+        up,
+        3.0,
+        0.2,
+        Rc::new(DiffuseLight::new(Color::new(6.0, 5.0, 4.0))),
+    ));
+    let disk: Rc<dyn Hittable> = Rc::new(Quad::new_disk(
+        Point3::new(0.0, 8.0, 0.0),
+        -up,
+        1.5,
+        Rc::new(DiffuseLight::new(Color::new(4.0, 4.0, 5.0))),
+    ));
+    world.add(ring.clone());
+    world.add(disk.clone());
+    lights.add(ring);
+    lights.add(disk);
+
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 35.0, 0., 10.0);
+    cam.lookfrom = Point3::new(0., 5., 13.);
+    cam.lookat = Point3::new(0., 1., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0.02, 0.02, 0.03);
+
+    cam.render(&world, Rc::new(lights)).unwrap();
+}
+
+//the planar shapes standing up on a floor, a triangle and an annulus light them from above
+fn planar_shapes() {
+    let mut world: HittableList = HittableList::new();
+    world.add(Rc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -20.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 40.0),
+        Rc::new(Lambertian::new(Color::new(0.6, 0.6, 0.6))),
+    )));
+
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    let y = Vec3::new(0.0, 1.0, 0.0);
+    world.add(Rc::new(Quad::new_triangle(
+        Point3::new(-5.0, 0.0, 0.0),
+        Point3::new(-3.0, 0.0, 0.0),
+        Point3::new(-4.0, 2.0, 0.0),
+        Rc::new(Lambertian::new(Color::new(0.8, 0.2, 0.2))),
+    )));
+    world.add(Rc::new(Quad::new_disk(
+        Point3::new(-1.5, 1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0),
+        1.0,
+        Rc::new(Lambertian::new(Color::new(0.2, 0.7, 0.2))),
+    )));
+    world.add(Rc::new(Quad::new_ellipse(
+        Point3::new(1.0, 1.0, 0.0),
+        0.6 * x,
+        y,
+        Rc::new(Lambertian::new(Color::new(0.2, 0.3, 0.8))),
+    )));
+    world.add(Rc::new(Quad::new_annulus(
+        Point3::new(3.2, 1.0, 0.0),
+        x,
+        y,
+        0.5,
+        Rc::new(Lambertian::new(Color::new(0.8, 0.7, 0.2))),
+    )));
+    let hexagon: Vec<Point3> = (0..6)
+        .map(|i| {
+            let a = (i as f64 * 60.0).to_radians();
+            Point3::new(5.5, 1.0, 0.0) + a.cos() * x + a.sin() * y
+        })
+        .collect();
+    world.add(Rc::new(Quad::new_polygon(
+        &hexagon,
+        Rc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))),
+    )));
+
+    let mut lights = HittableList::new();
+    let triangle: Rc<dyn Hittable> = Rc::new(Quad::new_triangle(
+        Point3::new(-3.0, 6.0, 1.0),
+        Point3::new(-1.0, 6.0, 3.0),
+        Point3::new(-3.0, 6.0, 3.0),
+        Rc::new(DiffuseLight::new(Color::new(8.0, 7.0, 6.0))),
+    ));
+    let ring: Rc<dyn Hittable> = Rc::new(Quad::new_annulus(
+        Point3::new(3.0, 6.0, 2.0),
+        Vec3::new(0.0, 0.0, 1.0),
+        x,
+        0.7,
+        Rc::new(DiffuseLight::new(Color::new(6.0, 6.0, 8.0))),
+    ));
+    world.add(triangle.clone());
+    world.add(ring.clone());
+    lights.add(triangle);
+    lights.add(ring);
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 40.0, 0., 10.0);
+    cam.lookfrom = Point3::new(0., 3., 12.);
+    cam.lookat = Point3::new(0.5, 1., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0.02, 0.02, 0.03);
+
+    cam.render(&world, Rc::new(lights)).unwrap();
+}
+
+//things that need csg: a lens (two spheres overlapping), a bowl and a drinking glass (a solid with a smaller one cut out)
+//and a rounded die (a box intersected with a sphere, pips cut out of the top)
+fn csg_shapes() {
+    let mut world: HittableList = HittableList::new();
+    world.add(Rc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -20.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 40.0),
+        Rc::new(Lambertian::new_tex(Rc::new(CheckerTexture::new_color(
+            0.5,
+            Color::new(0.2, 0.3, 0.1),
+            Color::new(0.9, 0.9, 0.9),
+        )))),
+    )));
+
+    let glass = Rc::new(Dielectric::new(1.5, 0.0));
+    let up = Vec3::new(0.0, 1.0, 0.0);
+
+    let lens_center = Point3::new(-4.5, 1.6, 0.0);
+    let offset = Vec3::new(0.0, 0.0, 2.6);
+    world.add(Rc::new(Csg::new(
+        CsgOp::Intersection,
+        Rc::new(Sphere::new(lens_center - offset, 3.0, glass.clone())),
+        Rc::new(Sphere::new(lens_center + offset, 3.0, glass.clone())),
+    )));
+
+    let clay = Rc::new(Lambertian::new(Color::new(0.8, 0.4, 0.2)));
+    let bowl_center = Point3::new(-1.5, 1.5, 0.0);
+    let shell = Rc::new(Csg::new(
+        CsgOp::Difference,
+        Rc::new(Sphere::new(bowl_center, 1.5, clay.clone())),
+        Rc::new(Sphere::new(bowl_center, 1.35, clay.clone())),
+    ));
+    world.add(Rc::new(Csg::new(
+        CsgOp::Difference,
+        shell,
+        boxx(
+            bowl_center + Vec3::new(-2.0, 0.0, -2.0),
+            bowl_center + Vec3::new(2.0, 2.0, 2.0),
+            clay,
+        ),
+    )));
+
+    world.add(Rc::new(Csg::new(
+        CsgOp::Difference,
+        Rc::new(Cylinder::new(
+            Point3::new(1.5, 0.0, 0.0),
+            2.5 * up,
+            0.8,
+            true,
+            glass.clone(),
+        )),
+        Rc::new(Cylinder::new(
+            Point3::new(1.5, 0.2, 0.0),
+            3.0 * up,
+            0.7,
+            true,
+            glass,
+        )),
+    )));
+
+    let ivory = Rc::new(Lambertian::new(Color::new(0.9, 0.88, 0.8)));
+    let die_center = Point3::new(4.5, 0.75, 0.5);
+    let mut die: Rc<dyn Hittable> = Rc::new(Csg::new(
+        CsgOp::Intersection,
+        boxx(
+            die_center - Vec3::new(0.75, 0.75, 0.75),
+            die_center + Vec3::new(0.75, 0.75, 0.75),
+            ivory.clone(),
+        ),
+        Rc::new(Sphere::new(die_center, 1.0, ivory.clone())),
+    ));
+    for (x, z) in [
+        (-0.4, -0.4),
+        (0.4, 0.4),
+        (0.0, 0.0),
+        (-0.4, 0.4),
+        (0.4, -0.4),
+    ] {
+        die = Rc::new(Csg::new(
+            CsgOp::Difference,
+            die,
+            Rc::new(Sphere::new(
+                die_center + Vec3::new(x, 0.8, z),
+                0.15,
+                ivory.clone(),
+            )),
+        ));
+    }
+    world.add(die);
+
+    let light: Rc<dyn Hittable> = Rc::new(Quad::new(
+        Point3::new(-2.0, 8.0, -2.0),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 4.0),
+        Rc::new(DiffuseLight::new(Color::new(6.0, 6.0, 6.0))),
+    ));
+    world.add(light.clone());
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 35.0, 0., 10.0);
+    cam.lookfrom = Point3::new(0., 5., 12.);
+    cam.lookat = Point3::new(0., 1., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0.3, 0.35, 0.45);
+
+    cam.render(&world, light).unwrap();
+}
+
+//shapes that only exist as distance functions: melted blobs, a twisted rounded box, a field of repeated spheres and a mandelbulb
+fn sdf_shapes() {
+    let mut world: HittableList = HittableList::new();
+    world.add(Rc::new(Quad::new(
+        Point3::new(-20.0, 0.0, -20.0),
+        Vec3::new(40.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 40.0),
+        Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+
+    let blobs = sdf::smooth_union(
+        sdf::smooth_union(
+            sdf::sphere(Point3::new(-4.5, 1.0, 0.0), 0.9),
+            sdf::sphere(Point3::new(-3.6, 1.8, 0.2), 0.6),
+            0.5,
+        ),
+        sdf::torus(Point3::new(-4.2, 0.4, 0.0), 1.1, 0.25),
+        0.4,
+    );
+    world.add(Rc::new(SdfPrimitive::new(
+        blobs,
+        AABB::new_point(Point3::new(-6.0, 0.0, -1.6), Point3::new(-2.8, 2.6, 1.6)),
+        Rc::new(Lambertian::new(Color::new(0.8, 0.3, 0.2))),
+    )));
+
+    let twisted = sdf::translate(
+        sdf::twist(
+            sdf::round_box(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.5, 1.2, 0.5), 0.1),
+            1.2,
+        ),
+        Vec3::new(-1.3, 1.2, 0.0),
+    );
+    let mut twisted = SdfPrimitive::new(
+        twisted,
+        AABB::new_point(Point3::new(-2.2, 0.0, -0.9), Point3::new(-0.4, 2.4, 0.9)),
+        Rc::new(Metal::new(Color::new(0.8, 0.7, 0.4), 0.15)),
+    );
+    twisted.step_scale = 0.5;
+    world.add(Rc::new(twisted));
+
+    //a 5x5 patch of an infinite grid, the bounds cut it off
+    let grid = sdf::repeat(
+        sdf::sphere(Point3::new(0.0, 0.25, 0.0), 0.22),
+        Vec3::new(0.6, 0.0, 0.6),
+    );
+    world.add(Rc::new(SdfPrimitive::new(
+        grid,
+        AABB::new_point(Point3::new(-0.3, 0.0, 1.5), Point3::new(2.7, 0.5, 4.5)),
+        Rc::new(Lambertian::new(Color::new(0.2, 0.4, 0.8))),
+    )));
+
+    let mut bulb = SdfPrimitive::new(
+        sdf::mandelbulb(Point3::new(3.5, 1.5, 0.0), 1.2, 8.0, 8),
+        AABB::new_point(Point3::new(2.0, 0.0, -1.5), Point3::new(5.0, 3.0, 1.5)),
+        Rc::new(Lambertian::new(Color::new(0.7, 0.7, 0.7))),
+    );
+    //the fractal has detail all the way down, a coarser surface saves a lot of steps
+    bulb.step_scale = 0.8;
+    bulb.epsilon = 1e-3;
+    world.add(Rc::new(bulb));
+
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 35.0, 0., 10.0);
+    cam.lookfrom = Point3::new(0., 4., 12.);
+    cam.lookat = Point3::new(0., 1.2, 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0.7, 0.8, 1.0);
+
+    cam.render(&world, Rc::new(HittableList::new())).unwrap();
+}
+
+//rolling hills from perlin turbulence with a lake (a flat quad cutting through the low parts)
+//heightmap.png next to the binary is used instead when there is one
+fn terrain() {
+    let mut world: HittableList = HittableList::new();
+    let ground = Rc::new(Lambertian::new(Color::new(0.48, 0.83, 0.53)));
+    let corner = Point3::new(-1000.0, 0.0, -1000.0);
+    let size = Vec3::new(2000.0, 300.0, 2000.0);
+
+    let hills = Heightfield::from_image("heightmap.png", corner, size, ground.clone())
+        .unwrap_or_else(|_| {
+            let noise = Perlin::new();
+            Heightfield::from_fn(
+                256,
+                256,
+                corner,
+                size,
+                |u, v| noise.turb(Point3::new(4.0 * u, 0.0, 4.0 * v), 7).min(1.0),
+                ground,
+            )
+        });
+    world.add(Rc::new(hills));
+
+    world.add(Rc::new(Quad::new(
+        Point3::new(-1000.0, 40.0, -1000.0),
+        Vec3::new(2000.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 2000.0),
+        Rc::new(Metal::new(Color::new(0.3, 0.45, 0.6), 0.05)),
+    )));
+
+    let mut cam = Camera::new(16.0 / 9.0, 500, 100, 50, 50.0, 0., 10.0);
+    cam.lookfrom = Point3::new(0., 450., 900.);
+    cam.lookat = Point3::new(0., 50., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0., 0., 0.);
+    cam.scene_lights
+        .push(Rc::new(PreethamSky::new(25.0, 200.0, 2.5)));
+
+    cam.render(&world, Rc::new(HittableList::new())).unwrap();
+}
+
+//the cornell box with volumes the camera tracks through: a perlin cloud in a sphere, a smoke plume baked into a grid
+//and a fog ring in a torus (not convex, so the hole stays clear). Each one scatters with a different phase function
+fn volumes() {
+    let mut world: HittableList = HittableList::new();
+
+    let red = Rc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white = Rc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green = Rc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+
+    world.add(Rc::new(Quad::new(
+        Point3::new(555., 0., 0.),
+        Vec3::new(0., 555., 0.),
+        Vec3::new(0., 0., 555.),
+        green,
+    )));
+    world.add(Rc::new(Quad::new(
+        Point3::new(0., 0., 0.),
+        Vec3::new(0., 555., 0.),
+        Vec3::new(0., 0., 555.),
+        red,
+    )));
+    world.add(Rc::new(Quad::new(
+        Point3::new(0., 555., 0.),
+        Vec3::new(555., 0., 0.),
+        Vec3::new(0., 0., 555.),
+        white.clone(),
+    )));
+    world.add(Rc::new(Quad::new(
+        Point3::new(0., 0., 0.),
+        Vec3::new(555., 0., 0.),
+        Vec3::new(0., 0., 555.),
+        white.clone(),
+    )));
+    world.add(Rc::new(Quad::new(
+        Point3::new(0., 0., 555.),
+        Vec3::new(555., 0., 0.),
+        Vec3::new(0., 555., 0.),
+        white.clone(),
+    )));
+
+    let light: Rc<dyn Hittable> = Rc::new(Quad::new(
+        Point3::new(213., 554., 227.),
+        Vec3::new(130., 0., 0.),
+        Vec3::new(0., 0., 105.),
+        Rc::new(DiffuseLight::new(Color::new(15., 15., 15.))),
+    ));
+    world.add(light.clone());
+
+    let mut cam = Camera::new(1.0, 600, 200, 50, 40.0, 0.0, 10.0);
+    cam.lookfrom = Point3::new(278., 278., -800.);
+    cam.lookat = Point3::new(278., 278., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0., 0., 0.);
+
+    //the boundaries only say where the media are, their materials are never used
+    cam.media.push(Rc::new(HeterogeneousMedium::new(
+        Rc::new(Sphere::new(
+            Point3::new(170., 330., 330.),
+            120.,
+            white.clone(),
+        )),
+        Rc::new(NoiseDensity::new(0.015, 5, 0.1, 4.0)),
+        0.002,
+        0.06,
+        Color::new(1., 1., 1.),
+        Rc::new(DoubleHenyeyGreenstein::new(0.8, -0.3, 0.85)),
+    )));
+
+    //a column that rises from a point on the floor, spreading out and thinning as it goes up
+    let plume_box = AABB::new_point(Point3::new(300., 0., 200.), Point3::new(500., 450., 400.));
+    let plume = GridDensity::from_fn(32, 64, 32, plume_box, |p| {
+        let h = p.y() / 450.;
+        let radius = 15. + 70. * h;
+        let center = Point3::new(400. + 30. * (6. * h).sin(), p.y(), 300.);
+        let d = (p - center).length() / radius;
+        (1. - h) * (-d * d).exp()
+    });
+    cam.media.push(Rc::new(HeterogeneousMedium::new(
+        boxx(
+            Point3::new(300., 0., 200.),
+            Point3::new(500., 450., 400.),
+            white.clone(),
+        ),
+        Rc::new(plume),
+        0.02,
+        0.08,
+        Color::new(0.8, 0.8, 0.85),
+        Rc::new(HenyeyGreenstein::new(0.5)),
+    )));
+
+    cam.media.push(Rc::new(HeterogeneousMedium::new(
+        Rc::new(Torus::new(
+            Point3::new(150., 60., 150.),
+            Vec3::new(0., 1., 0.),
+            70.,
+            30.,
+            white.clone(),
+        )),
+        Rc::new(ProceduralDensity::new(|_| 1.0, 1.0)),
+        0.0,
+        0.03,
+        Color::new(0.9, 0.6, 0.3),
+        Rc::new(Rayleigh),
+    )));
+
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
+
+    cam.render(&world, light).unwrap();
+}
+
+//a smoke sim from smoke.vdb (or smoke.nvdb) next to the binary, glowing where its temperature grid is hot when it has
+//one. Without the file a perlin puff baked into a grid stands in for it. The camera and light are placed from the bounds
+fn vdb_smoke() {
+    let mut world: HittableList = HittableList::new();
+
+    let path = ["smoke.vdb", "smoke.nvdb"]
+        .into_iter()
+        .find(|p| std::path::Path::new(p).exists())
+        .unwrap_or("smoke.vdb");
+
+    let mut temperature: Option<Rc<dyn Density>> = None;
+    let (density, bbox): (Rc<dyn Density>, AABB) = match load_vdb(path, "density") {
+        Ok(grid) => {
+            if let Ok(heat) = load_vdb(path, "temperature") {
+                temperature = Some(Rc::new(heat));
+            }
+            let bbox = grid.bounding_box();
+            (Rc::new(grid), bbox)
+        }
+        Err(e) => {
+            eprintln!("{}, using a procedural puff instead", e);
+            let noise = Perlin::new();
+            let bbox = AABB::new_point(Point3::new(-1., 0., -1.), Point3::new(1., 2., 1.));
+            let puff = GridDensity::from_fn(64, 64, 64, bbox, |p| {
+                let d = (p - Point3::new(0., 1., 0.)).length();
+                (4. * (1. - d) + 2. * noise.turb(3. * p, 5) - 1.).max(0.)
+            });
+            (Rc::new(puff), bbox)
+        }
+    };
+
+    let center = bbox.centroid();
+    let size = Vec3::new(bbox.x.size(), bbox.y.size(), bbox.z.size()).length();
+
+    world.add(Rc::new(Quad::new(
+        Point3::new(center.x() - 5. * size, bbox.y.min, center.z() - 5. * size),
+        Vec3::new(10. * size, 0., 0.),
+        Vec3::new(0., 0., 10. * size),
+        Rc::new(Lambertian::new(Color::new(0.4, 0.4, 0.4))),
+    )));
+
+    let light: Rc<dyn Hittable> = Rc::new(Quad::new(
+        Point3::new(
+            center.x() - 0.5 * size,
+            bbox.y.max + size,
+            center.z() - 0.5 * size,
+        ),
+        Vec3::new(size, 0., 0.),
+        Vec3::new(0., 0., size),
+        Rc::new(DiffuseLight::new(Color::new(6., 6., 6.))),
+    ));
+    world.add(light.clone());
+
+    let mut smoke = HeterogeneousMedium::new_bounded(
+        bbox,
+        density,
+        1. / size,
+        8. / size,
+        Color::new(0.9, 0.9, 0.9),
+        Rc::new(HenyeyGreenstein::new(0.6)),
+    );
+    //houdini and embergen write temperature as a fraction of some maximum, 1 is taken to be 3000K here
+    if let Some(temperature) = temperature {
+        smoke.set_temperature(temperature, 3000., 0.5);
+    }
+
+    let mut cam = Camera::new(1.0, 600, 200, 50, 40.0, 0.0, 10.0);
+    cam.lookfrom = center + Vec3::new(0., 0.2 * size, 1.8 * size);
+    cam.lookat = center;
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0.02, 0.02, 0.03);
+    cam.media.push(Rc::new(smoke));
+
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
+
+    cam.render(&world, light).unwrap();
+}
+
+//the cornell box full of thin fog the camera sits in, with tinted glass and a glass of juice. The green ball is clear
+//glass with a colored medium inside, so thick parts get darker than thin ones (beer lambert). The juice is a bit wider
+//than the inside of the glass and goes a bit into its bottom, its higher priority makes it win where they overlap
+fn nested_media() {
+    let mut world: HittableList = HittableList::new();
+
+    let red = Rc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white = Rc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green = Rc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+
+    world.add(Rc::new(Quad::new(
+        Point3::new(555., 0., 0.),
+        Vec3::new(0., 555., 0.),
+        Vec3::new(0., 0., 555.),
+        green,
+    )));
+    world.add(Rc::new(Quad::new(
+        Point3::new(0., 0., 0.),
+        Vec3::new(0., 555., 0.),
+        Vec3::new(0., 0., 555.),
+        red,
+    )));
+    world.add(Rc::new(Quad::new(
+        Point3::new(0., 555., 0.),
+        Vec3::new(555., 0., 0.),
+        Vec3::new(0., 0., 555.),
+        white.clone(),
+    )));
+    world.add(Rc::new(Quad::new(
+        Point3::new(0., 0., 0.),
+        Vec3::new(555., 0., 0.),
+        Vec3::new(0., 0., 555.),
+        white.clone(),
+    )));
+    world.add(Rc::new(Quad::new(
+        Point3::new(0., 0., 555.),
+        Vec3::new(555., 0., 0.),
+        Vec3::new(0., 555., 0.),
+        white,
+    )));
+
+    let light: Rc<dyn Hittable> = Rc::new(Quad::new(
+        Point3::new(213., 554., 227.),
+        Vec3::new(130., 0., 0.),
+        Vec3::new(0., 0., 105.),
+        Rc::new(DiffuseLight::new(Color::new(15., 15., 15.))),
+    ));
+    world.add(light.clone());
+
+    world.add(Rc::new(MediumBoundary::new(
+        Rc::new(Sphere::new(
+            Point3::new(160., 110., 220.),
+            110.,
+            Rc::new(Dielectric::new(1.5, 0.0)),
+        )),
+        MediumInterface::new(
+            Some(Rc::new(HomogeneousMedium::absorbing(
+                Color::new(0.3, 0.8, 0.45),
+                100.,
+            ))),
+            None,
+        ),
+    )));
+
+    //a cylinder with a narrower one taken out of it from a little above the bottom, open at the top
+    let glass = Rc::new(Dielectric::new(1.5, 0.0));
+    let cup = Csg::new(
+        CsgOp::Difference,
+        Rc::new(Cylinder::new(
+            Point3::new(390., 0., 300.),
+            Vec3::new(0., 240., 0.),
+            85.,
+            true,
+            glass.clone(),
+        )),
+        Rc::new(Cylinder::new(
+            Point3::new(390., 15., 300.),
+            Vec3::new(0., 260., 0.),
+            75.,
+            true,
+            glass,
+        )),
+    );
+    world.add(Rc::new(MediumBoundary::new(
+        Rc::new(cup),
+        MediumInterface::new_priority(None, None, 1),
+    )));
+
+    world.add(Rc::new(MediumBoundary::new(
+        Rc::new(Cylinder::new(
+            Point3::new(390., 13., 300.),
+            Vec3::new(0., 150., 0.),
+            77.,
+            true,
+            Rc::new(Dielectric::new(1.33, 0.0)),
+        )),
+        MediumInterface::new_priority(
+            Some(Rc::new(HomogeneousMedium::absorbing(
+                Color::new(0.9, 0.45, 0.1),
+                60.,
+            ))),
+            None,
+            2,
+        ),
+    )));
+
+    let mut cam = Camera::new(1.0, 600, 200, 50, 40.0, 0.0, 10.0);
+    cam.lookfrom = Point3::new(278., 278., -800.);
+    cam.lookat = Point3::new(278., 278., 0.);
+    cam.vup = Vec3::new(0., 1., 0.);
+    cam.background = Color::new(0., 0., 0.);
+    cam.medium = Some(Rc::new(HomogeneousMedium::new(
+        Color::new(0., 0., 0.),
+        0.0004,
+        Color::new(1., 1., 1.),
+        Rc::new(HenyeyGreenstein::new(0.3)),
+    )));
+
+    world = HittableList::new_list(Rc::new(LinearBvh::new(world)));
+
+    cam.render(&world, light).unwrap();
 }
 
 fn main() -> () {
@@ -807,4 +1965,20 @@ fn main() -> () {
     //cornell_smoke();
     //final_scene();
     //cornell_car();
+    //rough_diffuse();
+    //fur_ball();
+    //light_profiles();
+    //punctual_lights();
+    //gltf_scene();
+    //ply_model();
+    //instancing();
+    //animated_motion();
+    //analytic_shapes();
+    //planar_shapes();
+    //csg_shapes();
+    //sdf_shapes();
+    //terrain();
+    //volumes();
+    //vdb_smoke();
+    //nested_media();
 }
@@ -0,0 +1,316 @@
+use std::ops::Mul;
+
+use crate::vec3::{Point3, Vec3};
+
+//4x4 matrix for affine transforms, m[row][column] and it multiplies column vectors (M * p), so A * B applies B first
+//the last row is 0 0 0 1 for everything we build here but inverse works for any invertible matrix
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn new(m: [[f64; 4]; 4]) -> Mat4 {
+        Mat4 { m }
+    }
+
+    //file formats like glTF store the columns one after another
+    pub fn from_columns(c: [[f64; 4]; 4]) -> Mat4 {
+        Mat4::new(c).transpose()
+    }
+
+    pub fn translate(offset: Vec3) -> Mat4 {
+        let mut t = Mat4::IDENTITY;
+        t.m[0][3] = offset.x();
+        t.m[1][3] = offset.y();
+        t.m[2][3] = offset.z();
+        t
+    }
+
+    pub fn scale(s: Vec3) -> Mat4 {
+        let mut t = Mat4::IDENTITY;
+        t.m[0][0] = s.x();
+        t.m[1][1] = s.y();
+        t.m[2][2] = s.z();
+        t
+    }
+
+    //counterclockwise around axis when it points at you, same direction RotateY turns for the y axis
+    //https://en.wikipedia.org/wiki/Rotation_matrix#Rotation_matrix_from_axis_and_angle
+    pub fn rotate(axis: Vec3, degrees: f64) -> Mat4 {
+        let a = Vec3::unit_vector(axis);
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let k = 1.0 - cos;
+
+        Mat4::new([
+            [
+                cos + x * x * k,
+                x * y * k - z * sin,
+                x * z * k + y * sin,
+                0.0,
+            ],
+            [
+                y * x * k + z * sin,
+                cos + y * y * k,
+                y * z * k - x * sin,
+                0.0,
+            ],
+            [
+                z * x * k - y * sin,
+                z * y * k + x * sin,
+                cos + z * z * k,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut t = Mat4::IDENTITY;
+        for (r, row) in t.m.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = self.m[c][r];
+            }
+        }
+        t
+    }
+
+    //gauss-jordan with partial pivoting, None when the matrix squashes space flat (a scale of 0 somewhere)
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.m;
+        let mut inv = Mat4::IDENTITY.m;
+
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap_or(col);
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let p = a[col][col];
+            for c in 0..4 {
+                a[col][c] /= p;
+                inv[col][c] /= p;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let f = a[row][col];
+                for c in 0..4 {
+                    a[row][c] -= f * a[col][c];
+                    inv[row][c] -= f * inv[col][c];
+                }
+            }
+        }
+
+        Some(Mat4::new(inv))
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        Point3::new(
+            m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3],
+            m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3],
+            m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3],
+        )
+    }
+
+    //directions ignore the translation
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+
+    //normals have to stay perpendicular to the surface, with non uniform scale or shear that takes the inverse transpose
+    //so call this on the inverse of the matrix that moves the points. The result is not unit length
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * n.x() + m[1][0] * n.y() + m[2][0] * n.z(),
+            m[0][1] * n.x() + m[1][1] * n.y() + m[2][1] * n.z(),
+            m[0][2] * n.x() + m[1][2] * n.y() + m[2][2] * n.z(),
+        )
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut out = [[0.0; 4]; 4];
+        for (r, row) in out.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[r][k] * rhs.m[k][c]).sum();
+            }
+        }
+        Mat4::new(out)
+    }
+}
+
+//unit quaternion for rotations that have to be blended (keyframes), matrices cant be interpolated without shearing
+//https://en.wikipedia.org/wiki/Slerp
+#[derive(Copy, Clone, Debug)]
+pub struct Quat {
+    pub w: f64,
+    pub v: Vec3,
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat {
+        w: 1.0,
+        v: Vec3 { e: [0.0, 0.0, 0.0] },
+    };
+
+    //same direction as Mat4::rotate
+    pub fn from_axis_angle(axis: Vec3, degrees: f64) -> Quat {
+        let (sin, cos) = (0.5 * degrees.to_radians()).sin_cos();
+        Quat {
+            w: cos,
+            v: sin * Vec3::unit_vector(axis),
+        }
+    }
+
+    fn dot(a: Quat, b: Quat) -> f64 {
+        a.w * b.w + Vec3::dot(a.v, b.v)
+    }
+
+    //q and -q are the same rotation, flipping one of them makes it go the short way around
+    pub fn slerp(a: Quat, b: Quat, t: f64) -> Quat {
+        let mut b = b;
+        let mut cos = Quat::dot(a, b);
+        if cos < 0.0 {
+            b = Quat { w: -b.w, v: -b.v };
+            cos = -cos;
+        }
+
+        //nearly the same rotation, a straight blend is fine and avoids dividing by sin ~ 0
+        let (wa, wb) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+
+        let q = Quat {
+            w: wa * a.w + wb * b.w,
+            v: wa * a.v + wb * b.v,
+        };
+        let len = Quat::dot(q, q).sqrt();
+        Quat {
+            w: q.w / len,
+            v: q.v / len,
+        }
+    }
+
+    //the angle between two rotations in radians, used to know how far a keyframe segment turns
+    pub fn angle_between(a: Quat, b: Quat) -> f64 {
+        2.0 * Quat::dot(a, b).abs().min(1.0).acos()
+    }
+
+    pub fn to_mat4(self) -> Mat4 {
+        let (w, x, y, z) = (self.w, self.v.x(), self.v.y(), self.v.z());
+        Mat4::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Mat4, b: Mat4) {
+        for r in 0..4 {
+            for c in 0..4 {
+                assert!(
+                    (a.m[r][c] - b.m[r][c]).abs() < 1e-9,
+                    "{:?} != {:?} at [{}][{}]",
+                    a,
+                    b,
+                    r,
+                    c
+                );
+            }
+        }
+    }
+
+    fn shear() -> Mat4 {
+        let mut m = Mat4::IDENTITY;
+        m.m[0][1] = 0.7;
+        m.m[2][0] = -0.3;
+        m
+    }
+
+    #[test]
+    fn inverse_of_rotate_shear_scale() {
+        let m = Mat4::translate(Vec3::new(3.0, -2.0, 5.0))
+            * Mat4::rotate(Vec3::new(1.0, 2.0, -0.5), 37.0)
+            * shear()
+            * Mat4::scale(Vec3::new(2.0, 0.5, -3.0));
+        let inv = m.inverse().unwrap();
+
+        assert_near(m * inv, Mat4::IDENTITY);
+        assert_near(inv * m, Mat4::IDENTITY);
+
+        let p = Point3::new(0.4, -1.2, 7.0);
+        let back = inv.transform_point(m.transform_point(p));
+        assert!((back - p).length() < 1e-9);
+    }
+
+    #[test]
+    fn zero_scale_has_no_inverse() {
+        let m =
+            Mat4::rotate(Vec3::new(0.0, 1.0, 0.0), 30.0) * Mat4::scale(Vec3::new(1.0, 0.0, 2.0));
+        assert_eq!(m.inverse(), None);
+    }
+
+    #[test]
+    fn normals_stay_perpendicular() {
+        let m = shear() * Mat4::scale(Vec3::new(4.0, 1.0, 0.25));
+        let inv = m.inverse().unwrap();
+
+        //a plane through the origin spanned by two tangents, its normal is their cross product
+        let (t1, t2) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 1.0));
+        let n = inv.transform_normal(Vec3::cross(t1, t2));
+        assert!(Vec3::dot(n, m.transform_vector(t1)).abs() < 1e-9);
+        assert!(Vec3::dot(n, m.transform_vector(t2)).abs() < 1e-9);
+    }
+}
@@ -1,45 +1,59 @@
 use std::f64::consts::PI;
 use std::rc::Rc;
 
+use crate::bsdf::{BsdfSample, BxdfFlags, abs_cos_theta, cos_theta, reflect, same_hemisphere};
 use crate::color::*;
 use crate::hittable::HitRecord;
-use crate::pdf::{CosinePDF, PDF, SpherePDF};
+use crate::onb::ONB;
+use crate::pdf::{PDF, PhongLobePDF};
+use crate::phase::PhaseFunction;
 use crate::ray::Ray;
 use crate::texture::{SolidColor, Texture};
-use crate::utils::random_double;
 use crate::vec3::{Point3, Vec3};
 
-pub struct ScatterRecord {
-    pub attenuation: Color,
-    //attenuation is how much the light is kept after a hit
-    pub pdf: Rc<dyn PDF>,
-    pub skip_pdf: bool,
-    pub skip_ray: Ray,
-}
+//everything here is in the local shading frame (z is the normal on the side the ray came from, see bsdf.rs)
+//wo points back to where the ray came from and wi is the other direction, both unit vectors
+//eval is the brdf with the cosine already multiplied in (what scatter_pdf * attenuation used to be), that way
+//phase functions that have no cosine fit the same interface. sample returns the same f so the path weight is f / pdf
+//a material that does none of these (lights) just has no lobes and the path stops there
+pub trait Material {
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::NONE
+    }
 
-impl ScatterRecord {
-    pub fn new() -> ScatterRecord {
-        ScatterRecord {
-            attenuation: Color::new(0.0, 0.0, 0.0),
-            pdf: Rc::new(SpherePDF::new()),
-            skip_pdf: false,
-            skip_ray: Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 0.0),
-        }
+    fn eval(&self, _rec: &HitRecord, _wo: Vec3, _wi: Vec3) -> Color {
+        Color::new(0., 0., 0.)
     }
-}
 
-pub trait Material {
-    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _srec: &mut ScatterRecord) -> bool {
-        false
+    fn sample(&self, _rec: &HitRecord, _wo: Vec3, _uc: f64, _u: [f64; 2]) -> Option<BsdfSample> {
+        None
     }
 
-    fn emitted(&self, _rec: &HitRecord, _u: f64, _v: f64, _p: Point3) -> Color {
+    fn pdf(&self, _rec: &HitRecord, _wo: Vec3, _wi: Vec3) -> f64 {
+        0.0
+    }
+
+    //r_in is the ray that hit the light, the light is emitting back along it
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, _u: f64, _v: f64, _p: Point3) -> Color {
         Color::new(0., 0., 0.)
     }
 
-    fn scatter_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
-        0.0
+    //the normal the shading frame gets built on, materials with bump maps bend it
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        rec.shading_normal
     }
+
+    //index of refraction of what the material is made of, for dielectrics nested inside this one
+    fn ior(&self) -> f64 {
+        1.0
+    }
+}
+
+//cosine weighted hemisphere from the two uniforms, same as random_cosine_dir but driven by the sample we are given
+fn cosine_hemisphere(u: [f64; 2]) -> Vec3 {
+    let phi = 2.0 * PI * u[0];
+    let r = u[1].sqrt();
+    Vec3::new(phi.cos() * r, phi.sin() * r, (1.0 - u[1]).max(0.0).sqrt())
 }
 
 //True Lambertian Reflection
@@ -63,28 +77,211 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
-        srec.attenuation = self.tex.value(rec.u, rec.v, rec.p);
-        srec.pdf = Rc::new(CosinePDF::new(rec.normal));
-        srec.skip_pdf = false;
-        true
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::DIFFUSE_REFLECTION
     }
 
-    fn scatter_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
-        //return 1.0 / (2.0 * PI);
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        //wait the book might be cooking with this check in the later chapters in book 3
+        //(lambertian scatter cant produce a invalid cos theta, but others might)
+        //it indeed is, tested
+        if !same_hemisphere(wo, wi) {
+            return Color::new(0., 0., 0.);
+        }
+
+        let mut albedo = self.tex.value(rec.u, rec.v, rec.p);
+        if let Some(c) = rec.vertex_color {
+            albedo = albedo * c;
+        }
+
+        albedo * abs_cos_theta(wi) / PI
+    }
+
+    //the pdf is exactly the cosine part of eval, which is why the two canceled out in book 1 & 2
+    fn sample(&self, rec: &HitRecord, wo: Vec3, _uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        let mut wi = cosine_hemisphere(u);
+        if cos_theta(wo) < 0.0 {
+            wi = -wi;
+        }
 
-        let cos_theta = Vec3::dot(rec.normal, Vec3::unit_vector(scattered.direction()));
-        if cos_theta < 0.0 {
-            //wait the book might be cooking with this check in the later chapters in book 3
-            //(lambertian scatter cant produce a invalid cos theta, but others might)
-            //it indeed is, tested
+        let pdf = self.pdf(rec, wo, wi);
+        Some(BsdfSample::new(
+            wi,
+            self.eval(rec, wo, wi),
+            pdf,
+            BxdfFlags::DIFFUSE_REFLECTION,
+        ))
+    }
+
+    fn pdf(&self, _rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        if !same_hemisphere(wo, wi) {
             return 0.0;
+        }
+
+        abs_cos_theta(wi) / PI
+    }
+}
+
+//Oren-Nayar rough diffuse, the surface is modeled as tiny v shaped lambertian facets
+//sigma is the standard deviation of the facet slopes in degrees, sigma = 0 gives back lambertian exactly (A = 1, B = 0)
+//rough surfaces (clay, plaster, the moon) look flatter than lambertian because facets facing the viewer get brighter near the edges
+//this is the "qualitative" model from the paper, the full one has interreflections between the facets which we ignore
+//https://en.wikipedia.org/wiki/Oren%E2%80%93Nayar_reflectance_model
+pub struct OrenNayar {
+    tex: Rc<dyn Texture>,
+    a: f64,
+    b: f64,
+}
+
+impl OrenNayar {
+    pub fn new(albedo: Color, sigma: f64) -> OrenNayar {
+        OrenNayar::new_tex(Rc::new(SolidColor::new(albedo)), sigma)
+    }
+
+    pub fn new_tex(tex: Rc<dyn Texture>, sigma: f64) -> OrenNayar {
+        let sigma = sigma.to_radians();
+        let sigma2 = sigma * sigma;
+
+        OrenNayar {
+            tex,
+            a: 1.0 - sigma2 / (2.0 * (sigma2 + 0.33)),
+            b: 0.45 * sigma2 / (sigma2 + 0.09),
+        }
+    }
+}
+
+impl Material for OrenNayar {
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::DIFFUSE_REFLECTION
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        if !same_hemisphere(wo, wi) {
+            return Color::new(0., 0., 0.);
+        }
+
+        let cos_i = abs_cos_theta(wi);
+        let cos_o = abs_cos_theta(wo);
+
+        let sin_i = (1.0 - cos_i * cos_i).max(0.0).sqrt();
+        let sin_o = (1.0 - cos_o * cos_o).max(0.0).sqrt();
+
+        //cos(phi_i - phi_o) is the dot of the two directions after projecting them on the tangent plane
+        //straight up directions have no azimuth so the term is just dropped
+        let mut max_cos = 0.0;
+        if sin_i > 1e-4 && sin_o > 1e-4 {
+            let di = Vec3::new(wi.x(), wi.y(), 0.0) / sin_i;
+            let dk = Vec3::new(wo.x(), wo.y(), 0.0) / sin_o;
+            max_cos = Vec3::dot(di, dk).max(0.0);
+        }
+
+        //alpha is the larger of the two angles, beta the smaller one
+        let (sin_alpha, tan_beta) = if cos_i > cos_o {
+            (sin_o, sin_i / cos_i)
         } else {
-            return cos_theta / PI;
+            (sin_i, sin_o / cos_o.max(1e-8))
+        };
+
+        self.tex.value(rec.u, rec.v, rec.p) * cos_i / PI
+            * (self.a + self.b * max_cos * sin_alpha * tan_beta)
+    }
+
+    //the brdf is still mostly cosine shaped so the cosine pdf is a good fit, both are 0 below the surface so they match
+    fn sample(&self, rec: &HitRecord, wo: Vec3, _uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        let mut wi = cosine_hemisphere(u);
+        if cos_theta(wo) < 0.0 {
+            wi = -wi;
+        }
+
+        Some(BsdfSample::new(
+            wi,
+            self.eval(rec, wo, wi),
+            self.pdf(rec, wo, wi),
+            BxdfFlags::DIFFUSE_REFLECTION,
+        ))
+    }
+
+    fn pdf(&self, _rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+
+        abs_cos_theta(wi) / PI
+    }
+}
+
+//retro reflection sends light back towards where it came from (road signs, cat eyes, the full moon being evenly bright to the edge)
+//modeled as a lambertian base plus a cos^n lobe around the incoming direction instead of around the mirror direction
+//retro is the fraction of the energy that goes to the retro lobe, the rest is lambertian
+pub struct RetroReflective {
+    tex: Rc<dyn Texture>,
+    exponent: f64,
+    retro: f64,
+}
+
+impl RetroReflective {
+    pub fn new(albedo: Color, exponent: f64, retro: f64) -> RetroReflective {
+        RetroReflective::new_tex(Rc::new(SolidColor::new(albedo)), exponent, retro)
+    }
+
+    pub fn new_tex(tex: Rc<dyn Texture>, exponent: f64, retro: f64) -> RetroReflective {
+        RetroReflective {
+            tex,
+            exponent: exponent.max(0.0),
+            retro: retro.clamp(0.0, 1.0),
         }
     }
 }
 
+impl Material for RetroReflective {
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::DIFFUSE_REFLECTION | BxdfFlags::GLOSSY_REFLECTION
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        if !same_hemisphere(wo, wi) {
+            return Color::new(0., 0., 0.);
+        }
+
+        //(n + 2) / 2pi is the normalization of the "modified phong" lobe, it keeps the lobe from creating energy at normal incidence
+        let diffuse = (1.0 - self.retro) / PI;
+        let lobe = self.retro * (self.exponent + 2.0) / (2.0 * PI)
+            * Vec3::dot(wi, wo).max(0.0).powf(self.exponent);
+
+        self.tex.value(rec.u, rec.v, rec.p) * (diffuse + lobe) * abs_cos_theta(wi)
+    }
+
+    //half the samples go around the normal for the diffuse part and half around the way back to the viewer
+    //the mixture is fine even when the lobe generates under the surface, eval is 0 there
+    fn sample(&self, rec: &HitRecord, wo: Vec3, uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        let (wi, flags) = if uc < 0.5 {
+            let wi = cosine_hemisphere(u);
+            let wi = if cos_theta(wo) < 0.0 { -wi } else { wi };
+            (wi, BxdfFlags::DIFFUSE_REFLECTION)
+        } else {
+            let wi = PhongLobePDF::new(wo, self.exponent).sample(u);
+            (wi, BxdfFlags::GLOSSY_REFLECTION)
+        };
+
+        Some(BsdfSample::new(
+            wi,
+            self.eval(rec, wo, wi),
+            self.pdf(rec, wo, wi),
+            flags,
+        ))
+    }
+
+    fn pdf(&self, _rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        let diffuse = if same_hemisphere(wo, wi) {
+            abs_cos_theta(wi) / PI
+        } else {
+            0.0
+        };
+
+        0.5 * diffuse + 0.5 * PhongLobePDF::new(wo, self.exponent).value(wi)
+    }
+}
+
 //Metal surfaces instead of diffusing reflection perfectly reflects rays -> This is because at the microscopic level the surface is smooth
 //we simulate fuzz by adding some randomness to the direction of the reflected ray -> if it goes back into the sphere quick, dot check
 pub struct Metal {
@@ -101,25 +298,43 @@ impl Metal {
     }
 }
 
+//the fuzz has no closed form pdf (random point in a sphere added to the normal) so even fuzzy metal
+//can only be sampled, it is flagged specular so the integrator never tries to eval it
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::SPECULAR_REFLECTION
+    }
+
+    fn sample(&self, _rec: &HitRecord, wo: Vec3, _uc: f64, _u: [f64; 2]) -> Option<BsdfSample> {
+        if self.fuzz == 0.0 {
+            return Some(BsdfSample::new(
+                reflect(wo),
+                self.albedo,
+                1.0,
+                BxdfFlags::SPECULAR_REFLECTION,
+            ));
+        }
+
         let alternate_normal =
-            Vec3::unit_vector(rec.normal + self.fuzz * Vec3::random_unit_vector());
-        let reflected = Vec3::reflect(r_in.direction(), alternate_normal);
+            Vec3::unit_vector(Vec3::new(0., 0., 1.) + self.fuzz * Vec3::random_unit_vector());
+        let reflected = Vec3::reflect(-wo, alternate_normal);
         //reflected = Vec3::unit_vector(reflected) + self.fuzz * Vec3::random_unit_vector();
 
-        srec.attenuation = self.albedo;
-        srec.skip_pdf = true;
-        srec.skip_ray = Ray::new(rec.p, reflected, r_in.time());
-
         //Vec3::dot(scattered.direction(), alternate_normal) > 0.0
-        true //why?
+        //why? -> it is still not checked, rays that end up under the surface keep going
+        Some(BsdfSample::new(
+            reflected,
+            self.albedo,
+            1.0,
+            BxdfFlags::SPECULAR_REFLECTION,
+        ))
     }
 }
 
 pub struct Dielectric {
     refraction_index: f64,
     fuzz: f64,
+    film: Option<ThinFilm>,
 }
 
 //things like water, glass all that shabang that light bends when it enters the material
@@ -129,6 +344,16 @@ impl Dielectric {
         Dielectric {
             refraction_index: refraction_index,
             fuzz: fuzz,
+            film: None,
+        }
+    }
+
+    //dielectric with a thin film on the outside, oil on water is the classic one
+    pub fn new_film(refraction_index: f64, fuzz: f64, film: ThinFilm) -> Dielectric {
+        Dielectric {
+            refraction_index,
+            fuzz,
+            film: Some(film),
         }
     }
 
@@ -141,66 +366,338 @@ impl Dielectric {
 }
 
 //see refract in vec3.rs for more explanation but the high level idea is that the light bends according to its refraction index
+//in the local frame the normal is just +z (facing the side the ray came from)
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
-        srec.attenuation = Color::new(1.0, 1.0, 1.0);
-        srec.skip_pdf = true;
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::SPECULAR_REFLECTION | BxdfFlags::SPECULAR_TRANSMISSION
+    }
 
-        //here its 1 over because air has a refraction index of 1. If its front face we are entering from air into the material
+    fn sample(&self, rec: &HitRecord, wo: Vec3, uc: f64, _u: [f64; 2]) -> Option<BsdfSample> {
+        //outer_ior is 1 for air. If its front face we are entering from outside into the material
         let ri: f64 = if rec.front_face {
-            1.0 / self.refraction_index
+            rec.outer_ior / self.refraction_index
         } else {
-            self.refraction_index
+            self.refraction_index / rec.outer_ior
         };
 
-        let unit_direction = Vec3::unit_vector(r_in.direction());
+        let unit_direction = -wo;
 
         //change up the normal a little to simulate roughness, should we check for normal into?
-        let alternate_normal =
-            Vec3::unit_vector(rec.normal + self.fuzz * Vec3::random_unit_vector());
+        let alternate_normal = if self.fuzz == 0.0 {
+            Vec3::new(0., 0., 1.)
+        } else {
+            Vec3::unit_vector(Vec3::new(0., 0., 1.) + self.fuzz * Vec3::random_unit_vector())
+        };
 
         let cos_theta = Vec3::dot(-unit_direction, alternate_normal).min(1.0); //again the scalar is calculated with -uv because we want positive angles.
-        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt(); //trig identity
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt(); //trig identity
 
         //some angles cant refracts because there is no solution to the snell equation -> light has to reflect
         let cannot_refract = ri * sin_theta > 1.0;
-        let direction;
+
+        //the film only coats the outside so rays inside the material see the plain interface
+        if let (Some(film), true, false) = (&self.film, rec.front_face, cannot_refract) {
+            //the reflectance is different per channel now so we cant just flip a coin with it
+            //pick with the average, the pdf is that probability so f / pdf keeps the expected value at R and 1 - R per channel
+            let reflectance = film.reflectance(cos_theta, rec.outer_ior, self.refraction_index);
+            let prob = (reflectance.x() + reflectance.y() + reflectance.z()) / 3.0;
+
+            if prob > uc {
+                return Some(BsdfSample::new(
+                    Vec3::reflect(unit_direction, alternate_normal),
+                    reflectance,
+                    prob,
+                    BxdfFlags::SPECULAR_REFLECTION,
+                ));
+            }
+
+            return Some(BsdfSample::new(
+                Vec3::refract(unit_direction, alternate_normal, ri),
+                Color::new(1.0, 1.0, 1.0) - reflectance,
+                1.0 - prob,
+                BxdfFlags::SPECULAR_TRANSMISSION,
+            ));
+        }
 
         //according to the book self.reflectance needs to be used for glass? not explained more than that
-        if cannot_refract || self.reflectance(cos_theta, ri) > random_double() {
-            direction = Vec3::reflect(unit_direction, alternate_normal);
+        //the fresnel weight and the probability of picking the branch are the same number so they cancel, f / pdf = 1
+        let white = Color::new(1.0, 1.0, 1.0);
+        if cannot_refract || self.reflectance(cos_theta, ri) > uc {
+            Some(BsdfSample::new(
+                Vec3::reflect(unit_direction, alternate_normal),
+                white,
+                1.0,
+                BxdfFlags::SPECULAR_REFLECTION,
+            ))
+        } else {
+            Some(BsdfSample::new(
+                Vec3::refract(unit_direction, alternate_normal, ri),
+                white,
+                1.0,
+                BxdfFlags::SPECULAR_TRANSMISSION,
+            ))
+        }
+    }
+
+    fn ior(&self) -> f64 {
+        self.refraction_index
+    }
+}
+
+//thin film interference, the colors on soap bubbles and oil slicks
+//light reflects off both the top and the bottom of the film, the two reflections travel different distances and interfere
+//whether they add up or cancel depends on the wavelength, which is where the colors come from
+//we only have rgb so each channel is treated as a single wavelength, good enough for bubbles
+//https://en.wikipedia.org/wiki/Thin-film_interference
+pub struct ThinFilm {
+    thickness: f64, //in nanometers, bubbles are a few hundred
+    ior: f64,
+}
+
+impl ThinFilm {
+    const WAVELENGTHS: [f64; 3] = [650.0, 532.0, 450.0];
+
+    pub fn new(thickness: f64, ior: f64) -> ThinFilm {
+        ThinFilm {
+            thickness: thickness.max(0.0),
+            ior,
+        }
+    }
+
+    //fresnel amplitude (not power) coefficients for both polarizations, the sign matters for interference
+    fn amplitudes(n1: f64, cos1: f64, n2: f64, cos2: f64) -> (f64, f64) {
+        let rs = (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2);
+        let rp = (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2);
+        (rs, rp)
+    }
+
+    //airy summation of all the bounces inside the film: R = |r12 + r23 e^(i delta)|^2 / |1 + r12 r23 e^(i delta)|^2
+    //expanding the complex magnitudes only leaves a cos(delta) term
+    fn airy(r12: f64, r23: f64, cos_delta: f64) -> f64 {
+        let num = r12 * r12 + r23 * r23 + 2.0 * r12 * r23 * cos_delta;
+        let den = 1.0 + r12 * r12 * r23 * r23 + 2.0 * r12 * r23 * cos_delta;
+        (num / den).clamp(0.0, 1.0)
+    }
+
+    //reflectance of the film sitting between outside_ior (where the ray comes from) and inside_ior
+    pub fn reflectance(&self, cos_theta: f64, outside_ior: f64, inside_ior: f64) -> Color {
+        let cos1 = cos_theta.clamp(0.0, 1.0);
+        let sin1_sq = 1.0 - cos1 * cos1;
+
+        //snell into the film and into whatever is under it
+        let sin2_sq = (outside_ior / self.ior).powi(2) * sin1_sq;
+        let sin3_sq = (outside_ior / inside_ior).powi(2) * sin1_sq;
+        if sin2_sq >= 1.0 || sin3_sq >= 1.0 {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+        let cos2 = (1.0 - sin2_sq).sqrt();
+        let cos3 = (1.0 - sin3_sq).sqrt();
+
+        let (r12s, r12p) = ThinFilm::amplitudes(outside_ior, cos1, self.ior, cos2);
+        let (r23s, r23p) = ThinFilm::amplitudes(self.ior, cos2, inside_ior, cos3);
+
+        let mut reflectance = Color::new(0.0, 0.0, 0.0);
+        for c in 0..3 {
+            //phase difference of the ray that went down and back up the film
+            let delta = 4.0 * PI * self.ior * self.thickness * cos2 / ThinFilm::WAVELENGTHS[c];
+            let rs = ThinFilm::airy(r12s, r23s, delta.cos());
+            let rp = ThinFilm::airy(r12p, r23p, delta.cos());
+            reflectance[c] = 0.5 * (rs + rp);
+        }
+
+        reflectance
+    }
+}
+
+//glass with no thickness, windows and bubbles
+//a real slab refracts in and then refracts back out parallel to where it came from, so the ray just continues straight
+//this way a window can be a single quad instead of a box and there is no offset from the refraction
+pub struct ThinDielectric {
+    refraction_index: f64,
+    film: Option<ThinFilm>,
+}
+
+impl ThinDielectric {
+    pub fn new(refraction_index: f64) -> ThinDielectric {
+        ThinDielectric {
+            refraction_index,
+            film: None,
+        }
+    }
+
+    //a soap bubble is the film itself with air on both sides
+    pub fn new_film(film: ThinFilm) -> ThinDielectric {
+        ThinDielectric {
+            refraction_index: film.ior,
+            film: Some(film),
+        }
+    }
+}
+
+//exact fresnel for unpolarized light going from outside into a material with relative index eta
+//schlick is too far off at grazing angles once the bounces inside get summed up (thin glass, hair)
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin_t_sq = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin_t_sq >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin_t_sq).sqrt();
+
+    let r_parl = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parl * r_parl + r_perp * r_perp)
+}
+
+impl Material for ThinDielectric {
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::SPECULAR_REFLECTION | BxdfFlags::SPECULAR_TRANSMISSION
+    }
+
+    fn sample(&self, _rec: &HitRecord, wo: Vec3, uc: f64, _u: [f64; 2]) -> Option<BsdfSample> {
+        let cos_theta = abs_cos_theta(wo).min(1.0);
+
+        //both sides of the slab are the same so front_face doesnt matter here
+        let reflectance = match &self.film {
+            Some(film) => film.reflectance(cos_theta, 1.0, 1.0),
+            None => {
+                //light bounces back and forth inside the slab, the geometric series of all the bounces is
+                //R + T^2 R (1 + R^2 + R^4 ...) = R + T^2 R / (1 - R^2)
+                let r = fresnel_dielectric(cos_theta, self.refraction_index);
+                let r = if r < 1.0 {
+                    r + (1.0 - r) * (1.0 - r) * r / (1.0 - r * r)
+                } else {
+                    r
+                };
+                Color::new(r, r, r)
+            }
+        };
+
+        let prob = (reflectance.x() + reflectance.y() + reflectance.z()) / 3.0;
+
+        if prob > uc {
+            Some(BsdfSample::new(
+                reflect(wo),
+                reflectance,
+                prob,
+                BxdfFlags::SPECULAR_REFLECTION,
+            ))
         } else {
-            direction = Vec3::refract(unit_direction, alternate_normal, ri);
+            Some(BsdfSample::new(
+                -wo,
+                Color::new(1.0, 1.0, 1.0) - reflectance,
+                1.0 - prob,
+                BxdfFlags::SPECULAR_TRANSMISSION,
+            ))
+        }
+    }
+}
+
+//how the emission changes with direction, dir is in the emitter's frame where z is the emitting side's normal
+//and x follows dpdu (the u edge for quads) so profiles that arent round have something to line up with
+//returns a multiplier, the plain lambertian emitter is 1 everywhere
+pub trait EmissionProfile {
+    fn scale(&self, dir: Vec3) -> f64;
+}
+
+//spotlight cone on an area light: full strength inside falloff_start, smoothly down to 0 at total_width (both half angles in degrees)
+pub struct SpotProfile {
+    cos_falloff_start: f64,
+    cos_total_width: f64,
+}
+
+impl SpotProfile {
+    pub fn new(total_width: f64, falloff_start: f64) -> SpotProfile {
+        let total_width = total_width.clamp(0.0, 90.0);
+        SpotProfile {
+            cos_falloff_start: falloff_start.min(total_width).to_radians().cos(),
+            cos_total_width: total_width.to_radians().cos(),
         }
+    }
+}
 
-        srec.skip_ray = Ray::new(rec.p, direction, r_in.time());
-        true
+impl EmissionProfile for SpotProfile {
+    fn scale(&self, dir: Vec3) -> f64 {
+        let cos_theta = dir.z();
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_total_width || self.cos_falloff_start <= self.cos_total_width {
+            return 0.0;
+        }
+
+        //smoothstep so the edge of the cone doesnt have a hard ring
+        let t =
+            (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        t * t * (3.0 - 2.0 * t)
     }
 }
 
+//area light. tex is the radiance leaving the surface, scale multiplies it (what the physical unit constructors set)
+//two_sided makes the back face emit too (by default only the side the normal points to does)
+//profile shapes the emission by direction, e.g. a spot cone or an ies file
 pub struct DiffuseLight {
     tex: Rc<dyn Texture>,
+    scale: f64,
+    pub two_sided: bool,
+    pub profile: Option<Rc<dyn EmissionProfile>>,
 }
 
 impl DiffuseLight {
     pub fn new(emit: Color) -> DiffuseLight {
+        DiffuseLight::new_tex(Rc::new(SolidColor::new(emit)))
+    }
+
+    pub fn new_tex(tex: Rc<dyn Texture>) -> DiffuseLight {
         DiffuseLight {
-            tex: Rc::new(SolidColor::new(emit)),
+            tex: tex,
+            scale: 1.0,
+            two_sided: false,
+            profile: None,
         }
     }
 
-    pub fn new_tex(tex: Rc<dyn Texture>) -> DiffuseLight {
-        DiffuseLight { tex: tex }
+    //a lambertian emitter with radiance L sends out pi * L * area watts per side, so L = watts / (pi * area * sides)
+    //color only gives the tint, it is normalized so its luminance is 1 (blackbody() already is)
+    //area is the area of the shape this goes on, the shapes dont share it with their material so pass in its area()
+    //this is exact for the plain emitter, a profile only takes away power so a spot will be dimmer than the watts say
+    pub fn new_power(color: Color, watts: f64, area: f64, two_sided: bool) -> DiffuseLight {
+        let sides = if two_sided { 2.0 } else { 1.0 };
+        let y = luminance(color);
+        let tint = if y > 0.0 { color / y } else { color };
+
+        let mut light = DiffuseLight::new(tint);
+        light.scale = watts / (PI * area.max(1e-12) * sides);
+        light.two_sided = two_sided;
+        light
+    }
+
+    //photometric version, 683 lumens per watt is the peak of the eye's sensitivity (that is how the lumen is defined)
+    pub fn new_lumens(color: Color, lumens: f64, area: f64, two_sided: bool) -> DiffuseLight {
+        DiffuseLight::new_power(color, lumens / 683.0, area, two_sided)
     }
 }
 
 impl Material for DiffuseLight {
-    fn emitted(&self, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
-        if !rec.front_face {
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        if !rec.front_face && !self.two_sided {
             return Color::new(0.0, 0.0, 0.0);
         }
 
-        self.tex.value(u, v, p)
+        let mut scale = self.scale;
+
+        //rec.normal already faces the ray, which for two sided lights is the normal of the side we are looking at
+        if let Some(profile) = &self.profile {
+            let frame = ONB::new_tangent(rec.normal, rec.dpdu);
+            let dir = frame.to_local(-Vec3::unit_vector(r_in.direction()));
+            scale *= profile.scale(dir);
+        }
+
+        if scale == 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        scale * self.tex.value(u, v, p)
     }
 }
 
@@ -221,15 +718,452 @@ impl Isotropic {
 }
 
 //quite literally scatter in a random direction
+//this is a phase function, not a surface, so there is no cosine and both hemispheres are fine
 impl Material for Isotropic {
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
-        srec.attenuation = self.tex.value(rec.u, rec.v, rec.p);
-        srec.pdf = Rc::new(SpherePDF::new());
-        srec.skip_pdf = false;
-        true
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::DIFFUSE_REFLECTION | BxdfFlags::DIFFUSE_TRANSMISSION
     }
 
-    fn scatter_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+    fn eval(&self, rec: &HitRecord, _wo: Vec3, _wi: Vec3) -> Color {
+        self.tex.value(rec.u, rec.v, rec.p) / (4.0 * PI)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, _uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        let z = 1.0 - 2.0 * u[0];
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * u[1];
+        let wi = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+
+        Some(BsdfSample::new(
+            wi,
+            self.eval(rec, wo, wi),
+            1.0 / (4.0 * PI),
+            BxdfFlags::DIFFUSE_TRANSMISSION,
+        ))
+    }
+
+    fn pdf(&self, _rec: &HitRecord, _wo: Vec3, _wi: Vec3) -> f64 {
         1.0 / (4.0 * PI)
     }
 }
+
+//Isotropic with any phase function, what media scatter with. Like Isotropic its lobes count as diffuse so the camera
+//still samples lights from inside the medium, and eval is the phase function times the albedo (there is no cosine)
+pub struct PhaseMaterial {
+    tex: Rc<dyn Texture>,
+    phase: Rc<dyn PhaseFunction>,
+}
+
+impl PhaseMaterial {
+    pub fn new(albedo: Color, phase: Rc<dyn PhaseFunction>) -> PhaseMaterial {
+        PhaseMaterial {
+            tex: Rc::new(SolidColor::new(albedo)),
+            phase,
+        }
+    }
+
+    pub fn new_tex(tex: Rc<dyn Texture>, phase: Rc<dyn PhaseFunction>) -> PhaseMaterial {
+        PhaseMaterial { tex, phase }
+    }
+}
+
+impl Material for PhaseMaterial {
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::DIFFUSE_REFLECTION | BxdfFlags::DIFFUSE_TRANSMISSION
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        self.tex.value(rec.u, rec.v, rec.p) * self.phase.p(wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        let (wi, pdf) = self.phase.sample_p(wo, uc, u)?;
+        let flags = if same_hemisphere(wo, wi) {
+            BxdfFlags::DIFFUSE_REFLECTION
+        } else {
+            BxdfFlags::DIFFUSE_TRANSMISSION
+        };
+
+        Some(BsdfSample::new(wi, self.eval(rec, wo, wi), pdf, flags))
+    }
+
+    fn pdf(&self, _rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        self.phase.pdf(wo, wi)
+    }
+}
+
+//no surface at all, for a MediumBoundary that only says where a medium starts (the edge of a fog bank, the water in a
+//fish tank without glass). Rays go straight through it, the camera only keeps track of the media
+pub struct InterfaceMaterial;
+
+impl Material for InterfaceMaterial {}
+
+//bump mapping on top of any material: the texture is a height field over the surface (its brightness), and the
+//shading normal is tilted by how fast the height changes along u and v. Needs a tangent (dpdu) from the primitive,
+//without one the normal is left alone. scale is the height of a white texel in world units
+//https://pbr-book.org/3ed-2018/Materials/Bump_Mapping
+pub struct BumpMapped {
+    inner: Rc<dyn Material>,
+    bump: Rc<dyn Texture>,
+    scale: f64,
+}
+
+impl BumpMapped {
+    pub fn new(inner: Rc<dyn Material>, bump: Rc<dyn Texture>, scale: f64) -> BumpMapped {
+        BumpMapped { inner, bump, scale }
+    }
+
+    fn height(&self, u: f64, v: f64, p: Point3) -> f64 {
+        self.scale * luminance(self.bump.value(u, v, p))
+    }
+}
+
+impl Material for BumpMapped {
+    fn flags(&self, rec: &HitRecord) -> BxdfFlags {
+        self.inner.flags(rec)
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        self.inner.eval(rec, wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        self.inner.sample(rec, wo, uc, u)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        self.inner.pdf(rec, wo, wi)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        self.inner.emitted(r_in, rec, u, v, p)
+    }
+
+    fn ior(&self) -> f64 {
+        self.inner.ior()
+    }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let n = self.inner.shading_normal(rec);
+        let dpdu_len = rec.dpdu.length();
+        if dpdu_len == 0.0 {
+            return n;
+        }
+
+        //we only know dpdu, so v is taken to run along n x dpdu at the same rate
+        let t = rec.dpdu / dpdu_len;
+        let b = Vec3::cross(n, t);
+        let dpdv = dpdu_len * b;
+
+        //finite differences in texture space, moving p along with it for textures that use the position
+        let delta = 1.0 / 1024.0;
+        let h = self.height(rec.u, rec.v, rec.p);
+        let dh_du = (self.height(rec.u + delta, rec.v, rec.p + delta * rec.dpdu) - h) / delta;
+        let dh_dv = (self.height(rec.u, rec.v + delta, rec.p + delta * dpdv) - h) / delta;
+
+        //height change per world unit along t and b, the normal leans away from the uphill direction
+        let bumped = n - (dh_du / dpdu_len) * t - (dh_dv / dpdu_len) * b;
+        if bumped.near_zero() {
+            return n;
+        }
+
+        Vec3::unit_vector(bumped)
+    }
+}
+
+//tangent space normal maps (what glTF and most game assets use): the texture stores the normal itself, red along dpdu,
+//green along n x dpdu (flipped where the uvs are mirrored, see tangent_sign) and blue along the normal, each mapped from 0..1 to -1..1. The texture must be linear (not srgb)
+//scale only tilts x and y, 1 is the normal as baked
+pub struct NormalMapped {
+    inner: Rc<dyn Material>,
+    normal_map: Rc<dyn Texture>,
+    scale: f64,
+}
+
+impl NormalMapped {
+    pub fn new(inner: Rc<dyn Material>, normal_map: Rc<dyn Texture>, scale: f64) -> NormalMapped {
+        NormalMapped {
+            inner,
+            normal_map,
+            scale,
+        }
+    }
+}
+
+impl Material for NormalMapped {
+    fn flags(&self, rec: &HitRecord) -> BxdfFlags {
+        self.inner.flags(rec)
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        self.inner.eval(rec, wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        self.inner.sample(rec, wo, uc, u)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        self.inner.pdf(rec, wo, wi)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        self.inner.emitted(r_in, rec, u, v, p)
+    }
+
+    fn ior(&self) -> f64 {
+        self.inner.ior()
+    }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let n = self.inner.shading_normal(rec);
+
+        //the tangent has to be perpendicular to the (smooth) normal, dpdu is only perpendicular to the flat one
+        let t = rec.dpdu - Vec3::dot(rec.dpdu, n) * n;
+        if t.near_zero() {
+            return n;
+        }
+        let t = Vec3::unit_vector(t);
+        let b = rec.tangent_sign * Vec3::cross(n, t);
+
+        let c = self.normal_map.value(rec.u, rec.v, rec.p);
+        let x = (2.0 * c.x() - 1.0) * self.scale;
+        let y = (2.0 * c.y() - 1.0) * self.scale;
+        let z = 2.0 * c.z() - 1.0;
+
+        let mapped = x * t + y * b + z * n;
+        if mapped.near_zero() {
+            return n;
+        }
+
+        Vec3::unit_vector(mapped)
+    }
+}
+
+//the metallic-roughness model from glTF: a GGX microfacet specular lobe on top of a lambertian base
+//metallic 0 is plastic-like (4% reflection at normal incidence, colored diffuse), metallic 1 is metal (the base color tints the reflection, no diffuse)
+//roughness is perceptual, alpha = roughness^2. The textures multiply the factors, metallic_roughness_tex has roughness in green and metallic in blue
+//https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html#appendix-b-brdf-implementation
+//https://jcgt.org/published/0007/04/01/ for sampling the visible normals
+pub struct MetallicRoughness {
+    base_color: Color,
+    metallic: f64,
+    roughness: f64,
+    pub base_color_tex: Option<Rc<dyn Texture>>,
+    pub metallic_roughness_tex: Option<Rc<dyn Texture>>,
+    pub emission: Color,
+    pub emission_tex: Option<Rc<dyn Texture>>,
+}
+
+//what the lobes need at one hit point, after the textures are looked up
+struct PbrParams {
+    base: Color,
+    metallic: f64,
+    alpha: f64,
+}
+
+impl MetallicRoughness {
+    pub fn new(base_color: Color, metallic: f64, roughness: f64) -> MetallicRoughness {
+        MetallicRoughness {
+            base_color,
+            metallic,
+            roughness,
+            base_color_tex: None,
+            metallic_roughness_tex: None,
+            emission: Color::new(0.0, 0.0, 0.0),
+            emission_tex: None,
+        }
+    }
+
+    fn params(&self, rec: &HitRecord) -> PbrParams {
+        let mut base = self.base_color;
+        if let Some(tex) = &self.base_color_tex {
+            base = base * tex.value(rec.u, rec.v, rec.p);
+        }
+        if let Some(c) = rec.vertex_color {
+            base = base * c;
+        }
+
+        let (mut metallic, mut roughness) = (self.metallic, self.roughness);
+        if let Some(tex) = &self.metallic_roughness_tex {
+            let mr = tex.value(rec.u, rec.v, rec.p);
+            roughness *= mr.y();
+            metallic *= mr.z();
+        }
+
+        //a perfect mirror would be a delta, keep a tiny bit of roughness so the lobe can still be evaluated
+        let roughness = roughness.clamp(0.0, 1.0);
+        PbrParams {
+            base,
+            metallic: metallic.clamp(0.0, 1.0),
+            alpha: (roughness * roughness).max(1e-3),
+        }
+    }
+
+    fn f0(p: &PbrParams) -> Color {
+        (1.0 - p.metallic) * Color::new(0.04, 0.04, 0.04) + p.metallic * p.base
+    }
+
+    fn schlick(f0: Color, cos: f64) -> Color {
+        let m = (1.0 - cos).clamp(0.0, 1.0).powi(5);
+        f0 + m * (Color::new(1.0, 1.0, 1.0) - f0)
+    }
+
+    //GGX normal distribution
+    fn d(wm: Vec3, alpha: f64) -> f64 {
+        let cos2 = wm.z() * wm.z();
+        let a2 = alpha * alpha;
+        let denom = cos2 * (a2 - 1.0) + 1.0;
+        a2 / (PI * denom * denom)
+    }
+
+    //smith lambda for GGX, how much of the surface is hidden by other microfacets from direction w
+    fn lambda(w: Vec3, alpha: f64) -> f64 {
+        let cos2 = w.z() * w.z();
+        if cos2 == 0.0 {
+            return f64::INFINITY;
+        }
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        0.5 * ((1.0 + alpha * alpha * tan2).sqrt() - 1.0)
+    }
+
+    fn g1(w: Vec3, alpha: f64) -> f64 {
+        1.0 / (1.0 + MetallicRoughness::lambda(w, alpha))
+    }
+
+    //height correlated masking-shadowing
+    fn g(wo: Vec3, wi: Vec3, alpha: f64) -> f64 {
+        1.0 / (1.0 + MetallicRoughness::lambda(wo, alpha) + MetallicRoughness::lambda(wi, alpha))
+    }
+
+    //a normal from the part of the microfacets wo can actually see (Heitz 2018), wo above the surface
+    fn sample_wm(wo: Vec3, alpha: f64, u: [f64; 2]) -> Vec3 {
+        let wh = Vec3::unit_vector(Vec3::new(alpha * wo.x(), alpha * wo.y(), wo.z()));
+        let t1 = if wh.z() < 0.99999 {
+            Vec3::unit_vector(Vec3::cross(Vec3::new(0.0, 0.0, 1.0), wh))
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = Vec3::cross(wh, t1);
+
+        let r = u[0].sqrt();
+        let phi = 2.0 * PI * u[1];
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + wh.z());
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * r * phi.sin();
+        let p3 = (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        let nh = p1 * t1 + p2 * t2 + p3 * wh;
+        Vec3::unit_vector(Vec3::new(alpha * nh.x(), alpha * nh.y(), nh.z().max(1e-6)))
+    }
+
+    //how often the specular lobe gets sampled, by how bright it is compared to the diffuse one
+    fn specular_probability(p: &PbrParams, wo: Vec3) -> f64 {
+        let spec = luminance(MetallicRoughness::schlick(
+            MetallicRoughness::f0(p),
+            abs_cos_theta(wo),
+        ));
+        let diffuse = (1.0 - p.metallic) * luminance(p.base) * (1.0 - spec);
+        if diffuse <= 0.0 {
+            return 1.0;
+        }
+
+        //never too rare, the highlight is where the noise would be
+        (spec / (spec + diffuse)).max(0.25)
+    }
+
+    fn eval_params(p: &PbrParams, wo: Vec3, wi: Vec3) -> Color {
+        if cos_theta(wo) <= 0.0 || cos_theta(wi) <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let wm = wo + wi;
+        if wm.near_zero() {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let wm = Vec3::unit_vector(wm);
+
+        let f = MetallicRoughness::schlick(MetallicRoughness::f0(p), Vec3::dot(wo, wm).abs());
+        //D * G * F / (4 cos_o cos_i), times cos_i
+        let specular = MetallicRoughness::d(wm, p.alpha) * MetallicRoughness::g(wo, wi, p.alpha)
+            / (4.0 * cos_theta(wo))
+            * f;
+        let diffuse =
+            (1.0 - p.metallic) * (Color::new(1.0, 1.0, 1.0) - f) * p.base * (cos_theta(wi) / PI);
+
+        specular + diffuse
+    }
+
+    fn pdf_params(p: &PbrParams, wo: Vec3, wi: Vec3) -> f64 {
+        if cos_theta(wo) <= 0.0 || cos_theta(wi) <= 0.0 {
+            return 0.0;
+        }
+
+        let wm = wo + wi;
+        if wm.near_zero() {
+            return 0.0;
+        }
+        let wm = Vec3::unit_vector(wm);
+
+        //visible normal pdf G1(wo) D(wm) (wo . wm) / cos_o, and the reflection jacobian 1 / (4 wo . wm)
+        let spec_pdf = MetallicRoughness::g1(wo, p.alpha) * MetallicRoughness::d(wm, p.alpha)
+            / (4.0 * cos_theta(wo));
+        let diffuse_pdf = cos_theta(wi) / PI;
+
+        let ps = MetallicRoughness::specular_probability(p, wo);
+        ps * spec_pdf + (1.0 - ps) * diffuse_pdf
+    }
+}
+
+impl Material for MetallicRoughness {
+    fn flags(&self, _rec: &HitRecord) -> BxdfFlags {
+        BxdfFlags::GLOSSY_REFLECTION | BxdfFlags::DIFFUSE_REFLECTION
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        MetallicRoughness::eval_params(&self.params(rec), wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        if cos_theta(wo) <= 0.0 {
+            return None;
+        }
+
+        let p = self.params(rec);
+        let ps = MetallicRoughness::specular_probability(&p, wo);
+
+        let (wi, flags) = if uc < ps {
+            let wm = MetallicRoughness::sample_wm(wo, p.alpha, u);
+            let wi = -wo + 2.0 * Vec3::dot(wo, wm) * wm;
+            (wi, BxdfFlags::GLOSSY_REFLECTION)
+        } else {
+            (cosine_hemisphere(u), BxdfFlags::DIFFUSE_REFLECTION)
+        };
+        if cos_theta(wi) <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample::new(
+            wi,
+            MetallicRoughness::eval_params(&p, wo, wi),
+            MetallicRoughness::pdf_params(&p, wo, wi),
+            flags,
+        ))
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        MetallicRoughness::pdf_params(&self.params(rec), wo, wi)
+    }
+
+    //like the area light, only the front of the surface glows
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        if !rec.front_face || self.emission.near_zero() {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        match &self.emission_tex {
+            Some(tex) => self.emission * tex.value(u, v, p),
+            None => self.emission,
+        }
+    }
+}
@@ -0,0 +1,674 @@
+use std::rc::Rc;
+
+use crate::aabb::AABB;
+use crate::bsdf::{BsdfSample, BxdfFlags};
+use crate::color::{Color, blackbody};
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::{Material, PhaseMaterial};
+use crate::perlin::Perlin;
+use crate::phase::{IsotropicPhase, PhaseFunction};
+use crate::ray::Ray;
+use crate::utils::random_double;
+use crate::vec3::{Point3, Vec3};
+
+//media the camera looks for along every path segment instead of objects in the world like ConstantMedium
+//they do two things: pick where a ray really collides with them (and scatters), and say how much light gets through
+//a segment. The second one is for shadow rays, a light behind thin smoke gets dimmed instead of being all there or all gone
+//https://pbr-book.org/4ed/Volume_Scattering/Volume_Scattering_Processes
+pub trait Medium {
+    //the first real collision inside ray_t, as a record with the phase function to scatter off at that point
+    fn sample(&self, r: &Ray, ray_t: Interval) -> MediumSample;
+
+    //the fraction of light that makes it through along the ray inside ray_t
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color;
+}
+
+//where a ray collides in a medium (None when it gets through ray_t) and what the path has to be multiplied by for
+//getting there. Tracking that picks collisions by the whole extinction needs no weight (it is 1), media that only
+//sample the scattering part carry the absorption along the way in it
+pub struct MediumSample {
+    pub collision: Option<HitRecord>,
+    pub weight: Color,
+}
+
+impl MediumSample {
+    fn new(collision: Option<HitRecord>) -> MediumSample {
+        MediumSample {
+            collision,
+            weight: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+//no surface at a collision, the normal is made up the same way ConstantMedium does it
+fn collision_record(r: &Ray, t: f64, mat: &Rc<dyn Material>) -> HitRecord {
+    let mut rec = HitRecord::new();
+    rec.t = t;
+    rec.p = r.at(t);
+    rec.normal = Vec3::new(1.0, 0.0, 0.0);
+    rec.shading_normal = rec.normal;
+    rec.front_face = true;
+    rec.mat = mat.clone();
+    rec
+}
+
+//how much stuff there is at a point, the medium multiplies it by its absorption and scattering coefficients
+pub trait Density {
+    fn density(&self, p: Point3) -> f64;
+
+    //nothing inside bbox can be denser than this. It doesnt have to be tight but everything runs faster when it is
+    fn max_density(&self, bbox: &AABB) -> f64;
+}
+
+//densities on the corners of an nx * ny * nz grid stretched over bbox, trilinear in between and 0 outside of it
+pub struct GridDensity {
+    values: Vec<f64>,
+    n: [usize; 3],
+    bbox: AABB,
+}
+
+impl GridDensity {
+    //x changes fastest, then y, then z
+    pub fn new(values: Vec<f64>, nx: usize, ny: usize, nz: usize, bbox: AABB) -> GridDensity {
+        assert!(
+            nx >= 2 && ny >= 2 && nz >= 2 && values.len() == nx * ny * nz,
+            "density grid needs at least 2x2x2 samples and nx * ny * nz values"
+        );
+
+        GridDensity {
+            values,
+            n: [nx, ny, nz],
+            bbox,
+        }
+    }
+
+    //samples density at every grid corner, for baking something expensive once
+    pub fn from_fn(
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        bbox: AABB,
+        density: impl Fn(Point3) -> f64,
+    ) -> GridDensity {
+        let at = |axis: usize, i: usize, n: usize| {
+            let ax = bbox.axis_interval(axis);
+            ax.min + ax.size() * i as f64 / (n - 1) as f64
+        };
+
+        let values = (0..nx * ny * nz)
+            .map(|k| {
+                let (i, j, l) = (k % nx, (k / nx) % ny, k / (nx * ny));
+                density(Point3::new(at(0, i, nx), at(1, j, ny), at(2, l, nz)))
+            })
+            .collect();
+        GridDensity::new(values, nx, ny, nz, bbox)
+    }
+
+    fn value(&self, i: usize, j: usize, k: usize) -> f64 {
+        self.values[(k * self.n[1] + j) * self.n[0] + i]
+    }
+
+    //where x is along an axis in grid units, 0 at the first sample and n - 1 at the last
+    fn grid_pos(&self, axis: usize, x: f64) -> f64 {
+        let ax = self.bbox.axis_interval(axis);
+        (x - ax.min) / ax.size() * (self.n[axis] - 1) as f64
+    }
+}
+
+impl Density for GridDensity {
+    fn density(&self, p: Point3) -> f64 {
+        let mut cell = [0; 3];
+        let mut f = [0.0; 3];
+        for axis in 0..3 {
+            let g = self.grid_pos(axis, p[axis]);
+            let last = (self.n[axis] - 1) as f64;
+            if !(0.0..=last).contains(&g) {
+                return 0.0;
+            }
+            cell[axis] = (g.floor() as usize).min(self.n[axis] - 2);
+            f[axis] = g - cell[axis] as f64;
+        }
+
+        let mut accum = 0.0;
+        for corner in 0..8 {
+            let (di, dj, dk) = (corner & 1, (corner >> 1) & 1, corner >> 2);
+            let weight = [di, dj, dk]
+                .iter()
+                .zip(f)
+                .map(|(&d, f)| if d == 1 { f } else { 1.0 - f })
+                .product::<f64>();
+            accum += weight * self.value(cell[0] + di, cell[1] + dj, cell[2] + dk);
+        }
+        accum
+    }
+
+    //trilinear never goes above its corners, so the highest sample that touches bbox is exact
+    fn max_density(&self, bbox: &AABB) -> f64 {
+        let mut range = [(0, 0); 3];
+        for (axis, range) in range.iter_mut().enumerate() {
+            let ax = bbox.axis_interval(axis);
+            let last = (self.n[axis] - 1) as f64;
+            let (lo, hi) = (
+                self.grid_pos(axis, ax.min).floor(),
+                self.grid_pos(axis, ax.max).ceil(),
+            );
+            if hi < 0.0 || lo > last {
+                return 0.0;
+            }
+            *range = (lo.max(0.0) as usize, hi.min(last) as usize);
+        }
+
+        let mut max: f64 = 0.0;
+        for k in range[2].0..=range[2].1 {
+            for j in range[1].0..=range[1].1 {
+                for i in range[0].0..=range[0].1 {
+                    max = max.max(self.value(i, j, k));
+                }
+            }
+        }
+        max
+    }
+}
+
+//perlin turbulence with the thin parts cut away so there are clear gaps between the puffs:
+//scale * max(turb(frequency * p) - threshold, 0)
+pub struct NoiseDensity {
+    noise: Perlin,
+    frequency: f64,
+    octaves: i64,
+    threshold: f64,
+    scale: f64,
+}
+
+impl NoiseDensity {
+    pub fn new(frequency: f64, octaves: i64, threshold: f64, scale: f64) -> NoiseDensity {
+        NoiseDensity {
+            noise: Perlin::new(),
+            frequency,
+            octaves: octaves.max(1),
+            threshold,
+            scale,
+        }
+    }
+}
+
+impl Density for NoiseDensity {
+    fn density(&self, p: Point3) -> f64 {
+        let turb = self.noise.turb(self.frequency * p, self.octaves);
+        self.scale * (turb - self.threshold).max(0.0)
+    }
+
+    //every octave of noise stays under 1 and they halve each time, so turb is under 2 (1 - 0.5^octaves)
+    fn max_density(&self, _bbox: &AABB) -> f64 {
+        let turb_max = 2.0 * (1.0 - 0.5f64.powi(self.octaves as i32));
+        self.scale * (turb_max - self.threshold).max(0.0)
+    }
+}
+
+//any function of position, with the upper bound given by hand
+pub struct ProceduralDensity {
+    density: Box<dyn Fn(Point3) -> f64>,
+    max: f64,
+}
+
+impl ProceduralDensity {
+    pub fn new(density: impl Fn(Point3) -> f64 + 'static, max: f64) -> ProceduralDensity {
+        ProceduralDensity {
+            density: Box::new(density),
+            max,
+        }
+    }
+}
+
+impl Density for ProceduralDensity {
+    fn density(&self, p: Point3) -> f64 {
+        (self.density)(p).clamp(0.0, self.max)
+    }
+
+    fn max_density(&self, _bbox: &AABB) -> f64 {
+        self.max
+    }
+}
+
+//cells per axis of the majorant grid
+pub const MAJORANT_RESOLUTION: usize = 16;
+
+//the highest density in each cell of a coarse grid over the medium. Tracking takes steps sized for the cell the ray is in,
+//so the thin and empty parts dont cost as many tentative collisions as the thickest part would
+struct MajorantGrid {
+    bbox: AABB,
+    max: Vec<f64>,
+}
+
+impl MajorantGrid {
+    fn new(bbox: AABB, density: &dyn Density) -> MajorantGrid {
+        let n = MAJORANT_RESOLUTION;
+        let max = (0..n * n * n)
+            .map(|k| {
+                density.max_density(&MajorantGrid::cell_bbox(
+                    &bbox,
+                    [k % n, (k / n) % n, k / (n * n)],
+                ))
+            })
+            .collect();
+
+        MajorantGrid { bbox, max }
+    }
+
+    fn cell_bbox(bbox: &AABB, cell: [usize; 3]) -> AABB {
+        let side = |axis: usize| {
+            let ax = bbox.axis_interval(axis);
+            let size = ax.size() / MAJORANT_RESOLUTION as f64;
+            Interval::new(
+                ax.min + size * cell[axis] as f64,
+                ax.min + size * (cell[axis] + 1) as f64,
+            )
+        };
+        AABB::new(side(0), side(1), side(2))
+    }
+
+    //3d dda, calls visit(t0, t1, majorant) for every cell the ray goes through between t0 and t1 in order
+    //visit says if it wants to keep going
+    fn traverse(&self, r: &Ray, t0: f64, t1: f64, mut visit: impl FnMut(f64, f64, f64) -> bool) {
+        let (o, d) = (r.origin(), r.direction());
+        let (mut t0, mut t1) = (t0, t1);
+        for axis in 0..3 {
+            let ax = self.bbox.axis_interval(axis);
+            let inv = 1.0 / d[axis];
+            let a = (ax.min - o[axis]) * inv;
+            let b = (ax.max - o[axis]) * inv;
+            t0 = t0.max(a.min(b));
+            t1 = t1.min(a.max(b));
+        }
+        if t0 >= t1 {
+            return;
+        }
+
+        let n = MAJORANT_RESOLUTION as i64;
+        let p = r.at(t0);
+        let mut cell = [0i64; 3];
+        let mut step = [0i64; 3];
+        let mut t_next = [f64::INFINITY; 3];
+        let mut t_delta = [f64::INFINITY; 3];
+        for axis in 0..3 {
+            let ax = self.bbox.axis_interval(axis);
+            let size = ax.size() / n as f64;
+            cell[axis] = (((p[axis] - ax.min) / size).floor() as i64).clamp(0, n - 1);
+
+            if d[axis] > 0.0 {
+                step[axis] = 1;
+                t_next[axis] = (ax.min + (cell[axis] + 1) as f64 * size - o[axis]) / d[axis];
+                t_delta[axis] = size / d[axis];
+            } else if d[axis] < 0.0 {
+                step[axis] = -1;
+                t_next[axis] = (ax.min + cell[axis] as f64 * size - o[axis]) / d[axis];
+                t_delta[axis] = -size / d[axis];
+            }
+        }
+
+        let mut t = t0;
+        loop {
+            let axis = (0..3)
+                .min_by(|&a, &b| t_next[a].total_cmp(&t_next[b]))
+                .unwrap();
+            let exit = t_next[axis].min(t1);
+
+            let index = ((cell[2] * n + cell[1]) * n + cell[0]) as usize;
+            if exit > t && !visit(t, exit, self.max[index]) {
+                return;
+            }
+            if t_next[axis] >= t1 {
+                return;
+            }
+
+            t = t_next[axis];
+            cell[axis] += step[axis];
+            if !(0..n).contains(&cell[axis]) {
+                return;
+            }
+            t_next[axis] += t_delta[axis];
+        }
+    }
+}
+
+//smoke and clouds whose density changes from place to place, inside a closed boundary of any shape
+//tracking fills the medium up with fictitious particles to a constant majorant density. Free flight through that is
+//just an exponential step, and at each tentative collision the real density decides if it was a real one
+//delta tracking stops at the first real one, ratio tracking multiplies in the chance that each one was fictitious
+//https://jannovak.info/publications/SDTracking/SDTracking.pdf
+pub struct HeterogeneousMedium {
+    //None for media that fill their box, like a vdb grid
+    boundary: Option<Rc<dyn Hittable>>,
+    density: Rc<dyn Density>,
+    sigma_a: f64,
+    sigma_t: f64,
+    phase_function: Rc<dyn Material>,
+    majorant: MajorantGrid,
+}
+
+impl HeterogeneousMedium {
+    //sigma_a and sigma_s are how much a density of 1 absorbs and scatters per unit length, albedo tints what scatters
+    //and phase says which way it goes. Absorption isnt a separate event, every collision scatters and the light that
+    //would have been absorbed is taken out of the albedo instead, which is the same on average and a lot less noisy
+    pub fn new(
+        boundary: Rc<dyn Hittable>,
+        density: Rc<dyn Density>,
+        sigma_a: f64,
+        sigma_s: f64,
+        albedo: Color,
+        phase: Rc<dyn PhaseFunction>,
+    ) -> HeterogeneousMedium {
+        let bbox = boundary.bounding_box();
+        HeterogeneousMedium::build(
+            Some(boundary),
+            bbox,
+            density,
+            [sigma_a, sigma_s],
+            albedo,
+            phase,
+        )
+    }
+
+    //same thing filling a box, without a shape to bound it the tracking only has to clip the ray to bbox
+    pub fn new_bounded(
+        bbox: AABB,
+        density: Rc<dyn Density>,
+        sigma_a: f64,
+        sigma_s: f64,
+        albedo: Color,
+        phase: Rc<dyn PhaseFunction>,
+    ) -> HeterogeneousMedium {
+        HeterogeneousMedium::build(None, bbox, density, [sigma_a, sigma_s], albedo, phase)
+    }
+
+    fn build(
+        boundary: Option<Rc<dyn Hittable>>,
+        bbox: AABB,
+        density: Rc<dyn Density>,
+        sigma: [f64; 2],
+        albedo: Color,
+        phase: Rc<dyn PhaseFunction>,
+    ) -> HeterogeneousMedium {
+        let [sigma_a, sigma_s] = sigma;
+        let sigma_t = sigma_a + sigma_s;
+        let survival = if sigma_t > 0.0 {
+            sigma_s / sigma_t
+        } else {
+            0.0
+        };
+
+        HeterogeneousMedium {
+            majorant: MajorantGrid::new(bbox, density.as_ref()),
+            boundary,
+            density,
+            sigma_a,
+            sigma_t,
+            phase_function: Rc::new(PhaseMaterial::new(albedo * survival, phase)),
+        }
+    }
+
+    //makes the medium glow like fire: temperature (scaled to kelvin by kelvin_scale) gives the color of a black body
+    //and how bright it is goes with the 4th power of it (stefan boltzmann), relative to 1000K times brightness
+    //only the absorbing part glows, a medium that just scatters is no fire
+    pub fn set_temperature(
+        &mut self,
+        temperature: Rc<dyn Density>,
+        kelvin_scale: f64,
+        brightness: f64,
+    ) {
+        let emission = self.sigma_a / self.sigma_t.max(1e-12) * brightness;
+        self.phase_function = Rc::new(BlackbodyEmission::new(
+            self.phase_function.clone(),
+            temperature,
+            kelvin_scale,
+            emission,
+        ));
+    }
+
+    //the pieces of the ray inside the boundary, from all of its crossings so hollow and concave shapes work too
+    //crossings are looked for past ray_t.max as well, the first one after the end says if the end is inside
+    fn spans(&self, r: &Ray, ray_t: Interval) -> Vec<(f64, f64)> {
+        let Some(boundary) = &self.boundary else {
+            return vec![(ray_t.min, ray_t.max)];
+        };
+
+        let mut hits = Vec::new();
+        boundary.hit_all(r, Interval::new(ray_t.min, f64::INFINITY), &mut hits);
+
+        let mut spans = Vec::new();
+        let mut start = match hits.first() {
+            Some(first) if !first.front_face => Some(ray_t.min),
+            _ => None,
+        };
+        for hit in &hits {
+            if hit.t >= ray_t.max {
+                break;
+            }
+            if hit.front_face {
+                start = Some(hit.t);
+            } else if let Some(s) = start.take() {
+                spans.push((s, hit.t));
+            }
+        }
+        if let Some(s) = start {
+            spans.push((s, ray_t.max));
+        }
+        spans
+    }
+
+    //walks tentative collisions along the ray inside the boundary, collide gets the t and the chance that it is a
+    //real one (density / majorant) and says if it wants to keep going
+    fn track(&self, r: &Ray, ray_t: Interval, mut collide: impl FnMut(f64, f64) -> bool) {
+        let dir_len = r.direction().length();
+        if dir_len == 0.0 || self.sigma_t <= 0.0 {
+            return;
+        }
+
+        let mut done = false;
+        for (t0, t1) in self.spans(r, ray_t) {
+            self.majorant.traverse(r, t0, t1, |a, b, max_density| {
+                if max_density <= 0.0 {
+                    return true;
+                }
+
+                //per unit of t instead of per unit of length, the direction doesnt have to be normalized
+                let sigma_maj = self.sigma_t * max_density * dir_len;
+                let mut t = a;
+                loop {
+                    //the exponential step is memoryless, so running past the cell just starts over in the next one
+                    t -= (1.0 - random_double()).ln() / sigma_maj;
+                    if t >= b {
+                        return true;
+                    }
+
+                    let ratio = (self.density.density(r.at(t)) / max_density).min(1.0);
+                    if !collide(t, ratio) {
+                        done = true;
+                        return false;
+                    }
+                }
+            });
+
+            if done {
+                return;
+            }
+        }
+    }
+}
+
+//highest temperature in the table and the step between entries, in kelvin
+const BLACKBODY_MAX_K: f64 = 12000.0;
+const BLACKBODY_STEP_K: f64 = 100.0;
+
+//the phase function of a medium plus emission from its temperature. blackbody() integrates a spectrum every call so the
+//colors are worked out once per 100K and blended
+struct BlackbodyEmission {
+    inner: Rc<dyn Material>,
+    temperature: Rc<dyn Density>,
+    kelvin_scale: f64,
+    emission: f64,
+    table: Vec<Color>,
+}
+
+impl BlackbodyEmission {
+    fn new(
+        inner: Rc<dyn Material>,
+        temperature: Rc<dyn Density>,
+        kelvin_scale: f64,
+        emission: f64,
+    ) -> BlackbodyEmission {
+        let steps = (BLACKBODY_MAX_K / BLACKBODY_STEP_K) as usize;
+        let table = (0..=steps)
+            .map(|i| {
+                let k = i as f64 * BLACKBODY_STEP_K;
+                let power = (k / 1000.0).powi(4);
+                power * blackbody(k)
+            })
+            .collect();
+
+        BlackbodyEmission {
+            inner,
+            temperature,
+            kelvin_scale,
+            emission,
+            table,
+        }
+    }
+}
+
+impl Material for BlackbodyEmission {
+    fn flags(&self, rec: &HitRecord) -> BxdfFlags {
+        self.inner.flags(rec)
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        self.inner.eval(rec, wo, wi)
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, uc: f64, u: [f64; 2]) -> Option<BsdfSample> {
+        self.inner.sample(rec, wo, uc, u)
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        self.inner.pdf(rec, wo, wi)
+    }
+
+    //collisions happen in proportion to sigma_t, so the absorbed share of them carries the emission (sigma_a / sigma_t)
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, _u: f64, _v: f64, p: Point3) -> Color {
+        let k = (self.temperature.density(p) * self.kelvin_scale).clamp(0.0, BLACKBODY_MAX_K);
+        let x = k / BLACKBODY_STEP_K;
+        let i = (x.floor() as usize).min(self.table.len() - 2);
+        let f = x - i as f64;
+
+        self.emission * ((1.0 - f) * self.table[i] + f * self.table[i + 1])
+    }
+}
+
+impl Medium for HeterogeneousMedium {
+    //delta tracking
+    fn sample(&self, r: &Ray, ray_t: Interval) -> MediumSample {
+        let mut collision = None;
+        self.track(r, ray_t, |t, ratio| {
+            if random_double() < ratio {
+                collision = Some(t);
+                return false;
+            }
+            true
+        });
+
+        MediumSample::new(collision.map(|t| collision_record(r, t, &self.phase_function)))
+    }
+
+    //ratio tracking
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        let mut tr = 1.0;
+        self.track(r, ray_t, |_, ratio| {
+            tr *= 1.0 - ratio;
+
+            //russian roulette once most of it is gone, so thick media dont walk every tentative collision for nothing
+            if tr < 0.1 {
+                if random_double() < 0.5 {
+                    tr = 0.0;
+                    return false;
+                }
+                tr *= 2.0;
+            }
+            true
+        });
+
+        Color::new(tr, tr, tr)
+    }
+}
+
+//the same stuff everywhere and no boundary of its own, it fills whatever it is put in: the inside of a surface through a
+//MediumInterface (tinted glass, juice in a glass) or the whole scene as the medium the camera sits in (fog)
+//only scattering is sampled as collisions, absorption is worked out exactly (beer lambert) and carried in the weight. That
+//keeps a glass that only absorbs free of noise, and lets the absorption be a color without spectral tracking
+//https://pbr-book.org/4ed/Volume_Scattering/Media#HomogeneousMedium
+pub struct HomogeneousMedium {
+    sigma_a: Color,
+    sigma_s: f64,
+    phase_function: Rc<dyn Material>,
+}
+
+impl HomogeneousMedium {
+    //sigma_a is per channel, sigma_s scatters every channel the same and albedo tints what it scatters
+    pub fn new(
+        sigma_a: Color,
+        sigma_s: f64,
+        albedo: Color,
+        phase: Rc<dyn PhaseFunction>,
+    ) -> HomogeneousMedium {
+        HomogeneousMedium {
+            sigma_a,
+            sigma_s,
+            phase_function: Rc::new(PhaseMaterial::new(albedo, phase)),
+        }
+    }
+
+    //colored glass or liquid that doesnt scatter: light that travels distance through it comes out as color
+    //(beer lambert backwards, sigma_a = -ln(color) / distance)
+    pub fn absorbing(color: Color, distance: f64) -> HomogeneousMedium {
+        let channel = |c: f64| -c.clamp(1e-6, 1.0).ln() / distance;
+        HomogeneousMedium::new(
+            Color::new(channel(color.x()), channel(color.y()), channel(color.z())),
+            0.0,
+            Color::new(1.0, 1.0, 1.0),
+            Rc::new(IsotropicPhase),
+        )
+    }
+
+    //a channel that doesnt absorb stays 1 even for rays that go on forever (0 * inf would be NaN)
+    fn beer_lambert(sigma: Color, length: f64) -> Color {
+        let channel = |s: f64| if s > 0.0 { (-s * length).exp() } else { 1.0 };
+        Color::new(channel(sigma.x()), channel(sigma.y()), channel(sigma.z()))
+    }
+}
+
+impl Medium for HomogeneousMedium {
+    //the distance is picked by the scattering alone, its pdf cancels with sigma_s (or the chance of getting through)
+    //and what is left is the absorption up to wherever the ray stopped
+    fn sample(&self, r: &Ray, ray_t: Interval) -> MediumSample {
+        let dir_len = r.direction().length();
+        let mut t = ray_t.max;
+        if self.sigma_s > 0.0 && dir_len > 0.0 {
+            t = ray_t.min - (1.0 - random_double()).ln() / (self.sigma_s * dir_len);
+        }
+
+        let collided = t < ray_t.max;
+        let end = if collided { t } else { ray_t.max };
+        let length = (end - ray_t.min).max(0.0) * dir_len;
+
+        MediumSample {
+            collision: collided.then(|| collision_record(r, t, &self.phase_function)),
+            weight: HomogeneousMedium::beer_lambert(self.sigma_a, length),
+        }
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> Color {
+        let length = (ray_t.max - ray_t.min).max(0.0) * r.direction().length();
+        let sigma_t = self.sigma_a + Color::new(self.sigma_s, self.sigma_s, self.sigma_s);
+        HomogeneousMedium::beer_lambert(sigma_t, length)
+    }
+}
@@ -0,0 +1,175 @@
+use std::rc::Rc;
+
+use crate::aabb::AABB;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::medium::Medium;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+//which media are on the two sides of a surface. inside is what fills the object (None is nothing, clear glass),
+//outside is what a ray goes into when it leaves and isnt inside anything else, None there keeps whatever the path was
+//in before (the medium the camera sits in, or the last outside it went through)
+//priority is for objects that overlap: where they do, the highest one is the one that is really there and the surfaces
+//of the others are skipped. Liquid in a glass is modeled a little bigger than the hole so it overlaps the walls, and
+//gets a higher priority than the glass so the glass/liquid boundary is the liquid's surface
+//https://www.researchgate.net/publication/247523037_Simple_Nested_Dielectrics_in_Ray_Traced_Images
+pub struct MediumInterface {
+    pub inside: Option<Rc<dyn Medium>>,
+    pub outside: Option<Rc<dyn Medium>>,
+    pub priority: i64,
+}
+
+impl MediumInterface {
+    pub fn new(inside: Option<Rc<dyn Medium>>, outside: Option<Rc<dyn Medium>>) -> MediumInterface {
+        MediumInterface {
+            inside,
+            outside,
+            priority: 0,
+        }
+    }
+
+    pub fn new_priority(
+        inside: Option<Rc<dyn Medium>>,
+        outside: Option<Rc<dyn Medium>>,
+        priority: i64,
+    ) -> MediumInterface {
+        MediumInterface {
+            inside,
+            outside,
+            priority,
+        }
+    }
+}
+
+//puts a medium interface on every hit of an object, the object needs a closed surface with outward normals so
+//front_face says if a ray goes in or out. Give it InterfaceMaterial for a boundary that isnt a surface at all
+pub struct MediumBoundary {
+    object: Rc<dyn Hittable>,
+    interface: Rc<MediumInterface>,
+}
+
+impl MediumBoundary {
+    pub fn new(object: Rc<dyn Hittable>, interface: MediumInterface) -> MediumBoundary {
+        MediumBoundary {
+            object,
+            interface: Rc::new(interface),
+        }
+    }
+}
+
+impl Hittable for MediumBoundary {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if !self.object.hit(r, ray_t, rec) {
+            return false;
+        }
+
+        rec.interface = Some(self.interface.clone());
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.object.bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, dir: Vec3) -> f64 {
+        self.object.pdf_value(origin, dir)
+    }
+
+    fn random(&self, origin: Vec3) -> Vec3 {
+        self.object.random(origin)
+    }
+
+    fn hit_all(&self, r: &Ray, ray_t: Interval, hits: &mut Vec<HitRecord>) {
+        let first = hits.len();
+        self.object.hit_all(r, ray_t, hits);
+        for rec in &mut hits[first..] {
+            rec.interface = Some(self.interface.clone());
+        }
+    }
+}
+
+//an object the path is inside of, with the index of refraction of its material for the surfaces nested in it
+#[derive(Clone)]
+struct Entry {
+    interface: Rc<MediumInterface>,
+    ior: f64,
+}
+
+//what a path is inside of. Each ray through a surface with an interface pushes it (going in) or takes it out (coming
+//out), the medium the ray is in is the inside of the highest priority object, or base when it isnt in anything
+//copies of it go with shadow rays so they see the same media
+#[derive(Clone)]
+pub struct MediumStack {
+    base: Option<Rc<dyn Medium>>,
+    entries: Vec<Entry>,
+}
+
+impl MediumStack {
+    pub fn new(base: Option<Rc<dyn Medium>>) -> MediumStack {
+        MediumStack {
+            base,
+            entries: Vec::new(),
+        }
+    }
+
+    //highest priority, the latest one wins a tie. skip leaves out the object whose surface we are at
+    fn top(&self, skip: Option<&Rc<MediumInterface>>) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .filter(|e| skip.is_none_or(|s| !Rc::ptr_eq(&e.interface, s)))
+            .fold(None, |best: Option<&Entry>, e| match best {
+                Some(b) if b.interface.priority > e.interface.priority => Some(b),
+                _ => Some(e),
+            })
+    }
+
+    pub fn current(&self) -> Option<&Rc<dyn Medium>> {
+        match self.top(None) {
+            Some(entry) => entry.interface.inside.as_ref(),
+            None => self.base.as_ref(),
+        }
+    }
+
+    //the index of refraction on the other side of the surface from the object it belongs to: whatever the path is in
+    //(going in) or will be in once it leaves (coming out). Air unless there is some other dielectric around
+    pub fn outer_ior(&self, rec: &HitRecord) -> f64 {
+        self.top(rec.interface.as_ref()).map_or(1.0, |e| e.ior)
+    }
+
+    //the surface of an object with a lower priority than the one the path is in isnt really there, the ray goes
+    //straight through it (it still has to be crossed to keep track of what we are inside of)
+    pub fn is_false_hit(&self, rec: &HitRecord) -> bool {
+        let Some(interface) = &rec.interface else {
+            return false;
+        };
+        self.top(Some(interface))
+            .is_some_and(|e| e.interface.priority > interface.priority)
+    }
+
+    //the ray went through the surface, front_face says which way
+    pub fn cross(&mut self, rec: &HitRecord) {
+        let Some(interface) = &rec.interface else {
+            return;
+        };
+
+        if rec.front_face {
+            self.entries.push(Entry {
+                interface: interface.clone(),
+                ior: rec.mat.ior(),
+            });
+            return;
+        }
+
+        if let Some(i) = self
+            .entries
+            .iter()
+            .rposition(|e| Rc::ptr_eq(&e.interface, interface))
+        {
+            self.entries.remove(i);
+        }
+        if self.entries.is_empty() && interface.outside.is_some() {
+            self.base = interface.outside.clone();
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::rc::Rc;
+
+use crate::{
+    aabb::AABB,
+    bvh::{BvhOptions, LinearBvh},
+    color::Color,
+    hittable::*,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    triangle::{intersect_triangle, shade_triangle},
+    vec3::{Point3, Vec3},
+};
+
+//a whole mesh in one place: every vertex is stored once and triangles are 3 indices into the vertex buffers
+//normals, uvs, tangents and colors are optional, either empty or one per position (same index as the position)
+//tangent_signs go with the tangents, the handedness of the bitangent (-1 where the uvs are mirrored), empty is all 1
+//one material for the whole mesh, a model with several materials is several meshes
+pub struct TriangleMesh {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<[f64; 2]>,
+    pub tangents: Vec<Vec3>,
+    pub tangent_signs: Vec<f64>,
+    pub colors: Vec<Color>,
+    //3 per triangle
+    pub indices: Vec<u32>,
+    pub mat: Rc<dyn Material>,
+}
+
+impl TriangleMesh {
+    pub fn new(positions: Vec<Point3>, indices: Vec<u32>, mat: Rc<dyn Material>) -> TriangleMesh {
+        TriangleMesh {
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            tangents: Vec::new(),
+            tangent_signs: Vec::new(),
+            colors: Vec::new(),
+            indices,
+            mat,
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn vertex_indices(&self, triangle: usize) -> [usize; 3] {
+        let i = 3 * triangle;
+        [
+            self.indices[i] as usize,
+            self.indices[i + 1] as usize,
+            self.indices[i + 2] as usize,
+        ]
+    }
+
+    //one small MeshTriangle per face in a bvh, they all point back to this mesh. The bvh is the hittable you add to the world,
+    //wrap it in RotateY/Translate to move the whole mesh at once
+    pub fn into_bvh(self) -> LinearBvh<MeshTriangle> {
+        let mesh = Rc::new(self);
+        let triangles: Vec<MeshTriangle> = (0..mesh.triangle_count())
+            .map(|i| MeshTriangle {
+                mesh: mesh.clone(),
+                index: i as u32,
+            })
+            .collect();
+
+        LinearBvh::new_with(triangles, BvhOptions::default())
+    }
+}
+
+//a triangle of a TriangleMesh, just the mesh and which face it is (no copies of the vertices, no Rc per material)
+pub struct MeshTriangle {
+    mesh: Rc<TriangleMesh>,
+    index: u32,
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let mesh = &self.mesh;
+        let [i0, i1, i2] = mesh.vertex_indices(self.index as usize);
+        let (a, b, c) = (mesh.positions[i0], mesh.positions[i1], mesh.positions[i2]);
+
+        let e1 = b - a;
+        let e2 = c - a;
+
+        let Some((t, u, v)) = intersect_triangle(r, a, e1, e2) else {
+            return false;
+        };
+        if t <= f64::EPSILON || !ray_t.contains(t) {
+            return false;
+        }
+
+        //u and v are the weights of b and c, the rest goes to a
+        let w = 1.0 - u - v;
+
+        rec.t = t;
+        rec.p = r.origin() + r.direction() * t;
+        rec.mat = mesh.mat.clone();
+        rec.set_face_normal(r, Vec3::unit_vector(Vec3::cross(e1, e2)));
+
+        let pick =
+            |buffer: &[Vec3]| (!buffer.is_empty()).then(|| [buffer[i0], buffer[i1], buffer[i2]]);
+        let uvs = (!mesh.uvs.is_empty()).then(|| [mesh.uvs[i0], mesh.uvs[i1], mesh.uvs[i2]]);
+        let sign = |i: usize| mesh.tangent_signs.get(i).copied().unwrap_or(1.0);
+        let tangents = pick(&mesh.tangents)
+            .map(|[t0, t1, t2]| [(t0, sign(i0)), (t1, sign(i1)), (t2, sign(i2))]);
+        shade_triangle(rec, [w, u, v], e1, e2, pick(&mesh.normals), uvs, tangents);
+
+        if let Some([c0, c1, c2]) = pick(&mesh.colors) {
+            rec.vertex_color = Some(w * c0 + u * c1 + v * c2);
+        }
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let mesh = &self.mesh;
+        let [i0, i1, i2] = mesh.vertex_indices(self.index as usize);
+        let (a, b, c) = (mesh.positions[i0], mesh.positions[i1], mesh.positions[i2]);
+
+        AABB::new(
+            Interval::new(a[0].min(b[0]).min(c[0]), a[0].max(b[0]).max(c[0])),
+            Interval::new(a[1].min(b[1]).min(c[1]), a[1].max(b[1]).max(c[1])),
+            Interval::new(a[2].min(b[2]).min(c[2]), a[2].max(b[2]).max(c[2])),
+        )
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use image::ImageReader;
+
+use crate::color::{Color, luminance};
+use crate::hittable_list::HittableList;
+use crate::material::{BumpMapped, Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::mesh::TriangleMesh;
+use crate::texture::{ImageTexture, Texture};
+use crate::vec3::{Point3, Vec3};
+
+//scale, then rotate around y (degrees), then move. Baked into the vertices when loading so the meshes need no wrappers
+#[derive(Copy, Clone, Debug)]
+pub struct ImportTransform {
+    pub scale: Vec3,
+    pub rotate_y: f64,
+    pub offset: Vec3,
+}
+
+impl Default for ImportTransform {
+    fn default() -> ImportTransform {
+        ImportTransform {
+            scale: Vec3::new(1.0, 1.0, 1.0),
+            rotate_y: 0.0,
+            offset: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl ImportTransform {
+    fn rotate(&self, v: Vec3) -> Vec3 {
+        let (sin, cos) = self.rotate_y.to_radians().sin_cos();
+        Vec3::new(cos * v.x() + sin * v.z(), v.y(), -sin * v.x() + cos * v.z())
+    }
+
+    fn point(&self, p: Point3) -> Point3 {
+        self.rotate(p * self.scale) + self.offset
+    }
+
+    //normals go through the inverse transpose, for scale that is dividing instead of multiplying
+    fn normal(&self, n: Vec3) -> Vec3 {
+        let s = self.scale;
+        self.rotate(Vec3::new(n.x() / s.x(), n.y() / s.y(), n.z() / s.z()))
+    }
+
+    fn vector(&self, v: Vec3) -> Vec3 {
+        self.rotate(v * self.scale)
+    }
+}
+
+//by file name and whether it was decoded as srgb, a file can be both a color map and a bump map
+type TextureCache = HashMap<(String, bool), Option<Rc<dyn Texture>>>;
+
+//loads an OBJ and its MTL into one LinearBvh per object/group (each of those has one material in the file)
+//the list that comes back is meant to be put in a bvh or added to the world like any other list
+//missing mtl files or textures are not an error, those parts just get a plain grey material and a warning
+pub fn load_obj(path: &str, transform: Option<ImportTransform>) -> std::io::Result<HittableList> {
+    let transform = transform.unwrap_or_default();
+
+    //the offline preset keeps quads and ngons as they are, the mesh only takes triangles
+    //single_index makes normals and texcoords use the same indices as the positions
+    let (models, materials) = tobj::load_obj(
+        path,
+        &tobj::LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..tobj::OFFLINE_RENDERING_LOAD_OPTIONS
+        },
+    )
+    .map_err(|e| Error::new(ErrorKind::InvalidData, format!("obj {}: {}", path, e)))?;
+
+    let materials = materials.unwrap_or_else(|e| {
+        eprintln!("obj {}: couldnt load materials ({}), using grey", path, e);
+        Vec::new()
+    });
+
+    //textures are relative to the file, and the same one is often used by several materials
+    let dir = Path::new(path)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+    let mut textures: TextureCache = HashMap::new();
+    let converted: Vec<Rc<dyn Material>> = materials
+        .iter()
+        .map(|m| convert_material(m, &dir, &mut textures, &transform))
+        .collect();
+    let default_material: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+
+    let mut list = HittableList::new();
+    for model in models {
+        let mesh = model.mesh;
+        if mesh.indices.is_empty() {
+            continue;
+        }
+
+        let mat = mesh
+            .material_id
+            .and_then(|id| converted.get(id).cloned())
+            .unwrap_or_else(|| default_material.clone());
+
+        let positions = mesh
+            .positions
+            .chunks(3)
+            .map(|p| transform.point(Point3::new(p[0] as f64, p[1] as f64, p[2] as f64)))
+            .collect();
+        let mut tri_mesh = TriangleMesh::new(positions, mesh.indices, mat);
+
+        tri_mesh.normals = mesh
+            .normals
+            .chunks(3)
+            .map(|n| {
+                Vec3::unit_vector(transform.normal(Vec3::new(
+                    n[0] as f64,
+                    n[1] as f64,
+                    n[2] as f64,
+                )))
+            })
+            .collect();
+        tri_mesh.uvs = mesh
+            .texcoords
+            .chunks(2)
+            .map(|t| [t[0] as f64, t[1] as f64])
+            .collect();
+
+        list.add(Rc::new(tri_mesh.into_bvh()));
+    }
+
+    Ok(list)
+}
+
+//the MTL model is phong-ish, we pick whichever of our materials is closest:
+//  Ke (emission) -> DiffuseLight
+//  d < 1 or a glass illum mode -> Dielectric with Ni as the index
+//  mirror illum mode or Ks brighter than Kd -> Metal, Ns (phong exponent) turned into fuzz
+//  everything else -> Lambertian with Kd or map_Kd
+//map_Bump goes on top of whatever was picked
+fn convert_material(
+    m: &tobj::Material,
+    dir: &Path,
+    textures: &mut TextureCache,
+    transform: &ImportTransform,
+) -> Rc<dyn Material> {
+    let color = |c: Option<[f32; 3]>| c.map(|c| Color::new(c[0] as f64, c[1] as f64, c[2] as f64));
+    let kd = color(m.diffuse).unwrap_or(Color::new(0.73, 0.73, 0.73));
+    let ks = color(m.specular).unwrap_or(Color::new(0.0, 0.0, 0.0));
+    let ke = m
+        .unknown_param
+        .get("Ke")
+        .and_then(|s| parse_color(s))
+        .unwrap_or(Color::new(0.0, 0.0, 0.0));
+    let illum = m.illumination_model.unwrap_or(2);
+    let dissolve = m.dissolve.unwrap_or(1.0) as f64;
+
+    let base: Rc<dyn Material> = if !ke.near_zero() {
+        Rc::new(DiffuseLight::new(ke))
+    } else if dissolve < 1.0 || matches!(illum, 4 | 6 | 7 | 9) {
+        Rc::new(Dielectric::new(
+            m.optical_density.unwrap_or(1.5) as f64,
+            0.0,
+        ))
+    } else if illum == 3 || luminance(ks) > luminance(kd) {
+        //the usual phong exponent to roughness conversion, a mirror has a huge Ns
+        let ns = (m.shininess.unwrap_or(1000.0) as f64).max(0.0);
+        Rc::new(Metal::new(ks, (2.0 / (ns + 2.0)).sqrt().min(1.0)))
+    } else {
+        match m
+            .diffuse_texture
+            .as_ref()
+            .and_then(|t| load_texture(t, true, dir, textures))
+        {
+            Some(tex) => Rc::new(Lambertian::new_tex(tex)),
+            None => Rc::new(Lambertian::new(kd)),
+        }
+    };
+
+    let Some(bump) = &m.normal_texture else {
+        return base;
+    };
+    //heights are data, not colors, so no srgb decode
+    let Some(tex) = load_texture(bump, false, dir, textures) else {
+        return base;
+    };
+
+    //-bm is the height multiplier, it scales with the model like everything else
+    let bm = texture_option(bump, "-bm").unwrap_or(1.0);
+    let model_scale =
+        (transform.vector(Vec3::new(1.0, 1.0, 1.0)).length() / 3f64.sqrt()).max(1e-12);
+    Rc::new(BumpMapped::new(base, tex, bm * model_scale))
+}
+
+//texture statements can have options in front of the name (map_Bump -bm 0.5 bumps.png), the file is the last word
+fn load_texture(
+    statement: &str,
+    srgb: bool,
+    dir: &Path,
+    textures: &mut TextureCache,
+) -> Option<Rc<dyn Texture>> {
+    let name = statement.split_whitespace().last()?;
+    let key = (name.to_string(), srgb);
+    if let Some(tex) = textures.get(&key) {
+        return tex.clone();
+    }
+
+    let file: PathBuf = dir.join(name.replace('\\', "/"));
+    let image = ImageReader::open(&file)
+        .map_err(image::ImageError::from)
+        .and_then(|reader| reader.decode());
+    let tex: Option<Rc<dyn Texture>> = match image {
+        Ok(image) => {
+            let mut image = ImageTexture::from_image(image.to_rgb8(), srgb);
+            image.repeat = true;
+            Some(Rc::new(image))
+        }
+        Err(e) => {
+            eprintln!("obj: couldnt load texture {} ({})", file.display(), e);
+            None
+        }
+    };
+
+    textures.insert(key, tex.clone());
+    tex
+}
+
+fn texture_option(statement: &str, option: &str) -> Option<f64> {
+    let mut words = statement.split_whitespace();
+    words.position(|w| w == option)?;
+    words.next()?.parse().ok()
+}
+
+fn parse_color(s: &str) -> Option<Color> {
+    let v: Vec<f64> = s
+        .split_whitespace()
+        .map(|x| x.parse::<f64>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    match v.as_slice() {
+        [r, g, b, ..] => Some(Color::new(*r, *g, *b)),
+        [x] => Some(Color::new(*x, *x, *x)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::{Rgb, RgbImage};
+
+    //128 is about half way in the file, as a color that decodes to about a quarter but a height has to stay a half
+    #[test]
+    fn bump_maps_are_not_srgb() {
+        let dir = std::env::temp_dir().join(format!("obj_bump_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        RgbImage::from_pixel(2, 2, Rgb([128, 128, 128]))
+            .save(dir.join("gray.png"))
+            .unwrap();
+
+        let mut textures = TextureCache::new();
+        let p = Point3::new(0.0, 0.0, 0.0);
+        let color = load_texture("gray.png", true, &dir, &mut textures).unwrap();
+        let bump = load_texture("-bm 0.5 gray.png", false, &dir, &mut textures).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let half = 128.0 / 255.0;
+        assert!((bump.value(0.5, 0.5, p).x() - half).abs() < 1e-9);
+        assert!((color.value(0.5, 0.5, p).x() - half * half).abs() < 1e-9);
+
+        //asking again comes from the cache, still with the right decode
+        let again = load_texture("gray.png", false, &dir, &mut textures).unwrap();
+        assert!((again.value(0.5, 0.5, p).x() - half).abs() < 1e-9);
+        assert_eq!(textures.len(), 2);
+    }
+}
@@ -21,10 +21,35 @@ impl ONB {
         ONB { axis: [u, v, w] }
     }
 
+    //same as new but the x axis is pinned to the tangent direction instead of an arbitrary one
+    //materials that care about the direction along the surface (hair, brushed stuff) need this
+    //falls back to new if the tangent is missing or parallel to the normal
+    pub fn new_tangent(n: Vec3, tangent: Vec3) -> ONB {
+        let w = Vec3::unit_vector(n);
+        let t = tangent - Vec3::dot(tangent, w) * w;
+        if t.near_zero() {
+            return ONB::new(n);
+        }
+
+        let u = Vec3::unit_vector(t);
+        let v = Vec3::cross(w, u);
+
+        ONB { axis: [u, v, w] }
+    }
+
     pub fn transform(&self, v: Vec3) -> Vec3 {
         v.x() * self.axis[0] + v.y() * self.axis[1] + v.z() * self.axis[2]
     }
 
+    //the other way around, world space into local space. The axes are orthonormal so the inverse is just the dots
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            Vec3::dot(v, self.axis[0]),
+            Vec3::dot(v, self.axis[1]),
+            Vec3::dot(v, self.axis[2]),
+        )
+    }
+
     pub fn x(&self) -> Vec3 {
         self.axis[0]
     }
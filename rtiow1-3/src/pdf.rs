@@ -101,3 +101,39 @@ impl PDF for MixturePDF {
         }
     }
 }
+
+//cos^n lobe around an arbitrary axis instead of the normal, used for the retro reflective lobe that is centered on the incoming direction
+//note that it can generate directions below the surface, the RetroReflective eval is 0 for those (and its pdf only counts the
+//diffuse half there) so it stays unbiased (just wasted samples)
+pub struct PhongLobePDF {
+    uvw: ONB,
+    exponent: f64,
+}
+
+impl PhongLobePDF {
+    pub fn new(axis: Vec3, exponent: f64) -> PhongLobePDF {
+        PhongLobePDF {
+            uvw: ONB::new(axis),
+            exponent,
+        }
+    }
+
+    pub fn sample(&self, u: [f64; 2]) -> Vec3 {
+        self.uvw.transform(Vec3::phong_dir(self.exponent, u))
+    }
+}
+
+impl PDF for PhongLobePDF {
+    fn value(&self, dir: Vec3) -> f64 {
+        let cos = Vec3::dot(Vec3::unit_vector(dir), self.uvw.w());
+        if cos <= 0.0 {
+            return 0.0;
+        }
+
+        (self.exponent + 1.0) / (2.0 * PI) * cos.powf(self.exponent)
+    }
+
+    fn generate(&self) -> Vec3 {
+        self.uvw.transform(Vec3::random_phong_dir(self.exponent))
+    }
+}
@@ -0,0 +1,191 @@
+use std::f64::consts::PI;
+
+use crate::onb::ONB;
+use crate::utils::random_double;
+use crate::vec3::Vec3;
+
+//the medium version of a bsdf: how much of the light at a scattering point goes from one direction to another
+//it only depends on the angle between where the light was going and where it goes now, so the cosine used
+//everywhere in here is dot(-wo, wi): 1 is straight through (forward scattering), -1 is right back (back scattering)
+//wo and wi both point away from the point like they do for materials, and every phase function integrates to 1
+//https://pbr-book.org/4ed/Volume_Scattering/Phase_Functions
+pub trait PhaseFunction {
+    fn p(&self, wo: Vec3, wi: Vec3) -> f64;
+
+    //uc picks the lobe for the ones that have several, u is for the direction. Gives back wi and its pdf
+    fn sample_p(&self, wo: Vec3, uc: f64, u: [f64; 2]) -> Option<(Vec3, f64)>;
+
+    //all of these are sampled exactly, so the pdf is the phase function itself
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        self.p(wo, wi)
+    }
+}
+
+fn cos_scatter(wo: Vec3, wi: Vec3) -> f64 {
+    Vec3::dot(-Vec3::unit_vector(wo), Vec3::unit_vector(wi)).clamp(-1.0, 1.0)
+}
+
+//the direction at cos_theta from the way the light was going (-wo), at angle phi around it
+fn direction_around(wo: Vec3, cos_theta: f64, phi: f64) -> Vec3 {
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    ONB::new(-wo).transform(Vec3::new(
+        sin_theta * phi.cos(),
+        sin_theta * phi.sin(),
+        cos_theta,
+    ))
+}
+
+//same in every direction, what Isotropic does
+pub struct IsotropicPhase;
+
+impl PhaseFunction for IsotropicPhase {
+    fn p(&self, _wo: Vec3, _wi: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn sample_p(&self, wo: Vec3, _uc: f64, u: [f64; 2]) -> Option<(Vec3, f64)> {
+        let wi = direction_around(wo, 1.0 - 2.0 * u[0], 2.0 * PI * u[1]);
+        Some((wi, 1.0 / (4.0 * PI)))
+    }
+}
+
+fn henyey_greenstein(cos_theta: f64, g: f64) -> f64 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.max(1e-12).sqrt())
+}
+
+//inverting the cdf of henyey greenstein gives the cosine in closed form
+fn sample_henyey_greenstein(g: f64, u: f64) -> f64 {
+    if g.abs() < 1e-3 {
+        return 1.0 - 2.0 * u;
+    }
+
+    let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+    ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+}
+
+//one parameter for how lopsided the scattering is: g is the average cosine, 0 is isotropic, towards 1 more and more of it
+//keeps going forward (fog and clouds are around 0.8), towards -1 it bounces back
+pub struct HenyeyGreenstein {
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein {
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+}
+
+impl PhaseFunction for HenyeyGreenstein {
+    fn p(&self, wo: Vec3, wi: Vec3) -> f64 {
+        henyey_greenstein(cos_scatter(wo, wi), self.g)
+    }
+
+    fn sample_p(&self, wo: Vec3, _uc: f64, u: [f64; 2]) -> Option<(Vec3, f64)> {
+        let cos_theta = sample_henyey_greenstein(self.g, u[0]);
+        let wi = direction_around(wo, cos_theta, 2.0 * PI * u[1]);
+        Some((wi, henyey_greenstein(cos_theta, self.g)))
+    }
+}
+
+//a blend of two henyey greensteins, weight of the first and 1 - weight of the second. A strong forward lobe with a
+//weaker backward one is what real clouds look like (the silver lining and the glow when the sun is behind you)
+pub struct DoubleHenyeyGreenstein {
+    g1: f64,
+    g2: f64,
+    weight: f64,
+}
+
+impl DoubleHenyeyGreenstein {
+    pub fn new(g1: f64, g2: f64, weight: f64) -> DoubleHenyeyGreenstein {
+        DoubleHenyeyGreenstein {
+            g1: g1.clamp(-0.99, 0.99),
+            g2: g2.clamp(-0.99, 0.99),
+            weight: weight.clamp(0.0, 1.0),
+        }
+    }
+
+    fn value(&self, cos_theta: f64) -> f64 {
+        self.weight * henyey_greenstein(cos_theta, self.g1)
+            + (1.0 - self.weight) * henyey_greenstein(cos_theta, self.g2)
+    }
+}
+
+impl PhaseFunction for DoubleHenyeyGreenstein {
+    fn p(&self, wo: Vec3, wi: Vec3) -> f64 {
+        self.value(cos_scatter(wo, wi))
+    }
+
+    //the lobe is picked by its weight, the pdf is still the whole blend since either lobe could have made wi
+    fn sample_p(&self, wo: Vec3, uc: f64, u: [f64; 2]) -> Option<(Vec3, f64)> {
+        let g = if uc < self.weight { self.g1 } else { self.g2 };
+        let cos_theta = sample_henyey_greenstein(g, u[0]);
+        let wi = direction_around(wo, cos_theta, 2.0 * PI * u[1]);
+        Some((wi, self.value(cos_theta)))
+    }
+}
+
+//scattering off things much smaller than the wavelength (air molecules), a little more forward and back than sideways
+//3 / (16 pi) (1 + cos^2)
+pub struct Rayleigh;
+
+impl PhaseFunction for Rayleigh {
+    fn p(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let cos_theta = cos_scatter(wo, wi);
+        3.0 / (16.0 * PI) * (1.0 + cos_theta * cos_theta)
+    }
+
+    //the cdf of the cosine is (cos^3 + 3 cos + 4) / 8, setting it to u is a cubic with a single real root (cardano)
+    fn sample_p(&self, wo: Vec3, _uc: f64, u: [f64; 2]) -> Option<(Vec3, f64)> {
+        let q = 2.0 - 4.0 * u[0];
+        let s = (q * q + 1.0).sqrt();
+        let cos_theta = ((s - q).cbrt() - (s + q).cbrt()).clamp(-1.0, 1.0);
+
+        let wi = direction_around(wo, cos_theta, 2.0 * PI * u[1]);
+        Some((wi, 3.0 / (16.0 * PI) * (1.0 + cos_theta * cos_theta)))
+    }
+}
+
+//the cornette shanks approximation of mie scattering (haze, droplets, things about the size of the wavelength)
+//henyey greenstein with the rayleigh (1 + cos^2) shape mixed in, which gets the strong forward peak and the
+//little bump backwards that plain henyey greenstein misses
+pub struct Mie {
+    g: f64,
+}
+
+impl Mie {
+    pub fn new(g: f64) -> Mie {
+        Mie {
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    fn value(&self, cos_theta: f64) -> f64 {
+        let g2 = self.g * self.g;
+        1.5 / (2.0 + g2) * (1.0 + cos_theta * cos_theta) * henyey_greenstein(cos_theta, self.g)
+    }
+}
+
+impl PhaseFunction for Mie {
+    fn p(&self, wo: Vec3, wi: Vec3) -> f64 {
+        self.value(cos_scatter(wo, wi))
+    }
+
+    //it is henyey greenstein times (1 + cos^2) / 2 up to a constant, so henyey greenstein samples are kept with that
+    //chance. Retries need fresh numbers, only the first try uses u
+    fn sample_p(&self, wo: Vec3, _uc: f64, u: [f64; 2]) -> Option<(Vec3, f64)> {
+        let mut u0 = u[0];
+        let cos_theta = loop {
+            let cos_theta = sample_henyey_greenstein(self.g, u0);
+            if random_double() * 2.0 <= 1.0 + cos_theta * cos_theta {
+                break cos_theta;
+            }
+            u0 = random_double();
+        };
+
+        let wi = direction_around(wo, cos_theta, 2.0 * PI * u[1]);
+        Some((wi, self.value(cos_theta)))
+    }
+}
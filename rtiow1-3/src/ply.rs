@@ -0,0 +1,493 @@
+use std::io::{Error, ErrorKind};
+use std::rc::Rc;
+
+use crate::color::Color;
+use crate::material::Material;
+use crate::mesh::TriangleMesh;
+use crate::vec3::{Point3, Vec3};
+
+//PLY is a header that lists elements (vertex, face, sometimes edges or whatever the scanner wanted) and their properties,
+//then the data as text or as packed little/big endian binary. We take positions, normals, uvs and colors from the vertices
+//and the vertex index lists from the faces, polygons are split into a fan of triangles. Everything else is read past
+//http://paulbourke.net/dataformats/ply/
+#[derive(Copy, Clone, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Copy, Clone)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    //8 bit colors are 0..255, float ones are already 0..1
+    fn max_color(self) -> f64 {
+        match self {
+            Scalar::U8 | Scalar::I8 => 255.0,
+            Scalar::U16 | Scalar::I16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+//where the data part is and how to read numbers from it, every value comes out as f64 (exact for anything up to 32 bit ints)
+struct Data<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    format: Format,
+}
+
+impl Data<'_> {
+    fn error(&self, what: &str) -> Error {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("ply: {} at byte {}", what, self.pos),
+        )
+    }
+
+    //every element takes at least a byte, so a header claiming more of them than the file can hold (corrupt or cut off)
+    //only gets room for what is actually there, reading the rest fails with file ends early
+    fn capacity_for(&self, count: usize) -> usize {
+        count.min(self.bytes.len() - self.pos)
+    }
+
+    fn read(&mut self, ty: Scalar) -> std::io::Result<f64> {
+        if self.format == Format::Ascii {
+            return self.read_ascii();
+        }
+
+        let size = ty.size();
+        let Some(raw) = self.bytes.get(self.pos..self.pos + size) else {
+            return Err(self.error("file ends early"));
+        };
+        self.pos += size;
+
+        let mut b = [0u8; 8];
+        b[..size].copy_from_slice(raw);
+        if self.format == Format::BinaryBigEndian {
+            b[..size].reverse();
+        }
+
+        Ok(match ty {
+            Scalar::I8 => b[0] as i8 as f64,
+            Scalar::U8 => b[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(b),
+        })
+    }
+
+    //ascii values are whitespace separated, lines dont matter once we know the layout from the header
+    fn read_ascii(&mut self) -> std::io::Result<f64> {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.bytes.len() && !self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(self.error("file ends early"));
+        }
+
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| self.error("not a number"))
+    }
+}
+
+//loads a PLY file as one TriangleMesh, call into_bvh on it to get something for the world
+//the mesh only has the buffers the file has (no normals in the file means flat shading, and so on)
+pub fn load_ply(path: &str, mat: Rc<dyn Material>) -> std::io::Result<TriangleMesh> {
+    let bytes = std::fs::read(path)?;
+    parse_ply(&bytes, mat).map_err(|e| Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+fn parse_ply(bytes: &[u8], mat: Rc<dyn Material>) -> std::io::Result<TriangleMesh> {
+    let invalid = |what: &str| Error::new(ErrorKind::InvalidData, format!("ply: {}", what));
+
+    //the header is always text, the data starts right after the end_header line
+    let end = b"end_header";
+    let Some(header_end) = bytes.windows(end.len()).position(|w| w == end) else {
+        return Err(invalid("no end_header"));
+    };
+    let mut data_start = header_end + end.len();
+    if bytes.get(data_start) == Some(&b'\r') {
+        data_start += 1;
+    }
+    if bytes.get(data_start) == Some(&b'\n') {
+        data_start += 1;
+    }
+
+    let header = String::from_utf8_lossy(&bytes[..header_end]);
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err(invalid("not a ply file"));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", "ascii", ..] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", ..] => format = Some(Format::BinaryLittleEndian),
+            ["format", "binary_big_endian", ..] => format = Some(Format::BinaryBigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid("bad element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_ty, item_ty, name] => {
+                let (Some(count_ty), Some(item_ty)) =
+                    (Scalar::parse(count_ty), Scalar::parse(item_ty))
+                else {
+                    return Err(invalid(&format!("unknown list type in '{}'", line)));
+                };
+                elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("property before any element"))?
+                    .properties
+                    .push(Property::List(name.to_string(), count_ty, item_ty));
+            }
+            ["property", ty, name] => {
+                let ty = Scalar::parse(ty)
+                    .ok_or_else(|| invalid(&format!("unknown type in '{}'", line)))?;
+                elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("property before any element"))?
+                    .properties
+                    .push(Property::Scalar(name.to_string(), ty));
+            }
+            //comment, obj_info and blank lines
+            _ => {}
+        }
+    }
+
+    let Some(format) = format else {
+        return Err(invalid("no format line"));
+    };
+
+    let mut data = Data {
+        bytes: &bytes[data_start..],
+        pos: 0,
+        format,
+    };
+
+    let mut positions: Vec<Point3> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<[f64; 2]> = Vec::new();
+    let mut colors: Vec<Color> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(
+                element,
+                &mut data,
+                &mut positions,
+                &mut normals,
+                &mut uvs,
+                &mut colors,
+            )?,
+            "face" => read_faces(element, &mut data, &mut indices)?,
+            _ => skip(element, &mut data)?,
+        }
+    }
+
+    if let Some(&bad) = indices.iter().find(|&&i| i as usize >= positions.len()) {
+        return Err(invalid(&format!(
+            "face uses vertex {} but there are only {}",
+            bad,
+            positions.len()
+        )));
+    }
+
+    let mut mesh = TriangleMesh::new(positions, indices, mat);
+    mesh.normals = normals;
+    mesh.uvs = uvs;
+    mesh.colors = colors;
+    Ok(mesh)
+}
+
+//which slot a vertex property goes into, the names differ between exporters
+fn vertex_slot(name: &str) -> Option<usize> {
+    Some(match name {
+        "x" => 0,
+        "y" => 1,
+        "z" => 2,
+        "nx" => 3,
+        "ny" => 4,
+        "nz" => 5,
+        "u" | "s" | "texture_u" | "texture_s" => 6,
+        "v" | "t" | "texture_v" | "texture_t" => 7,
+        "red" | "r" | "diffuse_red" => 8,
+        "green" | "g" | "diffuse_green" => 9,
+        "blue" | "b" | "diffuse_blue" => 10,
+        _ => return None,
+    })
+}
+
+fn read_vertices(
+    element: &Element,
+    data: &mut Data,
+    positions: &mut Vec<Point3>,
+    normals: &mut Vec<Vec3>,
+    uvs: &mut Vec<[f64; 2]>,
+    colors: &mut Vec<Color>,
+) -> std::io::Result<()> {
+    let mut has = [false; 11];
+    let mut color_max = 1.0;
+    for property in &element.properties {
+        if let Property::Scalar(name, ty) = property
+            && let Some(slot) = vertex_slot(name)
+        {
+            has[slot] = true;
+            if slot >= 8 {
+                color_max = ty.max_color();
+            }
+        }
+    }
+    let has_normals = has[3] && has[4] && has[5];
+    let has_uvs = has[6] && has[7];
+    let has_colors = has[8] && has[9] && has[10];
+
+    positions.reserve(data.capacity_for(element.count));
+    let mut v = [0.0; 11];
+    for _ in 0..element.count {
+        for property in &element.properties {
+            match property {
+                Property::Scalar(name, ty) => {
+                    let value = data.read(*ty)?;
+                    if let Some(slot) = vertex_slot(name) {
+                        v[slot] = value;
+                    }
+                }
+                list => skip_property(list, data)?,
+            }
+        }
+
+        positions.push(Point3::new(v[0], v[1], v[2]));
+        if has_normals {
+            normals.push(Vec3::new(v[3], v[4], v[5]));
+        }
+        if has_uvs {
+            uvs.push([v[6], v[7]]);
+        }
+        //scanner colors are srgb, squared the same way ImageTexture does it
+        if has_colors {
+            let c = Color::new(v[8], v[9], v[10]) / color_max;
+            colors.push(c * c);
+        }
+    }
+
+    Ok(())
+}
+
+//polygons become a fan around their first vertex, for quads that is the usual two triangles
+fn read_faces(element: &Element, data: &mut Data, indices: &mut Vec<u32>) -> std::io::Result<()> {
+    let capacity = data
+        .capacity_for(element.count)
+        .checked_mul(3)
+        .ok_or_else(|| data.error("too many faces"))?;
+    indices.reserve(capacity);
+    let mut polygon: Vec<u32> = Vec::new();
+
+    for _ in 0..element.count {
+        for property in &element.properties {
+            match property {
+                Property::List(name, count_ty, item_ty)
+                    if name == "vertex_indices" || name == "vertex_index" =>
+                {
+                    let count = data.read(*count_ty)? as usize;
+                    polygon.clear();
+                    for _ in 0..count {
+                        polygon.push(data.read(*item_ty)? as u32);
+                    }
+
+                    for i in 1..count.saturating_sub(1) {
+                        indices.extend([polygon[0], polygon[i], polygon[i + 1]]);
+                    }
+                }
+                other => skip_property(other, data)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn skip(element: &Element, data: &mut Data) -> std::io::Result<()> {
+    for _ in 0..element.count {
+        for property in &element.properties {
+            skip_property(property, data)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn skip_property(property: &Property, data: &mut Data) -> std::io::Result<()> {
+    match property {
+        Property::Scalar(_, ty) => {
+            data.read(*ty)?;
+        }
+        Property::List(_, count_ty, item_ty) => {
+            let count = data.read(*count_ty)? as usize;
+            for _ in 0..count {
+                data.read(*item_ty)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    fn mat() -> Rc<dyn Material> {
+        Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    const CORNERS: [[f32; 3]; 4] = [[0., 0., 0.], [1., 0., 0.], [1., 1., 0.], [0., 1., 0.]];
+    const RGB: [[u8; 3]; 4] = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 255]];
+
+    fn header(format: &str, faces: usize) -> String {
+        format!(
+            "ply\nformat {} 1.0\ncomment test\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+             property uchar red\nproperty uchar green\nproperty uchar blue\nelement face {}\n\
+             property list uchar int vertex_indices\nend_header\n",
+            format, faces
+        )
+    }
+
+    //a unit square as one quad face, written with to_le_bytes or to_be_bytes
+    fn binary_quad(
+        format: &str,
+        f32_bytes: fn(f32) -> [u8; 4],
+        i32_bytes: fn(i32) -> [u8; 4],
+    ) -> Vec<u8> {
+        let mut bytes = header(format, 1).into_bytes();
+        for (p, c) in CORNERS.iter().zip(RGB) {
+            for x in p {
+                bytes.extend(f32_bytes(*x));
+            }
+            bytes.extend(c);
+        }
+        bytes.push(4);
+        for i in [0, 1, 2, 3] {
+            bytes.extend(i32_bytes(i));
+        }
+        bytes
+    }
+
+    fn check_quad(mesh: &TriangleMesh) {
+        assert_eq!(mesh.positions.len(), 4);
+        for (p, c) in mesh.positions.iter().zip(CORNERS) {
+            assert_eq!([p.x(), p.y(), p.z()], c.map(|x| x as f64));
+        }
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.colors.len(), 4);
+        for (color, c) in mesh.colors.iter().zip(RGB) {
+            let expected = c.map(|x| (x as f64 / 255.0).powi(2));
+            assert_eq!([color.x(), color.y(), color.z()], expected);
+        }
+        assert!(mesh.normals.is_empty() && mesh.uvs.is_empty());
+    }
+
+    #[test]
+    fn ascii_quad() {
+        let text = header("ascii", 1)
+            + "0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n4 0 1 2 3\n";
+        check_quad(&parse_ply(text.as_bytes(), mat()).unwrap());
+    }
+
+    #[test]
+    fn binary_little_endian_quad() {
+        let bytes = binary_quad("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes);
+        check_quad(&parse_ply(&bytes, mat()).unwrap());
+    }
+
+    #[test]
+    fn binary_big_endian_quad() {
+        let bytes = binary_quad("binary_big_endian", f32::to_be_bytes, i32::to_be_bytes);
+        check_quad(&parse_ply(&bytes, mat()).unwrap());
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let text = header("ascii", 1)
+            + "0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n3 0 1 7\n";
+        let err = parse_ply(text.as_bytes(), mat()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn huge_counts_in_header() {
+        //the vertices are all there, the face count is what is broken
+        let quad = binary_quad("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes);
+        let data = &quad[header("binary_little_endian", 1).len()..];
+        let mut bytes = header("binary_little_endian", usize::MAX).into_bytes();
+        bytes.extend(data);
+        let err = parse_ply(&bytes, mat()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let text = header("ascii", 1).replace(
+            "element vertex 4",
+            &format!("element vertex {}", usize::MAX),
+        );
+        let err = parse_ply(text.as_bytes(), mat()).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
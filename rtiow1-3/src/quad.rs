@@ -1,15 +1,30 @@
-use std::{f64::INFINITY, rc::Rc};
+use std::{f64::INFINITY, f64::consts::PI, rc::Rc};
 
 use crate::{
     aabb::AABB,
     hittable::*,
     hittable_list::*,
     material::Material,
+    onb::ONB,
     utils::random_double,
     vec3::{Point3, Vec3},
 };
 use crate::{interval::*, ray::*};
 
+//what part of the plane counts, in the (alpha, beta) coordinates of the hit point along u and v
+//Parallelogram is the usual quad, the others reuse the same plane math with a different interior test:
+//  Triangle: q, q + u, q + v
+//  Ellipse: centered on q with u and v as the two half axes (a disk when they are perpendicular and the same length)
+//  Annulus: an ellipse with a hole, inner is the hole size as a fraction of the outer one
+//  Polygon: convex, its corners in (alpha, beta) and going counterclockwise
+pub enum PlanarShape {
+    Parallelogram,
+    Triangle,
+    Ellipse,
+    Annulus { inner: f64 },
+    Polygon { corners: Vec<[f64; 2]> },
+}
+
 //See notes for the derivation
 pub struct Quad {
     q: Point3,
@@ -21,21 +36,115 @@ pub struct Quad {
     normal: Vec3,
     d: f64,
     area: f64,
+    shape: PlanarShape,
 }
 
 impl Quad {
     pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Rc<dyn Material>) -> Quad {
-        let bbox_diagonal1 = AABB::new_point(q, q + u + v);
-        let bbox_diagonal2 = AABB::new_point(q + u, q + v);
+        Quad::new_shape(q, u, v, PlanarShape::Parallelogram, mat)
+    }
+
+    pub fn new_triangle(a: Point3, b: Point3, c: Point3, mat: Rc<dyn Material>) -> Quad {
+        Quad::new_shape(a, b - a, c - a, PlanarShape::Triangle, mat)
+    }
+
+    pub fn new_disk(center: Point3, normal: Vec3, radius: f64, mat: Rc<dyn Material>) -> Quad {
+        let frame = ONB::new(normal);
+        Quad::new_shape(
+            center,
+            radius * frame.x(),
+            radius * frame.y(),
+            PlanarShape::Ellipse,
+            mat,
+        )
+    }
 
-        let bbox = AABB::new_boxes(&bbox_diagonal1, &bbox_diagonal2);
+    pub fn new_ellipse(center: Point3, u: Vec3, v: Vec3, mat: Rc<dyn Material>) -> Quad {
+        Quad::new_shape(center, u, v, PlanarShape::Ellipse, mat)
+    }
+
+    pub fn new_annulus(
+        center: Point3,
+        u: Vec3,
+        v: Vec3,
+        inner: f64,
+        mat: Rc<dyn Material>,
+    ) -> Quad {
+        let inner = inner.clamp(0.0, 1.0);
+        Quad::new_shape(center, u, v, PlanarShape::Annulus { inner }, mat)
+    }
+
+    //corners in world space, they have to be in one plane and make a convex polygon. Either winding works,
+    //the normal follows the right hand rule like it does for new (u is the first edge, v goes to the last corner)
+    pub fn new_polygon(corners: &[Point3], mat: Rc<dyn Material>) -> Quad {
+        assert!(corners.len() >= 3, "a polygon needs at least 3 corners");
+        let q = corners[0];
+        let u = corners[1] - q;
+        let v = corners[corners.len() - 1] - q;
+
+        let n = Vec3::cross(u, v);
+        let w = n / Vec3::dot(n, n);
+        let corners = corners
+            .iter()
+            .map(|&p| {
+                let hit = p - q;
+                [
+                    Vec3::dot(w, Vec3::cross(hit, v)),
+                    Vec3::dot(w, Vec3::cross(u, hit)),
+                ]
+            })
+            .collect();
+
+        Quad::new_shape(q, u, v, PlanarShape::Polygon { corners }, mat)
+    }
 
+    pub fn new_shape(
+        q: Point3,
+        u: Vec3,
+        v: Vec3,
+        shape: PlanarShape,
+        mat: Rc<dyn Material>,
+    ) -> Quad {
         let n = Vec3::cross(u, v);
         let normal = Vec3::unit_vector(n);
         let d = Vec3::dot(normal, q);
-        let w = normal / Vec3::dot(normal, n);
-
-        let area = n.length();
+        let w = n / Vec3::dot(n, n);
+
+        //the area in (alpha, beta) times the area of the unit u, v parallelogram
+        let parallelogram_area = n.length();
+        let area = parallelogram_area
+            * match &shape {
+                PlanarShape::Parallelogram => 1.0,
+                PlanarShape::Triangle => 0.5,
+                PlanarShape::Ellipse => PI,
+                PlanarShape::Annulus { inner } => PI * (1.0 - inner * inner),
+                PlanarShape::Polygon { corners } => polygon_area(corners),
+            };
+
+        let bbox = match &shape {
+            //(alpha, beta) = (cos, sin) traced through u and v reaches sqrt(u^2 + v^2) on each axis
+            PlanarShape::Ellipse | PlanarShape::Annulus { .. } => {
+                let e = Vec3::new(
+                    (u.x() * u.x() + v.x() * v.x()).sqrt(),
+                    (u.y() * u.y() + v.y() * v.y()).sqrt(),
+                    (u.z() * u.z() + v.z() * v.z()).sqrt(),
+                );
+                AABB::new_point(q - e, q + e)
+            }
+            PlanarShape::Parallelogram => AABB::new_boxes(
+                &AABB::new_point(q, q + u + v),
+                &AABB::new_point(q + u, q + v),
+            ),
+            PlanarShape::Triangle => {
+                AABB::new_boxes(&AABB::new_point(q, q + u), &AABB::new_point(q, q + v))
+            }
+            PlanarShape::Polygon { corners } => {
+                corners.iter().fold(AABB::new_point(q, q), |bbox, c| {
+                    let p = q + c[0] * u + c[1] * v;
+                    AABB::new_boxes(&bbox, &AABB::new_point(p, p))
+                })
+            }
+        };
 
         Quad {
             q: q,
@@ -47,20 +156,105 @@ impl Quad {
             normal: normal,
             d: d,
             area: area,
+            shape,
         }
     }
 
-    pub fn is_interior(a: f64, b: f64, rec: &mut HitRecord) -> bool {
-        let unit_interval = Interval::new(0., 1.);
+    pub fn area(&self) -> f64 {
+        self.area
+    }
 
-        if !unit_interval.contains(a) || !unit_interval.contains(b) {
+    //the interior test for the shape, also sets the uvs. Parallelograms and triangles use alpha and beta as they are,
+    //ellipses squash them from -1..1 into 0..1 so a texture covers the square around them
+    pub fn is_interior(&self, a: f64, b: f64, rec: &mut HitRecord) -> bool {
+        let inside = match &self.shape {
+            PlanarShape::Parallelogram => {
+                let unit_interval = Interval::new(0., 1.);
+                unit_interval.contains(a) && unit_interval.contains(b)
+            }
+            PlanarShape::Triangle => a >= 0.0 && b >= 0.0 && a + b <= 1.0,
+            PlanarShape::Ellipse => a * a + b * b <= 1.0,
+            PlanarShape::Annulus { inner } => {
+                let r2 = a * a + b * b;
+                r2 <= 1.0 && r2 >= inner * inner
+            }
+            //left of every edge
+            PlanarShape::Polygon { corners } => (0..corners.len()).all(|i| {
+                let p0 = corners[i];
+                let p1 = corners[(i + 1) % corners.len()];
+                (p1[0] - p0[0]) * (b - p0[1]) - (p1[1] - p0[1]) * (a - p0[0]) >= 0.0
+            }),
+        };
+
+        if !inside {
             return false;
         }
 
-        rec.u = a;
-        rec.v = b;
+        match self.shape {
+            PlanarShape::Ellipse | PlanarShape::Annulus { .. } => {
+                rec.u = 0.5 * (a + 1.0);
+                rec.v = 0.5 * (b + 1.0);
+            }
+            _ => {
+                rec.u = a;
+                rec.v = b;
+            }
+        }
         true
     }
+
+    //a uniform point in (alpha, beta), u and v are a linear map so it stays uniform in world space too
+    fn random_alpha_beta(&self) -> (f64, f64) {
+        match &self.shape {
+            PlanarShape::Parallelogram => (random_double(), random_double()),
+            PlanarShape::Triangle => random_in_triangle([0.0, 0.0], [1.0, 0.0], [0.0, 1.0]),
+            PlanarShape::Ellipse => random_in_ring(0.0),
+            PlanarShape::Annulus { inner } => random_in_ring(*inner),
+            //pick a triangle of the fan by its area, then a point in it
+            PlanarShape::Polygon { corners } => {
+                let mut pick = random_double() * polygon_area(corners);
+                for i in 1..corners.len() - 1 {
+                    let tri = [corners[0], corners[i], corners[i + 1]];
+                    let tri_area = polygon_area(&tri);
+                    if pick <= tri_area || i == corners.len() - 2 {
+                        return random_in_triangle(tri[0], tri[1], tri[2]);
+                    }
+                    pick -= tri_area;
+                }
+                (0.0, 0.0)
+            }
+        }
+    }
+}
+
+//shoelace formula, the corners are counterclockwise so it comes out positive
+fn polygon_area(corners: &[[f64; 2]]) -> f64 {
+    let twice: f64 = (0..corners.len())
+        .map(|i| {
+            let p0 = corners[i];
+            let p1 = corners[(i + 1) % corners.len()];
+            p0[0] * p1[1] - p1[0] * p0[1]
+        })
+        .sum();
+    0.5 * twice.abs()
+}
+
+//sqrt of the first number keeps the points from bunching up at the a corner
+fn random_in_triangle(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> (f64, f64) {
+    let s = random_double().sqrt();
+    let t = random_double();
+    let (wa, wb, wc) = (1.0 - s, s * (1.0 - t), s * t);
+    (
+        wa * a[0] + wb * b[0] + wc * c[0],
+        wa * a[1] + wb * b[1] + wc * c[1],
+    )
+}
+
+//r^2 uniform between inner^2 and 1 spreads the points evenly over the ring
+fn random_in_ring(inner: f64) -> (f64, f64) {
+    let r = (inner * inner + random_double() * (1.0 - inner * inner)).sqrt();
+    let phi = 2.0 * PI * random_double();
+    (r * phi.cos(), r * phi.sin())
 }
 
 impl Hittable for Quad {
@@ -85,16 +279,15 @@ impl Hittable for Quad {
         let alpha = Vec3::dot(self.w, Vec3::cross(planar_hitpt_vector, self.v));
         let beta = Vec3::dot(self.w, Vec3::cross(self.u, planar_hitpt_vector));
 
-        if !Quad::is_interior(alpha, beta, rec) {
+        if !self.is_interior(alpha, beta, rec) {
             return false;
         }
 
         rec.t = t;
         rec.p = intersection;
-        rec.u = alpha;
-        rec.v = beta;
         rec.mat = self.mat.clone();
         rec.set_face_normal(r, self.normal);
+        rec.dpdu = Vec3::unit_vector(self.u);
 
         true
     }
@@ -121,7 +314,8 @@ impl Hittable for Quad {
     }
 
     fn random(&self, origin: Vec3) -> Vec3 {
-        let p = self.q + (random_double() * self.u) + (random_double() * self.v);
+        let (a, b) = self.random_alpha_beta();
+        let p = self.q + (a * self.u) + (b * self.v);
         p - origin
     }
 }
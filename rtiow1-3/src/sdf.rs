@@ -0,0 +1,256 @@
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use crate::aabb::AABB;
+use crate::hittable::*;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+//signed distance function: how far p is from the surface, negative inside. It only has to be a lower bound
+//(never more than the real distance) for sphere tracing to be exact, the ones that bend space (twist, smooth union)
+//break that a bit and need a smaller step_scale on the primitive
+//https://iquilezles.org/articles/distfunctions/
+pub type Sdf = Rc<dyn Fn(Point3) -> f64>;
+
+//a shape from a distance function, marched inside bbox. The function has to be below 0 everywhere inside the shape
+//and the shape has to fit in bbox, anything outside of it is never looked at
+pub struct SdfPrimitive {
+    distance: Sdf,
+    mat: Rc<dyn Material>,
+    bbox: AABB,
+    //closer than this counts as on the surface, also the finite difference size for normals
+    pub epsilon: f64,
+    //1 for real distances, lower for the combinators that overestimate (0.5 is plenty for most twists)
+    pub step_scale: f64,
+    pub max_steps: usize,
+}
+
+impl SdfPrimitive {
+    pub fn new(distance: Sdf, bbox: AABB, mat: Rc<dyn Material>) -> SdfPrimitive {
+        let diagonal = Vec3::new(bbox.x.size(), bbox.y.size(), bbox.z.size()).length();
+
+        SdfPrimitive {
+            distance,
+            mat,
+            bbox,
+            epsilon: 1e-5 * diagonal.max(1e-3),
+            step_scale: 1.0,
+            max_steps: 512,
+        }
+    }
+
+    //the gradient of the distance is the outward normal, 4 samples on a tetrahedron instead of 6 for central differences
+    fn normal(&self, p: Point3) -> Vec3 {
+        let h = self.epsilon;
+        let k = [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ];
+
+        let n = k.iter().fold(Vec3::new(0.0, 0.0, 0.0), |n, &k| {
+            n + k * (self.distance)(p + h * k)
+        });
+        if n.near_zero() {
+            return Vec3::new(0.0, 1.0, 0.0);
+        }
+        Vec3::unit_vector(n)
+    }
+
+    //where the ray is inside the box, None if it misses it
+    fn clip(&self, r: &Ray, ray_t: Interval) -> Option<(f64, f64)> {
+        let (mut t0, mut t1) = (ray_t.min, ray_t.max);
+        for axis in 0..3 {
+            let ax = self.bbox.axis_interval(axis);
+            let inv = 1.0 / r.direction()[axis];
+            let a = (ax.min - r.origin()[axis]) * inv;
+            let b = (ax.max - r.origin()[axis]) * inv;
+            t0 = t0.max(a.min(b));
+            t1 = t1.min(a.max(b));
+        }
+
+        if t0 <= t1 { Some((t0, t1)) } else { None }
+    }
+}
+
+impl Hittable for SdfPrimitive {
+    //sphere tracing: the distance says how far the ray can go without crossing the surface, so step that far and repeat
+    //until it gets tiny. Rays that start inside march on the negated distance to find the way out
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let dir_len = r.direction().length();
+        if dir_len == 0.0 {
+            return false;
+        }
+        let Some((mut t, t_max)) = self.clip(r, ray_t) else {
+            return false;
+        };
+
+        //a ray leaving from the surface (a bounce) would find it again right away, which side it is going to
+        //comes from the normal and it gets moved off the surface a bit
+        let d = (self.distance)(r.at(t));
+        let sign = if d.abs() < self.epsilon {
+            let going_in = Vec3::dot(self.normal(r.at(t)), r.direction()) < 0.0;
+            t += 4.0 * self.epsilon / dir_len;
+            if going_in { -1.0 } else { 1.0 }
+        } else if d < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        for _ in 0..self.max_steps {
+            if t > t_max {
+                return false;
+            }
+
+            let d = sign * (self.distance)(r.at(t));
+            if d < self.epsilon {
+                let p = r.at(t);
+                let outward_normal = self.normal(p);
+
+                rec.t = t;
+                rec.p = p;
+                rec.mat = self.mat.clone();
+                rec.set_face_normal(r, outward_normal);
+                //spherical around the middle of the box, there is no natural parameterization
+                let dir = Vec3::unit_vector(p - self.bbox.centroid());
+                rec.u = ((-dir.z()).atan2(dir.x()) + PI) / (2.0 * PI);
+                rec.v = (-dir.y()).clamp(-1.0, 1.0).acos() / PI;
+
+                return true;
+            }
+
+            t += self.step_scale * d / dir_len;
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+}
+
+pub fn sphere(center: Point3, radius: f64) -> Sdf {
+    Rc::new(move |p| (p - center).length() - radius)
+}
+
+//box with half size half, corners rounded off by rounding (which is taken out of half so the size stays the same)
+pub fn round_box(center: Point3, half: Vec3, rounding: f64) -> Sdf {
+    let inner = half - Vec3::new(rounding, rounding, rounding);
+    Rc::new(move |p| {
+        let q = p - center;
+        let q = Vec3::new(
+            q.x().abs() - inner.x(),
+            q.y().abs() - inner.y(),
+            q.z().abs() - inner.z(),
+        );
+        let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).length();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        outside + inside - rounding
+    })
+}
+
+//lying flat, the y axis goes through the hole
+pub fn torus(center: Point3, major_radius: f64, minor_radius: f64) -> Sdf {
+    Rc::new(move |p| {
+        let q = p - center;
+        let ring = (q.x() * q.x() + q.z() * q.z()).sqrt() - major_radius;
+        (ring * ring + q.y() * q.y()).sqrt() - minor_radius
+    })
+}
+
+//power 8 is the usual one, it fits in a sphere of radius 1.2 * scale. An estimate, use a step_scale below 1
+//https://en.wikipedia.org/wiki/Mandelbulb
+pub fn mandelbulb(center: Point3, scale: f64, power: f64, iterations: usize) -> Sdf {
+    Rc::new(move |p| {
+        let c = (p - center) / scale;
+        let mut z = c;
+        let mut dr = 1.0;
+        let mut r = z.length();
+
+        for _ in 0..iterations {
+            if r > 2.0 || r == 0.0 {
+                break;
+            }
+
+            let theta = (z.z() / r).acos() * power;
+            let phi = z.y().atan2(z.x()) * power;
+            dr = r.powf(power - 1.0) * power * dr + 1.0;
+            let zr = r.powf(power);
+            z =
+                zr * Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                ) + c;
+            r = z.length();
+        }
+
+        if r == 0.0 {
+            return 0.0;
+        }
+        0.5 * r.ln() * r / dr * scale
+    })
+}
+
+pub fn union(a: Sdf, b: Sdf) -> Sdf {
+    Rc::new(move |p| a(p).min(b(p)))
+}
+
+pub fn intersection(a: Sdf, b: Sdf) -> Sdf {
+    Rc::new(move |p| a(p).max(b(p)))
+}
+
+//a with b cut out
+pub fn difference(a: Sdf, b: Sdf) -> Sdf {
+    Rc::new(move |p| a(p).max(-b(p)))
+}
+
+//union that melts the two together where they are closer than k
+pub fn smooth_union(a: Sdf, b: Sdf, k: f64) -> Sdf {
+    Rc::new(move |p| {
+        let (da, db) = (a(p), b(p));
+        let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+        db + (da - db) * h - k * h * (1.0 - h)
+    })
+}
+
+pub fn translate(a: Sdf, offset: Vec3) -> Sdf {
+    Rc::new(move |p| a(p - offset))
+}
+
+//uniform scale around the origin, the distance scales with it
+pub fn scale(a: Sdf, s: f64) -> Sdf {
+    Rc::new(move |p| a(p / s) * s)
+}
+
+//copies a every period along each axis (0 leaves that axis alone), the copies are centered on multiples of period
+//a should fit within half a period of the origin or the neighbours cut into each other
+pub fn repeat(a: Sdf, period: Vec3) -> Sdf {
+    Rc::new(move |p| {
+        let mut q = p;
+        for axis in 0..3 {
+            let c = period[axis];
+            if c > 0.0 {
+                q[axis] = p[axis] - c * (p[axis] / c).round();
+            }
+        }
+        a(q)
+    })
+}
+
+//turns the xz plane by k radians per unit of y, around the y axis
+pub fn twist(a: Sdf, k: f64) -> Sdf {
+    Rc::new(move |p| {
+        let (sin, cos) = (k * p.y()).sin_cos();
+        a(Point3::new(
+            cos * p.x() - sin * p.z(),
+            p.y(),
+            sin * p.x() + cos * p.z(),
+        ))
+    })
+}
@@ -0,0 +1,334 @@
+use std::f64::consts::PI;
+
+use crate::color::{Color, blackbody, luminance, xyz_to_rgb};
+use crate::light::{DirectionalLight, Light, LightSample};
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+//the Preetham daylight model: a fit of the sky brightness and color to the sun position and how hazy the air is
+//turbidity 2 is a very clear day, 3 is typical, 6+ is hazy/warm. The sun disk itself is a DirectionalLight with the
+//sun color filtered by the same atmosphere. y is up, azimuth goes from +x towards +z
+//"A Practical Analytic Model for Daylight" (Preetham, Shirley, Smits 1999)
+//https://courses.cs.duke.edu/cps124/fall01/resources/p91-preetham.pdf
+pub struct PreethamSky {
+    sun_dir: Vec3,
+    theta_s: f64,
+    //zenith values in xyY, the rest of the sky is the zenith times the perez function ratio
+    zenith: [f64; 3],
+    perez: [[f64; 5]; 3],
+    sun: DirectionalLight,
+    //the model gives kilo candela per m^2, this brings it to scene units (0.05 keeps a sunlit white surface just under 1)
+    pub scale: f64,
+    pub sun_scale: f64,
+    dist: Distribution2D,
+}
+
+//sky table resolution for importance sampling, the sky is smooth so this does not need to be big
+const SKY_THETA_RES: usize = 32;
+const SKY_PHI_RES: usize = 64;
+
+impl PreethamSky {
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> PreethamSky {
+        let t = turbidity.clamp(1.7, 10.0);
+        let theta_s = (90.0 - elevation.clamp(-10.0, 90.0)).to_radians();
+        let phi_s = azimuth.to_radians();
+        let sun_dir = spherical_direction(theta_s, phi_s);
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        //once the sun is under the horizon the fit stops making sense, keep the zenith at the horizon values
+        let ts = theta_s.min(PI / 2.0);
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * ts);
+        let zenith_y = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+
+        let (ts2, ts3) = (ts * ts, ts * ts * ts);
+        let zenith_x = t * t * (0.00166 * ts3 - 0.00375 * ts2 + 0.00209 * ts)
+            + t * (-0.02903 * ts3 + 0.06377 * ts2 - 0.03202 * ts + 0.00394)
+            + (0.11693 * ts3 - 0.21196 * ts2 + 0.06052 * ts + 0.25886);
+        let zenith_yy = t * t * (0.00275 * ts3 - 0.00610 * ts2 + 0.00317 * ts)
+            + t * (-0.04214 * ts3 + 0.08970 * ts2 - 0.04153 * ts + 0.00516)
+            + (0.15346 * ts3 - 0.26756 * ts2 + 0.06670 * ts + 0.26688);
+
+        //about 100 klux of sun at noon on a clear day, in the same kilo units as the sky
+        let sun_irradiance = if theta_s < PI / 2.0 {
+            100.0 * sun_transmittance(theta_s, t) * blackbody(5800.0)
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        };
+
+        let mut sky = PreethamSky {
+            sun_dir,
+            theta_s,
+            zenith: [zenith_y, zenith_x, zenith_yy],
+            perez,
+            sun: DirectionalLight::new(sun_dir, sun_irradiance, 0.53),
+            scale: 0.05,
+            sun_scale: 1.0,
+            dist: Distribution2D::new(&[1.0], 1, 1),
+        };
+
+        //tabulate how bright the sky is at the middle of each cell, sin theta because the cells near the poles are smaller
+        let mut table = Vec::with_capacity(SKY_THETA_RES * SKY_PHI_RES);
+        for i in 0..SKY_THETA_RES {
+            let theta = (i as f64 + 0.5) / SKY_THETA_RES as f64 * PI;
+            for j in 0..SKY_PHI_RES {
+                let phi = (j as f64 + 0.5) / SKY_PHI_RES as f64 * 2.0 * PI;
+                let value = luminance(sky.sky_radiance(spherical_direction(theta, phi))).max(0.0);
+                table.push(value * theta.sin());
+            }
+        }
+        sky.dist = Distribution2D::new(&table, SKY_PHI_RES, SKY_THETA_RES);
+
+        sky
+    }
+
+    fn perez_f(coeffs: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = *coeffs;
+        (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+
+    //unscaled sky radiance without the sun. Below the horizon we just keep the horizon color, there is usually ground geometry there anyway
+    fn sky_radiance(&self, dir: Vec3) -> Color {
+        let dir = Vec3::unit_vector(dir);
+        let cos_theta = dir.y().max(0.01);
+        let gamma = Vec3::dot(dir, self.sun_dir).clamp(-1.0, 1.0).acos();
+        let ts = self.theta_s.min(PI / 2.0);
+
+        let xyy: [f64; 3] = std::array::from_fn(|k| {
+            let num = PreethamSky::perez_f(&self.perez[k], cos_theta, gamma);
+            let den = PreethamSky::perez_f(&self.perez[k], 1.0, ts);
+            self.zenith[k] * num / den
+        });
+
+        //the sky fades to nothing as the sun goes further below the horizon
+        let dusk = if self.theta_s > PI / 2.0 {
+            (1.0 - (self.theta_s - PI / 2.0) / 10f64.to_radians()).max(0.0)
+        } else {
+            1.0
+        };
+
+        dusk * xyy_to_rgb(xyy[0], xyy[1], xyy[2])
+    }
+
+    fn sun_probability(&self) -> f64 {
+        if self.sun.is_delta() || self.theta_s >= PI / 2.0 || self.sun_scale <= 0.0 {
+            0.0
+        } else {
+            0.5
+        }
+    }
+
+    fn sky_pdf(&self, wi: Vec3) -> f64 {
+        let wi = Vec3::unit_vector(wi);
+        let theta = wi.y().clamp(-1.0, 1.0).acos();
+        let sin_theta = theta.sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let mut phi = wi.z().atan2(wi.x());
+        if phi < 0.0 {
+            phi += 2.0 * PI;
+        }
+
+        //pdf over the unit square to solid angle: du dv = dphi dtheta / (2 pi * pi), dw = sin theta dtheta dphi
+        self.dist.pdf(phi / (2.0 * PI), theta / PI) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+impl Light for PreethamSky {
+    fn sample_li(&self, p: Point3, u: [f64; 2]) -> Option<LightSample> {
+        let p_sun = self.sun_probability();
+
+        //pick the sun or the sky with u[0], then stretch u[0] back to 0..1 so it can be used again
+        let wi = if u[0] < p_sun {
+            self.sun.sample_li(p, [u[0] / p_sun, u[1]])?.wi
+        } else {
+            let uc = if p_sun > 0.0 {
+                (u[0] - p_sun) / (1.0 - p_sun)
+            } else {
+                u[0]
+            };
+            let (su, sv) = self.dist.sample([uc, u[1]]);
+            spherical_direction(sv * PI, su * 2.0 * PI)
+        };
+
+        let pdf = self.pdf_li(p, wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            wi,
+            li: self.le(&Ray::new(p, wi, 0.0)),
+            pdf,
+            dist: f64::INFINITY,
+        })
+    }
+
+    //the sample can come from either strategy, so the pdf is the mix of both
+    fn pdf_li(&self, p: Point3, wi: Vec3) -> f64 {
+        let p_sun = self.sun_probability();
+        let mut pdf = (1.0 - p_sun) * self.sky_pdf(wi);
+        if p_sun > 0.0 {
+            pdf += p_sun * self.sun.pdf_li(p, wi);
+        }
+        pdf
+    }
+
+    fn le(&self, r: &Ray) -> Color {
+        self.scale * (self.sky_radiance(r.direction()) + self.sun_scale * self.sun.le(r))
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+}
+
+//theta from the +y axis, phi from +x towards +z
+fn spherical_direction(theta: f64, phi: f64) -> Vec3 {
+    Vec3::new(
+        theta.sin() * phi.cos(),
+        theta.cos(),
+        theta.sin() * phi.sin(),
+    )
+}
+
+fn xyy_to_rgb(big_y: f64, x: f64, y: f64) -> Color {
+    if y <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+
+    xyz_to_rgb(Vec3::new(x / y * big_y, big_y, (1.0 - x - y) / y * big_y))
+}
+
+//how much of the sunlight makes it through the atmosphere, per channel (650, 550, 450 nm)
+//rayleigh scattering from the air plus aerosols (angstrom's formula), from the appendix of the preetham paper
+fn sun_transmittance(theta_s: f64, turbidity: f64) -> Color {
+    let theta_deg = theta_s.to_degrees();
+    let air_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_deg).max(1e-3).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+
+    let channel = |lambda_um: f64| {
+        let rayleigh = (-0.008735 * lambda_um.powf(-4.08) * air_mass).exp();
+        let aerosol = (-beta * lambda_um.powf(-1.3) * air_mass).exp();
+        rayleigh * aerosol
+    };
+
+    Color::new(channel(0.65), channel(0.55), channel(0.45))
+}
+
+//piecewise constant distributions for sampling a tabulated function (pbrt's Distribution1D/2D)
+//https://pbr-book.org/3ed-2018/Monte_Carlo_Integration/2D_Sampling_with_Multidimensional_Transformations
+struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    fn new(func: &[f64]) -> Distribution1D {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i].max(0.0) / n as f64;
+        }
+
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            //all zero, fall back to uniform
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n as f64
+            };
+        }
+
+        Distribution1D {
+            func: func.iter().map(|f| f.max(0.0)).collect(),
+            cdf,
+            integral,
+        }
+    }
+
+    //returns where in 0..1 we landed and the pdf there
+    fn sample(&self, u: f64) -> (f64, f64, usize) {
+        let n = self.func.len();
+        let i = (self.cdf.partition_point(|&c| c <= u).max(1) - 1).min(n - 1);
+
+        let span = self.cdf[i + 1] - self.cdf[i];
+        let du = if span > 0.0 {
+            (u - self.cdf[i]) / span
+        } else {
+            0.0
+        };
+
+        let pdf = if self.integral > 0.0 {
+            self.func[i] / self.integral
+        } else {
+            1.0
+        };
+
+        ((i as f64 + du) / n as f64, pdf, i)
+    }
+}
+
+struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    //func is nv rows of nu values
+    fn new(func: &[f64], nu: usize, nv: usize) -> Distribution2D {
+        let conditional: Vec<Distribution1D> = (0..nv)
+            .map(|v| Distribution1D::new(&func[v * nu..(v + 1) * nu]))
+            .collect();
+        let marginal_func: Vec<f64> = conditional.iter().map(|c| c.integral).collect();
+
+        Distribution2D {
+            conditional,
+            marginal: Distribution1D::new(&marginal_func),
+        }
+    }
+
+    fn sample(&self, u: [f64; 2]) -> (f64, f64) {
+        let (v, _, row) = self.marginal.sample(u[1]);
+        let (u, _, _) = self.conditional[row].sample(u[0]);
+        (u, v)
+    }
+
+    fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row =
+            ((v * self.marginal.func.len() as f64) as usize).min(self.marginal.func.len() - 1);
+        let cond = &self.conditional[row];
+        let col = ((u * cond.func.len() as f64) as usize).min(cond.func.len() - 1);
+
+        if self.marginal.integral <= 0.0 {
+            return 1.0;
+        }
+        cond.func[col] / self.marginal.integral
+    }
+}
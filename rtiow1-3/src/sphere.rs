@@ -44,6 +44,10 @@ impl Sphere {
         }
     }
 
+    pub fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
     //point on a unit sphere to uv calculations
     //see notes for derivation
     pub fn get_sphere_uv(p: &Point3, u: &mut f64, v: &mut f64) -> () {
@@ -54,6 +58,15 @@ impl Sphere {
         *v = theta / PI;
     }
 
+    fn set_record(&self, r: &Ray, root: f64, current_center: Point3, rec: &mut HitRecord) {
+        rec.t = root;
+        rec.p = r.at(rec.t);
+        let outward_normal = (rec.p - current_center) / self.radius;
+        rec.set_face_normal(r, outward_normal);
+        Sphere::get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
+        rec.mat = self.mat.clone(); //we use clone here because Rc counts references and gives a pointer back to the object, which you do by using clone().
+    }
+
     fn random_to_sphere(radius: f64, dsq: f64) -> Vec3 {
         let r1 = random_double();
         let r2 = random_double();
@@ -95,16 +108,34 @@ impl Hittable for Sphere {
             }
         }
 
-        rec.t = root;
-        rec.p = r.at(rec.t);
-        let outward_normal = (rec.p - current_center) / self.radius;
-        rec.set_face_normal(r, outward_normal);
-        Sphere::get_sphere_uv(&outward_normal, &mut rec.u, &mut rec.v);
-        rec.mat = self.mat.clone(); //we use clone here because Rc counts references and gives a pointer back to the object, which you do by using clone().
+        self.set_record(r, root, current_center, rec);
 
         true
     }
 
+    //both roots at once, the entry and the exit
+    fn hit_all(&self, r: &Ray, ray_t: Interval, hits: &mut Vec<HitRecord>) {
+        let current_center = self.center.at(r.time());
+        let oc: Vec3 = current_center - r.origin();
+        let a = r.direction().squared_length();
+        let h = Vec3::dot(r.direction(), oc);
+        let c = oc.squared_length() - self.radius * self.radius;
+        let discriminant = h * h - a * c;
+
+        if discriminant < 0. {
+            return;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        for root in [(h - sqrtd) / a, (h + sqrtd) / a] {
+            if ray_t.surrounds(root) {
+                let mut rec = HitRecord::new();
+                self.set_record(r, root, current_center, &mut rec);
+                hits.push(rec);
+            }
+        }
+    }
+
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
@@ -69,19 +69,33 @@ impl Texture for CheckerTexture {
 }
 
 //uses uv coordinates to sample color data images, creating an image texture on the object -> both spheres and quads
+//repeat tiles the image when uv goes outside 0..1 (what model files expect), otherwise the edge pixels get stretched
+//srgb images (photos, base colors) get converted to linear, data images (normal maps, roughness) are used as they are
 pub struct ImageTexture {
     image: RgbImage,
+    srgb: bool,
+    pub repeat: bool,
 }
 
 impl ImageTexture {
     pub fn new(filename: &str) -> ImageTexture {
-        let image = ImageReader::open(filename)
-            .expect("Failed")
-            .decode()
-            .expect("Failed")
-            .to_rgb8();
+        ImageTexture::open(filename).expect("Failed")
+    }
+
+    //same as new but hands back the error, for files that come from somewhere else (model materials) and might be missing
+    pub fn open(filename: &str) -> image::ImageResult<ImageTexture> {
+        let image = ImageReader::open(filename)?.decode()?.to_rgb8();
+
+        Ok(ImageTexture::from_image(image, true))
+    }
 
-        ImageTexture { image }
+    //for pixels that are already in memory (textures embedded in model files)
+    pub fn from_image(image: RgbImage, srgb: bool) -> ImageTexture {
+        ImageTexture {
+            image,
+            srgb,
+            repeat: false,
+        }
     }
 }
 
@@ -91,12 +105,16 @@ impl Texture for ImageTexture {
             return Color::new(0.0, 1.0, 1.0);
         }
 
-        let u = u.clamp(0.0, 1.0);
-        let v = 1.0 - v.clamp(0.0, 1.0);
+        let (u, v) = if self.repeat {
+            (u - u.floor(), v - v.floor())
+        } else {
+            (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0))
+        };
+        let v = 1.0 - v;
 
-        //might result in a out of bounds if u or v or exatly 1?
-        let i = (u * self.image.width() as f64) as u32;
-        let j = (v * self.image.height() as f64) as u32;
+        //it did go out of bounds when u or v was exactly 1
+        let i = ((u * self.image.width() as f64) as u32).min(self.image.width() - 1);
+        let j = ((v * self.image.height() as f64) as u32).min(self.image.height() - 1);
 
         let pixel = self.image.get_pixel(i, j);
 
@@ -106,6 +124,10 @@ impl Texture for ImageTexture {
         let g_srgb = color_scale * pixel[1] as f64;
         let b_srgb = color_scale * pixel[2] as f64;
 
+        if !self.srgb {
+            return Color::new(r_srgb, g_srgb, b_srgb);
+        }
+
         //Color::new(color_scale * pixel[0] as f64, color_scale * pixel[1] as f64, color_scale * pixel[2] as f64)
         Color::new(r_srgb.powi(2), g_srgb.powi(2), b_srgb.powi(2))
     }
@@ -0,0 +1,345 @@
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use crate::aabb::AABB;
+use crate::disk::disk_extent;
+use crate::hittable::*;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::ONB;
+use crate::ray::Ray;
+use crate::utils::random_double;
+use crate::vec3::{Point3, Vec3};
+
+//donut around axis through center. major_radius is from the center to the middle of the tube, minor_radius is the tube itself
+//u goes around the axis (angle / 2pi), v goes around the tube (0 on the outside equator, then over the top)
+pub struct Torus {
+    center: Point3,
+    frame: ONB,
+    major_radius: f64,
+    minor_radius: f64,
+    mat: Rc<dyn Material>,
+    bbox: AABB,
+    area: f64,
+}
+
+impl Torus {
+    pub fn new(
+        center: Point3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        mat: Rc<dyn Material>,
+    ) -> Torus {
+        let major_radius = major_radius.max(0.0);
+        let minor_radius = minor_radius.max(0.0);
+
+        //the ring of tube centers is a circle, the tube adds minor_radius in every direction
+        let r = Vec3::new(minor_radius, minor_radius, minor_radius);
+        let e = disk_extent(axis, major_radius) + r;
+
+        Torus {
+            center,
+            frame: ONB::new(axis),
+            major_radius,
+            minor_radius,
+            mat,
+            bbox: AABB::new_point(center - e, center + e),
+            area: 4.0 * PI * PI * major_radius * minor_radius,
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+}
+
+impl Hittable for Torus {
+    //in local space (axis = z) the surface is (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2), a quartic in t once p = o + t d
+    //the quartic loses a lot of precision when the ray starts far away, so the ray is normalized and moved up to
+    //the bounding sphere first, then the roots are cleaned up with a few newton steps
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let (big_r, small_r) = (self.major_radius, self.minor_radius);
+        let dir_len = r.direction().length();
+        if dir_len == 0.0 {
+            return false;
+        }
+
+        let o = self.frame.to_local(r.origin() - self.center);
+        let d = self.frame.to_local(r.direction()) / dir_len;
+
+        //bounding sphere, anything that misses it misses the torus
+        let bound = big_r + small_r;
+        let b = Vec3::dot(o, d);
+        let disc = b * b - (o.squared_length() - bound * bound);
+        if disc < 0.0 {
+            return false;
+        }
+        let t_exit = -b + disc.sqrt();
+        if t_exit < 0.0 {
+            return false;
+        }
+        let shift = (-b - disc.sqrt()).max(0.0);
+        let o = o + shift * d;
+
+        //coefficients of s^4 + c3 s^3 + c2 s^2 + c1 s + c0 with s the distance from the shifted origin
+        let f = Vec3::dot(o, d);
+        let k = o.squared_length() + big_r * big_r - small_r * small_r;
+        let four_r2 = 4.0 * big_r * big_r;
+        let c3 = 4.0 * f;
+        let c2 = 4.0 * f * f + 2.0 * k - four_r2 + four_r2 * d.z() * d.z();
+        let c1 = 4.0 * f * k - 2.0 * four_r2 * f + 2.0 * four_r2 * o.z() * d.z();
+        let c0 = k * k - four_r2 * o.squared_length() + four_r2 * o.z() * o.z();
+
+        let mut closest = f64::INFINITY;
+        for root in solve_quartic(c3, c2, c1, c0) {
+            let mut s = root;
+            for _ in 0..3 {
+                let value = (((s + c3) * s + c2) * s + c1) * s + c0;
+                let slope = ((4.0 * s + 3.0 * c3) * s + 2.0 * c2) * s + c1;
+                if slope.abs() < 1e-12 {
+                    break;
+                }
+                s -= value / slope;
+            }
+
+            let t = (shift + s) / dir_len;
+            if ray_t.surrounds(t) && t < closest {
+                closest = t;
+            }
+        }
+
+        if closest == f64::INFINITY {
+            return false;
+        }
+
+        let p = self.frame.to_local(r.at(closest) - self.center);
+        let dist = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        let ring = if dist > 1e-12 {
+            Vec3::new(p.x(), p.y(), 0.0) * (big_r / dist)
+        } else {
+            Vec3::new(big_r, 0.0, 0.0)
+        };
+        let outward_normal = Vec3::unit_vector(p - ring);
+        let phi = p.y().atan2(p.x()) + PI;
+        let theta = p.z().atan2(dist - big_r);
+
+        rec.t = closest;
+        rec.p = r.at(closest);
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(r, self.frame.transform(outward_normal));
+        rec.u = phi / (2.0 * PI);
+        rec.v = theta.rem_euclid(2.0 * PI) / (2.0 * PI);
+        rec.dpdu = self
+            .frame
+            .transform(Vec3::new(-p.y(), p.x(), 0.0) / dist.max(1e-12));
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, dir: Vec3) -> f64 {
+        area_pdf_value(self, origin, dir, self.area)
+    }
+
+    //uniform over the area. The outside of the tube is further from the axis so it has more area than the inside,
+    //(R + r cos theta) times more, so theta is picked uniformly and then kept with that probability
+    fn random(&self, origin: Vec3) -> Vec3 {
+        let (big_r, small_r) = (self.major_radius, self.minor_radius);
+        let phi = 2.0 * PI * random_double();
+        let theta = loop {
+            let theta = 2.0 * PI * random_double();
+            if random_double() * (big_r + small_r) <= big_r + small_r * theta.cos() {
+                break theta;
+            }
+        };
+
+        let dist = big_r + small_r * theta.cos();
+        let local = Vec3::new(dist * phi.cos(), dist * phi.sin(), small_r * theta.sin());
+        self.center + self.frame.transform(local) - origin
+    }
+}
+
+//real roots of x^2 + b x + c
+fn solve_quadratic(b: f64, c: f64) -> Vec<f64> {
+    let p = b / 2.0;
+    let disc = p * p - c;
+    if disc.abs() < 1e-12 {
+        vec![-p]
+    } else if disc < 0.0 {
+        Vec::new()
+    } else {
+        let s = disc.sqrt();
+        vec![-p - s, -p + s]
+    }
+}
+
+//real roots of x^3 + a x^2 + b x + c with cardano, the three root case uses the trig form
+//https://en.wikipedia.org/wiki/Cubic_equation
+fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    //x = y - a/3 gets rid of the square term, y^3 + 3p y + 2q = 0
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+    let cb_p = p * p * p;
+    let disc = q * q + cb_p;
+
+    let roots = if disc.abs() < 1e-12 {
+        if q.abs() < 1e-12 {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if disc < 0.0 {
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + PI / 3.0).cos(),
+            -t * (phi - PI / 3.0).cos(),
+        ]
+    } else {
+        let sqrt_disc = disc.sqrt();
+        vec![(sqrt_disc - q).cbrt() - (sqrt_disc + q).cbrt()]
+    };
+
+    roots.into_iter().map(|y| y - a / 3.0).collect()
+}
+
+//real roots of x^4 + a x^3 + b x^2 + c x + d with ferrari, splits into two quadratics using one root of a cubic
+//https://en.wikipedia.org/wiki/Quartic_function#Ferrari's_solution
+fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    //x = y - a/4 gets rid of the cube term, y^4 + p y^2 + q y + r = 0
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * b / 16.0 - a * c / 4.0 + d;
+
+    let mut roots = if r.abs() < 1e-12 {
+        //y (y^3 + p y + q) = 0
+        let mut roots = solve_cubic(0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        let z = solve_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0)[0];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        if u < -1e-12 || v < -1e-12 {
+            return Vec::new();
+        }
+        let u = u.max(0.0).sqrt();
+        let v = if q < 0.0 {
+            -v.max(0.0).sqrt()
+        } else {
+            v.max(0.0).sqrt()
+        };
+
+        let mut roots = solve_quadratic(v, z - u);
+        roots.extend(solve_quadratic(-v, z + u));
+        roots
+    };
+
+    for y in roots.iter_mut() {
+        *y -= a / 4.0;
+    }
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+
+    //coefficients of the monic quartic (x - r0)(x - r1) times x^2 + p x + q
+    fn quartic(r0: f64, r1: f64, p: f64, q: f64) -> [f64; 4] {
+        let (s, m) = (-(r0 + r1), r0 * r1);
+        [s + p, m + s * p + q, m * p + s * q, m * q]
+    }
+
+    fn sorted_roots(c: [f64; 4]) -> Vec<f64> {
+        let mut roots = solve_quartic(c[0], c[1], c[2], c[3]);
+        roots.sort_by(f64::total_cmp);
+        roots
+    }
+
+    fn assert_roots(found: Vec<f64>, expected: &[f64]) {
+        assert_eq!(found.len(), expected.len(), "{:?}", found);
+        for (f, e) in found.iter().zip(expected) {
+            assert!((f - e).abs() < 1e-6, "{:?} != {:?}", found, expected);
+        }
+    }
+
+    #[test]
+    fn quartic_four_real_roots() {
+        //(x - 2)(x + 3) (x - 0.5)(x - 1), the second pair is x^2 - 1.5x + 0.5
+        assert_roots(
+            sorted_roots(quartic(2.0, -3.0, -1.5, 0.5)),
+            &[-3.0, 0.5, 1.0, 2.0],
+        );
+    }
+
+    #[test]
+    fn quartic_two_real_roots() {
+        //(x - 1)(x + 2)(x^2 + 1)
+        assert_roots(sorted_roots(quartic(1.0, -2.0, 0.0, 1.0)), &[-2.0, 1.0]);
+    }
+
+    #[test]
+    fn quartic_no_real_roots() {
+        //(x^2 + 1)(x^2 + 2x + 5) multiplied out
+        let c = [2.0, 6.0, 2.0, 5.0];
+        assert_roots(sorted_roots(c), &[]);
+    }
+
+    fn torus() -> Torus {
+        Torus::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            2.0,
+            0.5,
+            Rc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5))),
+        )
+    }
+
+    fn hit(origin: Point3, dir: Vec3) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        torus()
+            .hit(
+                &Ray::new(origin, dir, 0.0),
+                Interval::new(0.001, f64::INFINITY),
+                &mut rec,
+            )
+            .then_some(rec)
+    }
+
+    #[test]
+    fn torus_hit_from_outside() {
+        let rec = hit(Point3::new(-10.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 3.75).abs() < 1e-9);
+        assert!((rec.p - Point3::new(-2.5, 0.0, 0.0)).length() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+
+        //straight down the hole misses
+        assert!(hit(Point3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn torus_hit_from_inside_the_hole() {
+        let rec = hit(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)).unwrap();
+        assert!((rec.t - 1.5).abs() < 1e-9);
+        assert!(rec.front_face);
+        //the outward normal on the inner side of the tube points back at the axis
+        assert!((rec.normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-9);
+
+        //from inside the tube the ray leaves through the back of the surface
+        let rec = hit(Point3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, 1.0)).unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-9);
+        assert!(!rec.front_face);
+    }
+}
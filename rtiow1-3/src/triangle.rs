@@ -5,6 +5,9 @@ pub struct Triangle {
     a: Point3,
     b: Point3,
     c: Point3,
+    //per vertex normals and texture coordinates, without them it is flat shaded and u v are the barycentrics
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[[f64; 2]; 3]>,
     mat: Rc<dyn Material>,
     bbox: AABB,
 }
@@ -24,48 +27,134 @@ impl Triangle {
             a: a,
             b: b,
             c: c,
+            normals: None,
+            uvs: None,
             mat: mat,
             bbox: bbox,
         }
     }
+
+    //smooth shaded triangle, the normals (and uvs if given) are for a, b and c in that order
+    pub fn new_smooth(
+        a: Point3,
+        b: Point3,
+        c: Point3,
+        normals: [Vec3; 3],
+        uvs: Option<[[f64; 2]; 3]>,
+        mat: Rc<dyn Material>,
+    ) -> Triangle {
+        let mut tri = Triangle::new(a, b, c, mat);
+        tri.normals = Some(normals);
+        tri.uvs = uvs;
+        tri
+    }
+
+    pub fn area(&self) -> f64 {
+        0.5 * Vec3::cross(self.b - self.a, self.c - self.a).length()
+    }
 }
 
 //https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
 //to be added to notes
-impl Hittable for Triangle {
-    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
-        let e1 = self.b - self.a;
-        let e2 = self.c - self.a;
+//the ray against the triangle a, a + e1, a + e2, gives back t and the barycentrics of the 2nd and 3rd corner
+//shared with MeshTriangle and Heightfield, the caller checks t against its interval
+pub fn intersect_triangle(r: &Ray, a: Point3, e1: Vec3, e2: Vec3) -> Option<(f64, f64, f64)> {
+    let ray_cross_e2 = Vec3::cross(r.direction(), e2);
+    let det = Vec3::dot(e1, ray_cross_e2);
+    if det.abs() < f64::EPSILON {
+        return None;
+    }
 
-        let ray_cross_e2 = Vec3::cross(r.direction(), e2);
-        let det = Vec3::dot(e1, ray_cross_e2);
+    let inv_det = 1.0 / det;
+    let s = r.origin() - a;
+    let u = inv_det * Vec3::dot(s, ray_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
 
-        if det.abs() < f64::EPSILON {
-            return false;
-        }
+    let s_cross_e1 = Vec3::cross(s, e1);
+    let v = inv_det * Vec3::dot(r.direction(), s_cross_e1);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
 
-        let inv_det = 1.0 / det;
-        let s = r.origin() - self.a;
-        let u = inv_det * Vec3::dot(s, ray_cross_e2);
-        if u < 0.0 || u > 1.0 {
-            return false;
+    Some((inv_det * Vec3::dot(e2, s_cross_e1), u, v))
+}
+
+//fills in the shading part of the record once the hit and the geometric normal are set, shared with MeshTriangle
+//bary are the weights of the 3 vertices, e1 and e2 the edges from the first one
+//tangents are per vertex with the handedness of their bitangent (glTF's w), otherwise the tangent comes from the uvs
+pub fn shade_triangle(
+    rec: &mut HitRecord,
+    bary: [f64; 3],
+    e1: Vec3,
+    e2: Vec3,
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[[f64; 2]; 3]>,
+    tangents: Option<[(Vec3, f64); 3]>,
+) {
+    let [w, u, v] = bary;
+
+    match uvs {
+        None => {
+            rec.u = u;
+            rec.v = v;
         }
+        Some([uv0, uv1, uv2]) => {
+            rec.u = w * uv0[0] + u * uv1[0] + v * uv2[0];
+            rec.v = w * uv0[1] + u * uv1[1] + v * uv2[1];
 
-        let s_cross_e1 = Vec3::cross(s, e1);
-        let v = inv_det * Vec3::dot(r.direction(), s_cross_e1);
-        if v < 0.0 || u + v > 1.0 {
-            return false;
+            //tangent from how the texture coordinates run over the triangle
+            let du1 = uv1[0] - uv0[0];
+            let dv1 = uv1[1] - uv0[1];
+            let du2 = uv2[0] - uv0[0];
+            let dv2 = uv2[1] - uv0[1];
+            let uv_det = du1 * dv2 - dv1 * du2;
+            if uv_det.abs() > 1e-12 {
+                rec.dpdu = (dv2 * e1 - dv1 * e2) / uv_det;
+                //normal x dpdu lines up with dpdv when the uvs wind the same way as the vertices, mirrored uvs flip it
+                rec.tangent_sign = uv_det.signum();
+            }
         }
+    }
 
-        let t = inv_det * Vec3::dot(e2, s_cross_e1);
+    if let Some([(t0, s0), (t1, s1), (t2, s2)]) = tangents {
+        rec.dpdu = w * t0 + u * t1 + v * t2;
+        rec.tangent_sign = if w * s0 + u * s1 + v * s2 < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+    }
+
+    if let Some([n0, n1, n2]) = normals {
+        rec.set_shading_normal(w * n0 + u * n1 + v * n2);
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let e1 = self.b - self.a;
+        let e2 = self.c - self.a;
+
+        let Some((t, u, v)) = intersect_triangle(r, self.a, e1, e2) else {
+            return false;
+        };
 
         if t > f64::EPSILON && ray_t.contains(t) {
             rec.t = t;
             rec.p = r.origin() + r.direction() * t;
-            rec.u = u;
-            rec.v = v;
             rec.mat = self.mat.clone();
             rec.set_face_normal(r, Vec3::unit_vector(Vec3::cross(e1, e2)));
+            shade_triangle(
+                rec,
+                [1.0 - u - v, u, v],
+                e1,
+                e2,
+                self.normals,
+                self.uvs,
+                None,
+            );
 
             return true;
         }
@@ -0,0 +1,1125 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read};
+
+use flate2::read::ZlibDecoder;
+
+use crate::aabb::AABB;
+use crate::mat4::Mat4;
+use crate::medium::Density;
+use crate::vec3::{Point3, Vec3};
+
+//a sparse float grid from an OpenVDB (.vdb) or NanoVDB (.nvdb) file, density or temperature out of a smoke sim
+//both store the same tree: a root with 4096^3 tiles, two levels of internal nodes (32^3 entries of 128^3 and 16^3 entries
+//of 8^3) and 8^3 leaves at the bottom. Any entry can be a constant tile instead of a child, so we keep the leaves and the
+//tiles of each size in hash maps by their origin and look a voxel up from the smallest size to the biggest
+//values sit on integer index coordinates (voxel centers) and get trilinear in between, the grid's transform maps index
+//space to world space
+//https://www.openvdb.org/documentation/doxygen/codeExamples.html
+pub struct VdbGrid {
+    leaves: HashMap<[i32; 3], Leaf>,
+    //tiles of 8^3, 128^3 and 4096^3 voxels, only the ones that are active or not the background
+    tiles: [HashMap<[i32; 3], f32>; 3],
+    background: f32,
+    index_to_world: Mat4,
+    world_to_index: Mat4,
+    //active voxels in index space, inclusive
+    index_min: [i32; 3],
+    index_max: [i32; 3],
+}
+
+//log2 of the side of the tiles in VdbGrid::tiles
+const TILE_LOG2: [u32; 3] = [3, 7, 12];
+
+struct Leaf {
+    //x changes slowest: value (x, y, z) is at x * 64 + y * 8 + z, same as the files
+    values: Box<[f32; 512]>,
+    max: f32,
+}
+
+impl VdbGrid {
+    fn new(background: f32, index_to_world: Mat4) -> std::io::Result<VdbGrid> {
+        let Some(world_to_index) = index_to_world.inverse() else {
+            return Err(invalid("the grid transform cant be inverted"));
+        };
+
+        Ok(VdbGrid {
+            leaves: HashMap::new(),
+            tiles: [HashMap::new(), HashMap::new(), HashMap::new()],
+            background,
+            index_to_world,
+            world_to_index,
+            index_min: [i32::MAX; 3],
+            index_max: [i32::MIN; 3],
+        })
+    }
+
+    fn grow_active(&mut self, min: [i32; 3], max: [i32; 3]) {
+        for axis in 0..3 {
+            self.index_min[axis] = self.index_min[axis].min(min[axis]);
+            self.index_max[axis] = self.index_max[axis].max(max[axis]);
+        }
+    }
+
+    fn add_tile(&mut self, log2: u32, origin: [i32; 3], value: f32, active: bool) {
+        if active {
+            let side = (1 << log2) - 1;
+            self.grow_active(origin, origin.map(|c| c + side));
+        }
+        if active || value != self.background {
+            let level = TILE_LOG2.iter().position(|&l| l == log2).unwrap();
+            self.tiles[level].insert(origin, value);
+        }
+    }
+
+    fn add_leaf(&mut self, origin: [i32; 3], values: &[f32], active: &[u64]) {
+        for n in 0..512 {
+            if is_on(active, n) {
+                let voxel = [
+                    origin[0] + (n >> 6) as i32,
+                    origin[1] + ((n >> 3) & 7) as i32,
+                    origin[2] + (n & 7) as i32,
+                ];
+                self.grow_active(voxel, voxel);
+            }
+        }
+
+        let mut leaf = Leaf {
+            values: Box::new([0.0; 512]),
+            max: f32::NEG_INFINITY,
+        };
+        leaf.values.copy_from_slice(&values[..512]);
+        leaf.max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        self.leaves.insert(origin, leaf);
+    }
+
+    fn value(&self, ijk: [i32; 3]) -> f32 {
+        let origin = |log2: u32| ijk.map(|c| c & !((1 << log2) - 1));
+
+        if let Some(leaf) = self.leaves.get(&origin(3)) {
+            let [x, y, z] = ijk.map(|c| (c & 7) as usize);
+            return leaf.values[x * 64 + y * 8 + z];
+        }
+        for (level, tiles) in self.tiles.iter().enumerate() {
+            if let Some(&value) = tiles.get(&origin(TILE_LOG2[level])) {
+                return value;
+            }
+        }
+        self.background
+    }
+
+    //world space box around everything that isnt background. Trilinear reaches one voxel past the last active one
+    pub fn bounding_box(&self) -> AABB {
+        if self.index_min[0] > self.index_max[0] {
+            return AABB::EMPTY;
+        }
+
+        let lo = self.index_min.map(|c| c as f64 - 1.0);
+        let hi = self.index_max.map(|c| c as f64 + 1.0);
+
+        let mut bbox = AABB::EMPTY;
+        for corner in 0..8 {
+            let p = Point3::new(
+                if corner & 1 == 0 { lo[0] } else { hi[0] },
+                if corner & 2 == 0 { lo[1] } else { hi[1] },
+                if corner & 4 == 0 { lo[2] } else { hi[2] },
+            );
+            let p = self.index_to_world.transform_point(p);
+            bbox = AABB::new_boxes(&bbox, &AABB::new_point(p, p));
+        }
+        bbox
+    }
+}
+
+//calls f for every block of a map (size 2^log2) that overlaps lo..=hi in index space, by looking up every block origin
+//in the range or by going through the whole map, whichever is less work
+fn blocks_in<T>(
+    map: &HashMap<[i32; 3], T>,
+    log2: u32,
+    lo: [i32; 3],
+    hi: [i32; 3],
+    mut f: impl FnMut(&T),
+) {
+    let first = lo.map(|c| c >> log2);
+    let last = hi.map(|c| c >> log2);
+    let count: i64 = (0..3).map(|a| (last[a] - first[a] + 1) as i64).product();
+
+    if count <= map.len() as i64 {
+        for x in first[0]..=last[0] {
+            for y in first[1]..=last[1] {
+                for z in first[2]..=last[2] {
+                    if let Some(block) = map.get(&[x << log2, y << log2, z << log2]) {
+                        f(block);
+                    }
+                }
+            }
+        }
+    } else {
+        for (origin, block) in map {
+            if (0..3).all(|a| origin[a] >> log2 >= first[a] && origin[a] >> log2 <= last[a]) {
+                f(block);
+            }
+        }
+    }
+}
+
+impl Density for VdbGrid {
+    fn density(&self, p: Point3) -> f64 {
+        let q = self.world_to_index.transform_point(p);
+        let base = [q.x().floor(), q.y().floor(), q.z().floor()];
+        let f = [q.x() - base[0], q.y() - base[1], q.z() - base[2]];
+        let base = base.map(|c| c as i32);
+
+        let mut accum = 0.0;
+        for corner in 0..8 {
+            let d = [corner & 1, (corner >> 1) & 1, corner >> 2];
+            let weight: f64 = (0..3)
+                .map(|a| if d[a] == 1 { f[a] } else { 1.0 - f[a] })
+                .product();
+            if weight > 0.0 {
+                let ijk = [base[0] + d[0], base[1] + d[1], base[2] + d[2]];
+                accum += weight * self.value(ijk) as f64;
+            }
+        }
+        accum.max(0.0)
+    }
+
+    //the highest value of every leaf and tile the box touches, and the background for the voxels that arent stored
+    fn max_density(&self, bbox: &AABB) -> f64 {
+        let mut lo = [f64::INFINITY; 3];
+        let mut hi = [f64::NEG_INFINITY; 3];
+        for corner in 0..8 {
+            let p = Point3::new(
+                if corner & 1 == 0 {
+                    bbox.x.min
+                } else {
+                    bbox.x.max
+                },
+                if corner & 2 == 0 {
+                    bbox.y.min
+                } else {
+                    bbox.y.max
+                },
+                if corner & 4 == 0 {
+                    bbox.z.min
+                } else {
+                    bbox.z.max
+                },
+            );
+            let q = self.world_to_index.transform_point(p);
+            for axis in 0..3 {
+                lo[axis] = lo[axis].min(q[axis]);
+                hi[axis] = hi[axis].max(q[axis]);
+            }
+        }
+
+        //trilinear uses the voxel below and the one above, clamped so a huge box cant overflow the index math
+        let limit = 1 << 30;
+        let lo = lo.map(|c| (c.floor() as i64).clamp(-limit, limit) as i32);
+        let hi = hi.map(|c| (c.floor() as i64 + 1).clamp(-limit, limit) as i32);
+
+        let mut max = self.background;
+        blocks_in(&self.leaves, 3, lo, hi, |leaf| max = max.max(leaf.max));
+        for (level, tiles) in self.tiles.iter().enumerate() {
+            blocks_in(tiles, TILE_LOG2[level], lo, hi, |&v| max = max.max(v));
+        }
+        (max as f64).max(0.0)
+    }
+}
+
+//loads the float grid called grid_name (usually "density" or "temperature"), the extension says which format it is
+pub fn load_vdb(path: &str, grid_name: &str) -> std::io::Result<VdbGrid> {
+    let grid = std::fs::read(path).and_then(|bytes| {
+        if path.to_lowercase().ends_with(".nvdb") {
+            parse_nanovdb(&bytes, grid_name)
+        } else {
+            parse_openvdb(&bytes, grid_name)
+        }
+    });
+    grid.map_err(|e| Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+fn invalid(what: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("vdb: {}", what))
+}
+
+fn missing(grid_name: &str, found: &[String]) -> Error {
+    invalid(&format!(
+        "no float grid called {} (the file has: {})",
+        grid_name,
+        found.join(", ")
+    ))
+}
+
+fn is_on(mask: &[u64], n: usize) -> bool {
+    mask[n >> 6] & (1 << (n & 63)) != 0
+}
+
+//everything in both formats is little endian
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> std::io::Result<&'a [u8]> {
+        let Some(raw) = self
+            .pos
+            .checked_add(n)
+            .and_then(|end| self.bytes.get(self.pos..end))
+        else {
+            return Err(invalid(&format!("file ends early at byte {}", self.pos)));
+        };
+        self.pos += n;
+        Ok(raw)
+    }
+
+    fn array<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> std::io::Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn i32(&mut self) -> std::io::Result<i32> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> std::io::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> std::io::Result<i64> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    fn f32(&mut self) -> std::io::Result<f32> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> std::io::Result<f64> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    fn vec3(&mut self) -> std::io::Result<Vec3> {
+        Ok(Vec3::new(self.f64()?, self.f64()?, self.f64()?))
+    }
+
+    fn coord(&mut self) -> std::io::Result<[i32; 3]> {
+        Ok([self.i32()?, self.i32()?, self.i32()?])
+    }
+
+    //openvdb strings are a u32 length and the bytes
+    fn string(&mut self) -> std::io::Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    fn mask(&mut self, bits: usize) -> std::io::Result<Vec<u64>> {
+        (0..bits / 64).map(|_| self.u64()).collect()
+    }
+
+    fn seek(&mut self, pos: i64) -> std::io::Result<()> {
+        if pos < 0 || pos as usize > self.bytes.len() {
+            return Err(invalid(&format!("bad offset {}", pos)));
+        }
+        self.pos = pos as usize;
+        Ok(())
+    }
+}
+
+const OPENVDB_MAGIC: i64 = 0x56444220;
+//files from openvdb 3 (2014) on, older ones store the compression and the leaves differently
+const OPENVDB_MIN_VERSION: u32 = 222;
+
+const COMPRESS_ZIP: u32 = 0x1;
+const COMPRESS_ACTIVE_MASK: u32 = 0x2;
+const COMPRESS_BLOSC: u32 = 0x4;
+
+//what the file tells us about how a grid's values are stored
+struct Storage {
+    compression: u32,
+    half: bool,
+    background: f32,
+}
+
+//header, file metadata, then a descriptor for every grid with the offsets of its data. A grid is its compression flags,
+//metadata, transform, then the tree: the topology first (root tiles, every internal node's masks and values, only the
+//masks of the leaves) and after it the values of all the leaves in the same order
+//https://github.com/AcademySoftwareFoundation/openvdb/blob/master/openvdb/openvdb/io/Archive.cc
+fn parse_openvdb(bytes: &[u8], grid_name: &str) -> std::io::Result<VdbGrid> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.i64()? != OPENVDB_MAGIC {
+        return Err(invalid("not an openvdb file"));
+    }
+    let version = r.u32()?;
+    if version < OPENVDB_MIN_VERSION {
+        return Err(invalid(&format!(
+            "file format {} is too old, {} or newer works",
+            version, OPENVDB_MIN_VERSION
+        )));
+    }
+    //library major and minor version
+    r.u32()?;
+    r.u32()?;
+    if r.u8()? == 0 {
+        return Err(invalid("files without grid offsets arent supported"));
+    }
+    //uuid as 36 characters of text
+    r.bytes(36)?;
+    skip_metadata(&mut r)?;
+
+    let grid_count = r.i32()?;
+    let mut found = Vec::new();
+    for _ in 0..grid_count {
+        let unique_name = r.string()?;
+        let grid_type = r.string()?;
+        let instance_parent = r.string()?;
+        let grid_pos = r.i64()?;
+        let _block_pos = r.i64()?;
+        let end_pos = r.i64()?;
+
+        //duplicate names get a suffix after a record separator
+        let name = unique_name.split('\u{1e}').next().unwrap_or("").to_string();
+        let half = grid_type.ends_with("_HalfFloat");
+        let is_float = grid_type.trim_end_matches("_HalfFloat") == "Tree_float_5_4_3";
+        if name == grid_name && is_float {
+            if !instance_parent.is_empty() {
+                return Err(invalid("instanced grids arent supported"));
+            }
+            r.seek(grid_pos)?;
+            return read_openvdb_grid(&mut r, half);
+        }
+
+        found.push(name);
+        r.seek(end_pos)?;
+    }
+
+    Err(missing(grid_name, &found))
+}
+
+//name, type name and the value with its size in front, we dont need any of it
+fn skip_metadata(r: &mut Reader) -> std::io::Result<()> {
+    let count = r.u32()?;
+    for _ in 0..count {
+        r.string()?;
+        r.string()?;
+        let size = r.u32()? as usize;
+        r.bytes(size)?;
+    }
+    Ok(())
+}
+
+//openvdb maps multiply row vectors (p * M), Mat4 wants column vectors so affine matrices get transposed
+fn read_transform(r: &mut Reader) -> std::io::Result<Mat4> {
+    let map_type = r.string()?;
+    Ok(match map_type.as_str() {
+        //after the scale come its voxel size, inverse and a few other cached versions of it
+        "UniformScaleMap" | "ScaleMap" => {
+            let scale = r.vec3()?;
+            r.bytes(4 * 24)?;
+            Mat4::scale(scale)
+        }
+        "UniformScaleTranslateMap" | "ScaleTranslateMap" => {
+            let translation = r.vec3()?;
+            let scale = r.vec3()?;
+            r.bytes(4 * 24)?;
+            Mat4::translate(translation) * Mat4::scale(scale)
+        }
+        "TranslationMap" => Mat4::translate(r.vec3()?),
+        "AffineMap" | "UnitaryMap" => {
+            let mut m = [[0.0; 4]; 4];
+            for row in m.iter_mut() {
+                for v in row.iter_mut() {
+                    *v = r.f64()?;
+                }
+            }
+            Mat4::new(m).transpose()
+        }
+        _ => return Err(invalid(&format!("{} transforms arent supported", map_type))),
+    })
+}
+
+fn read_openvdb_grid(r: &mut Reader, half: bool) -> std::io::Result<VdbGrid> {
+    let compression = r.u32()?;
+    skip_metadata(r)?;
+    let index_to_world = read_transform(r)?;
+
+    //buffer count, always 1
+    r.i32()?;
+    let background = r.f32()?;
+    let mut grid = VdbGrid::new(background, index_to_world)?;
+    let storage = Storage {
+        compression,
+        half,
+        background,
+    };
+
+    //root tiles and values are always full floats, even in half float grids
+    let tile_count = r.u32()?;
+    let child_count = r.u32()?;
+    for _ in 0..tile_count {
+        let origin = r.coord()?;
+        let value = r.f32()?;
+        let active = r.u8()? != 0;
+        grid.add_tile(12, origin, value, active);
+    }
+
+    let mut leaves = Vec::new();
+    for _ in 0..child_count {
+        let origin = r.coord()?;
+        read_internal_topology(r, &storage, &mut grid, 5, origin, &mut leaves)?;
+    }
+
+    let mut values = vec![0.0; 512];
+    for origin in leaves {
+        let active = r.mask(512)?;
+        read_compressed_values(r, &storage, &active, &mut values)?;
+        grid.add_leaf(origin, &values, &active);
+    }
+
+    Ok(grid)
+}
+
+//where entry n of a node with 2^log2_dim entries a side starts, x changes slowest
+fn child_origin(origin: [i32; 3], n: usize, log2_dim: u32, child_log2: u32) -> [i32; 3] {
+    let dim = (1 << log2_dim) - 1;
+    let offset = [n >> (2 * log2_dim), (n >> log2_dim) & dim, n & dim];
+    [0, 1, 2].map(|a| origin[a] + ((offset[a] as i32) << child_log2))
+}
+
+//the upper internal node has 32^3 entries of 128^3 voxels, the lower one 16^3 of 8^3 (leaves)
+//leaves only have their value mask here, their origins are collected for when the values come
+fn read_internal_topology(
+    r: &mut Reader,
+    storage: &Storage,
+    grid: &mut VdbGrid,
+    log2_dim: u32,
+    origin: [i32; 3],
+    leaves: &mut Vec<[i32; 3]>,
+) -> std::io::Result<()> {
+    let size = 1 << (3 * log2_dim);
+    let child_log2 = if log2_dim == 5 { 7 } else { 3 };
+
+    let child_mask = r.mask(size)?;
+    let value_mask = r.mask(size)?;
+    let mut values = vec![0.0; size];
+    read_compressed_values(r, storage, &value_mask, &mut values)?;
+
+    for (n, &value) in values.iter().enumerate() {
+        if !is_on(&child_mask, n) {
+            let tile = child_origin(origin, n, log2_dim, child_log2);
+            grid.add_tile(child_log2, tile, value, is_on(&value_mask, n));
+        }
+    }
+
+    for n in (0..size).filter(|&n| is_on(&child_mask, n)) {
+        let child = child_origin(origin, n, log2_dim, child_log2);
+        if log2_dim == 5 {
+            read_internal_topology(r, storage, grid, 4, child, leaves)?;
+        } else {
+            r.mask(512)?;
+            leaves.push(child);
+        }
+    }
+    Ok(())
+}
+
+//with mask compression only the active values are saved, the inactive ones are the background (or minus it, for level
+//sets) or up to two other values picked by a selection mask. A flag in front says which of those it is
+//https://github.com/AcademySoftwareFoundation/openvdb/blob/master/openvdb/openvdb/io/Compression.h
+fn read_compressed_values(
+    r: &mut Reader,
+    storage: &Storage,
+    value_mask: &[u64],
+    out: &mut [f32],
+) -> std::io::Result<()> {
+    let count = out.len();
+    let metadata = r.u8()?;
+    if metadata > 6 {
+        return Err(invalid("bad value compression flag"));
+    }
+
+    let background = storage.background;
+    let mut inactive = [
+        if metadata == 0 {
+            background
+        } else {
+            -background
+        },
+        background,
+    ];
+    if matches!(metadata, 2 | 4 | 5) {
+        inactive[0] = r.f32()?;
+    }
+    if metadata == 5 {
+        inactive[1] = r.f32()?;
+    }
+    let selection = if matches!(metadata, 3..=5) {
+        r.mask(count)?
+    } else {
+        vec![0; count / 64]
+    };
+
+    let mask_compressed = storage.compression & COMPRESS_ACTIVE_MASK != 0 && metadata != 6;
+    let stored = if mask_compressed {
+        (0..count).filter(|&n| is_on(value_mask, n)).count()
+    } else {
+        count
+    };
+
+    let value_size = if storage.half { 2 } else { 4 };
+    let data = read_data(r, storage.compression, stored * value_size)?;
+    let mut saved = data.chunks_exact(value_size).map(|b| {
+        if storage.half {
+            half_to_f32(u16::from_le_bytes([b[0], b[1]]))
+        } else {
+            f32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        }
+    });
+
+    for (n, v) in out.iter_mut().enumerate() {
+        *v = if !mask_compressed || is_on(value_mask, n) {
+            saved.next().unwrap_or(background)
+        } else if is_on(&selection, n) {
+            inactive[1]
+        } else {
+            inactive[0]
+        };
+    }
+    Ok(())
+}
+
+//a compressed chunk starts with its size, negative means it was stored as it is because compressing didnt help
+fn read_data(r: &mut Reader, compression: u32, size: usize) -> std::io::Result<Vec<u8>> {
+    if compression & (COMPRESS_ZIP | COMPRESS_BLOSC) == 0 {
+        return Ok(r.bytes(size)?.to_vec());
+    }
+
+    let chunk = r.i64()?;
+    if chunk <= 0 {
+        let data = r.bytes(chunk.unsigned_abs() as usize)?;
+        if data.len() != size {
+            return Err(invalid("uncompressed chunk has the wrong size"));
+        }
+        return Ok(data.to_vec());
+    }
+
+    let compressed = r.bytes(chunk as usize)?;
+    let mut data = if compression & COMPRESS_BLOSC != 0 {
+        blosc_decompress(compressed)?
+    } else {
+        zlib_decompress(compressed, size)?
+    };
+    //blosc pads tiny chunks, anything past what we asked for is that
+    if data.len() < size {
+        return Err(invalid("compressed chunk is too small"));
+    }
+    data.truncate(size);
+    Ok(data)
+}
+
+fn zlib_decompress(compressed: &[u8], size: usize) -> std::io::Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(size);
+    ZlibDecoder::new(compressed).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+//blosc 1: a 16 byte header, then the data split in blocks that each got byte shuffled (byte 0 of every value first,
+//then byte 1, ...) and compressed, usually with lz4 which is what openvdb asks for. Each block can also be split in
+//one stream per byte of the value before compressing
+//https://github.com/Blosc/c-blosc/blob/main/README_CHUNK_FORMAT.rst
+fn blosc_decompress(src: &[u8]) -> std::io::Result<Vec<u8>> {
+    let header = src
+        .get(..16)
+        .ok_or_else(|| invalid("blosc chunk too short"))?;
+    let flags = header[2];
+    let typesize = (header[3] as usize).max(1);
+    let u32_at =
+        |b: &[u8], i: usize| u32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]) as usize;
+    let nbytes = u32_at(header, 4);
+    let blocksize = u32_at(header, 8).max(1);
+
+    const DOSHUFFLE: u8 = 0x1;
+    const MEMCPYED: u8 = 0x2;
+    const DOBITSHUFFLE: u8 = 0x4;
+    const DONT_SPLIT: u8 = 0x10;
+
+    if flags & MEMCPYED != 0 {
+        return Ok(src
+            .get(16..16 + nbytes)
+            .ok_or_else(|| invalid("blosc chunk too short"))?
+            .to_vec());
+    }
+    if flags & DOBITSHUFFLE != 0 {
+        return Err(invalid("blosc bit shuffle isnt supported"));
+    }
+    let codec = flags >> 5;
+
+    let block_count = nbytes.div_ceil(blocksize);
+    let mut out = Vec::with_capacity(nbytes);
+    for block in 0..block_count {
+        let start = src
+            .get(16 + 4 * block..20 + 4 * block)
+            .map(|b| u32_at(b, 0))
+            .ok_or_else(|| invalid("blosc chunk too short"))?;
+        let leftover = block == block_count - 1 && nbytes % blocksize != 0;
+        let bsize = if leftover {
+            nbytes % blocksize
+        } else {
+            blocksize
+        };
+
+        //older blosc didnt write the flag and decided from the sizes, newer sets it whenever it didnt split
+        let split =
+            flags & DONT_SPLIT == 0 && !leftover && typesize <= 16 && bsize / typesize >= 128;
+        let splits = if split { typesize } else { 1 };
+        let split_size = bsize / splits;
+
+        let mut pos = start;
+        let mut shuffled = Vec::with_capacity(bsize);
+        for _ in 0..splits {
+            let size = src
+                .get(pos..pos + 4)
+                .map(|b| u32_at(b, 0))
+                .ok_or_else(|| invalid("blosc chunk too short"))?;
+            pos += 4;
+            let data = src
+                .get(pos..pos + size)
+                .ok_or_else(|| invalid("blosc chunk too short"))?;
+            pos += size;
+
+            if size == split_size {
+                shuffled.extend_from_slice(data);
+            } else {
+                let part = match codec {
+                    1 => lz4_decompress(data, split_size),
+                    3 => zlib_decompress(data, split_size).ok(),
+                    _ => return Err(invalid("only lz4 and zlib blosc chunks are supported")),
+                };
+                let part = part
+                    .filter(|p| p.len() == split_size)
+                    .ok_or_else(|| invalid("broken blosc block"))?;
+                shuffled.extend_from_slice(&part);
+            }
+        }
+
+        if flags & DOSHUFFLE != 0 && typesize > 1 {
+            let values = bsize / typesize;
+            let mut block_out = shuffled.clone();
+            for i in 0..values {
+                for b in 0..typesize {
+                    block_out[i * typesize + b] = shuffled[b * values + i];
+                }
+            }
+            out.extend_from_slice(&block_out);
+        } else {
+            out.extend_from_slice(&shuffled);
+        }
+    }
+    Ok(out)
+}
+
+//lz4 block format: a token with the literal count and match length, the literals, then a 2 byte offset back into
+//what was already written to copy the match from. The last sequence is only literals
+//https://github.com/lz4/lz4/blob/dev/doc/lz4_Block_format.md
+fn lz4_decompress(src: &[u8], size: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(size);
+    let mut i = 0;
+
+    let length = |i: &mut usize, mut len: usize| -> Option<usize> {
+        if len == 15 {
+            loop {
+                let b = *src.get(*i)?;
+                *i += 1;
+                len += b as usize;
+                if b != 255 {
+                    break;
+                }
+            }
+        }
+        Some(len)
+    };
+
+    while i < src.len() {
+        let token = src[i];
+        i += 1;
+
+        let literals = length(&mut i, (token >> 4) as usize)?;
+        out.extend_from_slice(src.get(i..i + literals)?);
+        i += literals;
+        if i >= src.len() {
+            break;
+        }
+
+        let offset = u16::from_le_bytes([*src.get(i)?, *src.get(i + 1)?]) as usize;
+        i += 2;
+        if offset == 0 || offset > out.len() {
+            return None;
+        }
+        let len = length(&mut i, (token & 15) as usize)? + 4;
+
+        //the match can run into itself (offset smaller than len), so byte by byte
+        let from = out.len() - offset;
+        for k in 0..len {
+            out.push(out[from + k]);
+        }
+    }
+
+    Some(out)
+}
+
+fn half_to_f32(h: u16) -> f32 {
+    let sign = if h & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((h >> 10) & 0x1f) as i32;
+    let fraction = (h & 0x3ff) as f32;
+
+    sign * match exponent {
+        0 => fraction * 2f32.powi(-24),
+        31 if fraction == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + fraction / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+const NANOVDB_MAGIC: u64 = 0x304244566f6e614e; //"NanoVDB0"
+const NANOVDB_MAGIC_GRID: u64 = 0x314244566f6e614e; //"NanoVDB1"
+const NANOVDB_MAGIC_FILE: u64 = 0x324244566f6e614e; //"NanoVDB2"
+const NANOVDB_MAJOR_VERSION: u32 = 32;
+const NANOVDB_FLOAT: u32 = 1;
+
+//segments of a header, the metadata and name of every grid in it, then the grids. A grid is the exact bytes nanovdb has
+//in memory: the grid struct, the tree struct, then nodes that point at their children with byte offsets
+//https://github.com/AcademySoftwareFoundation/openvdb/blob/master/nanovdb/nanovdb/io/IO.h
+fn parse_nanovdb(bytes: &[u8], grid_name: &str) -> std::io::Result<VdbGrid> {
+    let mut r = Reader { bytes, pos: 0 };
+    let mut found = Vec::new();
+
+    while r.pos < bytes.len() {
+        let magic = r.u64()?;
+        if magic != NANOVDB_MAGIC && magic != NANOVDB_MAGIC_FILE {
+            return Err(invalid("not a nanovdb file"));
+        }
+        let _version = r.u32()?;
+        let grid_count = r.u16()?;
+        let codec = r.u16()?;
+
+        let mut grids = Vec::new();
+        for _ in 0..grid_count {
+            let grid_size = r.u64()? as usize;
+            let file_size = r.u64()? as usize;
+            //name key and voxel count
+            r.bytes(16)?;
+            let grid_type = r.u32()?;
+            //class, world bbox, index bbox, voxel size
+            r.bytes(4 + 48 + 24 + 24)?;
+            let name_size = r.u32()? as usize;
+            //node and tile counts, codec, padding, version
+            r.bytes(16 + 12 + 2 + 2 + 4)?;
+            let name = r.bytes(name_size)?;
+            let name = String::from_utf8_lossy(name)
+                .trim_end_matches('\0')
+                .to_string();
+            grids.push((grid_size, file_size, grid_type, name));
+        }
+
+        for (grid_size, file_size, grid_type, name) in grids {
+            let data = r.bytes(file_size)?;
+            if name != grid_name || grid_type != NANOVDB_FLOAT {
+                found.push(name);
+                continue;
+            }
+
+            let buffer = match codec {
+                0 => data.to_vec(),
+                //the size of the zlib stream, then the stream
+                1 => zlib_decompress(data.get(8..).unwrap_or(&[]), grid_size)?,
+                _ => return Err(invalid("blosc compressed nanovdb files arent supported")),
+            };
+            if buffer.len() < grid_size {
+                return Err(invalid("grid is cut short"));
+            }
+            return read_nanovdb_grid(&buffer);
+        }
+    }
+
+    Err(missing(grid_name, &found))
+}
+
+//byte offsets into the grid, tree and node structs (nanovdb 32.x, float grids)
+const GRID_DATA_SIZE: usize = 672;
+const ROOT_DATA_SIZE: usize = 64;
+const ROOT_TILE_SIZE: usize = 32;
+const UPPER_TABLE: usize = 8256;
+const LOWER_TABLE: usize = 1088;
+const LEAF_MASK: usize = 16;
+const LEAF_VALUES: usize = 96;
+
+fn read_nanovdb_grid(buffer: &[u8]) -> std::io::Result<VdbGrid> {
+    let at = |pos: usize| Reader { bytes: buffer, pos };
+
+    let magic = at(0).u64()?;
+    if magic != NANOVDB_MAGIC && magic != NANOVDB_MAGIC_GRID {
+        return Err(invalid("grid data doesnt start with the magic number"));
+    }
+    let major = at(16).u32()? >> 21;
+    if major != NANOVDB_MAJOR_VERSION {
+        return Err(invalid(&format!(
+            "nanovdb version {} isnt supported, only {}",
+            major, NANOVDB_MAJOR_VERSION
+        )));
+    }
+    if at(636).u32()? != NANOVDB_FLOAT {
+        return Err(invalid("only float grids are supported"));
+    }
+
+    //the map at 296 has float and double versions of the matrix, the doubles start at 384
+    let mut r = at(384);
+    let mut m = [[0.0; 4]; 4];
+    for row in m.iter_mut().take(3) {
+        for v in row.iter_mut().take(3) {
+            *v = r.f64()?;
+        }
+    }
+    //the inverse matrix, then the translation
+    r.bytes(72)?;
+    for row in m.iter_mut().take(3) {
+        row[3] = r.f64()?;
+    }
+    m[3][3] = 1.0;
+
+    //the tree has the offset of the root from itself after the ones of the three node levels
+    let root = GRID_DATA_SIZE as i64 + at(GRID_DATA_SIZE + 24).i64()?;
+    if root < 0 {
+        return Err(invalid("bad root offset"));
+    }
+    let root = root as usize;
+    let table_size = at(root + 24).u32()? as usize;
+    let background = at(root + 28).f32()?;
+    let mut grid = VdbGrid::new(background, Mat4::new(m))?;
+
+    for i in 0..table_size {
+        let mut r = at(root + ROOT_DATA_SIZE + i * ROOT_TILE_SIZE);
+        let key = r.u64()?;
+        let child = r.i64()?;
+        let state = r.u32()?;
+        let value = r.f32()?;
+
+        //21 bits per axis, x highest. The coordinates were shifted as unsigned so they wrap back to negative
+        let bits = |shift: u32| ((((key >> shift) & 0x1f_ffff) << 12) as u32) as i32;
+        let origin = [bits(42), bits(21), bits(0)];
+        if child == 0 {
+            grid.add_tile(12, origin, value, state != 0);
+        } else {
+            let node = (root as i64 + child) as usize;
+            read_nanovdb_internal(buffer, &mut grid, node, 5, origin)?;
+        }
+    }
+
+    Ok(grid)
+}
+
+fn read_nanovdb_internal(
+    buffer: &[u8],
+    grid: &mut VdbGrid,
+    node: usize,
+    log2_dim: u32,
+    origin: [i32; 3],
+) -> std::io::Result<()> {
+    let at = |pos: usize| Reader { bytes: buffer, pos };
+    let size = 1 << (3 * log2_dim);
+    let (child_log2, table) = if log2_dim == 5 {
+        (7, UPPER_TABLE)
+    } else {
+        (3, LOWER_TABLE)
+    };
+
+    let value_mask = at(node + 32).mask(size)?;
+    let child_mask = at(node + 32 + size / 8).mask(size)?;
+
+    //each table entry is a value or a child offset from this node
+    for n in 0..size {
+        let entry = node + table + 8 * n;
+        let child = child_origin(origin, n, log2_dim, child_log2);
+        if !is_on(&child_mask, n) {
+            grid.add_tile(child_log2, child, at(entry).f32()?, is_on(&value_mask, n));
+            continue;
+        }
+
+        let child_node = (node as i64 + at(entry).i64()?) as usize;
+        if log2_dim == 5 {
+            read_nanovdb_internal(buffer, grid, child_node, 4, child)?;
+        } else {
+            let active = at(child_node + LEAF_MASK).mask(512)?;
+            let mut r = at(child_node + LEAF_VALUES);
+            let values = (0..512)
+                .map(|_| r.f32())
+                .collect::<std::io::Result<Vec<f32>>>()?;
+            grid.add_leaf(child, &values, &active);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    //blocks made by the lz4 command line tool (lz4 -9)
+    #[test]
+    fn lz4_blocks() {
+        //3 literals, then a match 3 back that runs into itself for 52 bytes, then the last 5 literals
+        let abc = lz4_decompress(&hex("3f616263030021506263616263"), 60).unwrap();
+        assert_eq!(abc, b"abc".repeat(20));
+
+        //one literal and a match 1 back, its length takes a 255 and another byte
+        let run = lz4_decompress(&hex("1f610100ff14506161616161"), 300).unwrap();
+        assert_eq!(run, vec![b'a'; 300]);
+
+        //45 literals (with an extra length byte), then the sentence again from 45 back
+        let text = lz4_decompress(
+            &hex(
+                "ff1e54686520717569636b2062726f776e20666f78206a756d7073206f76657220746865206c617a7920646f672e202d00145020646f6721",
+            ),
+            89,
+        )
+        .unwrap();
+        assert_eq!(
+            text,
+            b"The quick brown fox jumps over the lazy dog. The quick brown fox jumps over the lazy dog!"
+        );
+    }
+
+    #[test]
+    fn lz4_broken_blocks() {
+        //a match further back than anything written so far
+        assert!(lz4_decompress(&hex("10610500"), 8).is_none());
+        //an offset of 0
+        assert!(lz4_decompress(&hex("10610000"), 8).is_none());
+        //literals past the end of the block
+        assert!(lz4_decompress(&hex("5061"), 5).is_none());
+        //the offset is cut off
+        assert!(lz4_decompress(&hex("106105"), 8).is_none());
+    }
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(half_to_f32(0x0000), 0.0);
+        assert!(half_to_f32(0x8000).is_sign_negative());
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0xc000), -2.0);
+        assert_eq!(half_to_f32(0x3555), 0.33325195);
+        assert_eq!(half_to_f32(0x7bff), 65504.0);
+        //smallest normal, then the smallest and biggest subnormals
+        assert_eq!(half_to_f32(0x0400), 2f32.powi(-14));
+        assert_eq!(half_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(half_to_f32(0x03ff), 1023.0 * 2f32.powi(-24));
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(half_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(half_to_f32(0x7e00).is_nan());
+        assert!(half_to_f32(0xfc01).is_nan());
+    }
+
+    //the grid fixtures/make_vdb_fixtures.py writes: index (i, j, k) is at 0.5 (i, j, k) + (1, 2, 3) in the world
+    fn fixture(name: &str) -> String {
+        format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn value(i: i32, j: i32, k: i32) -> f64 {
+        1.0 + 0.01 * i as f64 + 0.02 * j as f64 + 0.03 * k as f64
+    }
+
+    fn check_fixture(grid: &VdbGrid, tolerance: f32) {
+        for i in -3..5 {
+            for j in 0..3 {
+                for k in 0..2 {
+                    let expected = value(i, j, k) as f32;
+                    let found = grid.value([i, j, k]);
+                    assert!(
+                        (found - expected).abs() <= tolerance,
+                        "({}, {}, {}) is {} not {}",
+                        i,
+                        j,
+                        k,
+                        found,
+                        expected
+                    );
+                }
+            }
+        }
+
+        //inactive voxels in the leaves, then a tile of each size, then nothing at all
+        assert_eq!(grid.value([4, 5, 5]), 0.0);
+        assert_eq!(grid.value([-8, 7, 7]), 0.0);
+        assert_eq!(grid.value([17, 3, 4]), 2.5);
+        assert_eq!(grid.value([200, 5, 5]), 0.75);
+        assert_eq!(grid.value([5000, 1, 1]), 0.5);
+        assert_eq!(grid.value([-300, 0, 0]), 0.0);
+        assert_eq!(grid.value([0, 0, -1]), 0.0);
+
+        //the active voxels go from (-3, 0, 0) to the far corner of the 8^3 tile at (16, 0, 0), one more on each side.
+        //The inactive tiles arent in it
+        let bbox = grid.bounding_box();
+        let expected = [(-1.0, 13.0), (1.5, 6.0), (2.5, 7.0)];
+        for (axis, (min, max)) in expected.into_iter().enumerate() {
+            let found = bbox.axis_interval(axis);
+            assert!((found.min - min).abs() < 1e-3 && (found.max - max).abs() < 1e-3);
+        }
+
+        //on a voxel center it is that voxel, halfway between two it is the average
+        let tolerance = tolerance as f64 + 1e-6;
+        let center = grid.density(Point3::new(2.0, 2.5, 3.5));
+        assert!((center - value(2, 1, 1)).abs() <= tolerance);
+        let between = grid.density(Point3::new(2.25, 2.5, 3.5));
+        assert!((between - 0.5 * (value(2, 1, 1) + value(3, 1, 1))).abs() <= tolerance);
+
+        let leaves_only = AABB::new_point(Point3::new(-0.5, 2.0, 3.0), Point3::new(3.0, 3.0, 3.5));
+        assert!((grid.max_density(&leaves_only) - value(4, 2, 1)).abs() <= tolerance);
+        assert_eq!(grid.max_density(&bbox), 2.5);
+    }
+
+    #[test]
+    fn openvdb_zip() {
+        check_fixture(&load_vdb(&fixture("tiny.vdb"), "density").unwrap(), 0.0);
+    }
+
+    #[test]
+    fn openvdb_blosc_half() {
+        check_fixture(
+            &load_vdb(&fixture("tiny_blosc.vdb"), "density").unwrap(),
+            1e-3,
+        );
+    }
+
+    #[test]
+    fn nanovdb_zip() {
+        check_fixture(&load_vdb(&fixture("tiny.nvdb"), "density").unwrap(), 0.0);
+    }
+
+    #[test]
+    fn grids_are_found_by_name() {
+        for name in ["tiny.vdb", "tiny.nvdb"] {
+            let other = load_vdb(&fixture(name), "other").unwrap();
+            assert!(other.bounding_box().is_empty());
+
+            let err = load_vdb(&fixture(name), "temperature").err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+            assert!(err.to_string().contains("other, density"), "{}", err);
+        }
+    }
+}
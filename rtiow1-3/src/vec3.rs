@@ -118,6 +118,21 @@ impl Vec3 {
         Vec3::new(x, y, z)
     }
 
+    //same idea as random_cosine_dir but the lobe is cos^n, higher exponents squeeze the directions towards z
+    //pdf is (n + 1) / (2pi) * cos^n, so n = 1 gives back the cosine distribution
+    pub fn random_phong_dir(exponent: f64) -> Vec3 {
+        Vec3::phong_dir(exponent, [random_double(), random_double()])
+    }
+
+    //same lobe from two given uniform numbers, for the bsdf sampling that passes its own u in
+    pub fn phong_dir(exponent: f64, u: [f64; 2]) -> Vec3 {
+        let phi = 2.0 * PI * u[0];
+        let z = u[1].powf(1.0 / (exponent + 1.0));
+        let sin_theta = f64::sqrt(1.0 - z * z);
+
+        Vec3::new(f64::cos(phi) * sin_theta, f64::sin(phi) * sin_theta, z)
+    }
+
     //Project vector v into n, here it is important that the direction of v is negated as it points into the surface, and we want positice numbers
     //Then to get the vector multipy that by the normal and at this amount twice to the v vector that goes into the surface to get the reflected vector
     pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {